@@ -1,56 +1,264 @@
 extern crate regex;
+extern crate unicode_normalization;
 
 use regex::Regex;
 use std::char;
+use std::iter;
+use unicode_normalization::UnicodeNormalization;
 
 // Case folding a single code point can give up to this many code points.
 const MAX_FOLDED_CODE_POINTS: usize = 3;
 
-fn main() {
-    let mut lines = include_str!("../../CaseFolding.txt").lines();
+struct CaseFolding {
+    version: (u64, u64, u64),
+    full: Vec<(char, Vec<char>)>,
+    simple: Vec<(char, char)>,
+    turkic: Vec<(char, Vec<char>)>,
+}
+
+fn parse(text: &str) -> CaseFolding {
+    let mut lines = text.lines();
     let first_line = lines.next().unwrap();
     let version_regex = Regex::new(r"^# CaseFolding-(\d+)\.(\d+)\.(\d+).txt$").unwrap();
     let unicode_version = &version_regex.captures(first_line).unwrap();
-    let (major, minor, patch): (u64, u64, u64) = (
+    let version = (
         unicode_version[1].parse().unwrap(),
         unicode_version[2].parse().unwrap(),
         unicode_version[3].parse().unwrap(),
     );
-    print!(
-        "pub const UNICODE_VERSION: (u64, u64, u64) = ({}, {}, {});\n",
-        major, minor, patch
-    );
-    print!("pub const CASE_FOLDING_TABLE: &'static [(char, [char; 3])] = &[\n");
 
-    // Entry with C (common case folding) or F (full case folding) status
-    let c_or_f_entry = Regex::new(r"^([0-9A-F]+); [CF]; ([0-9A-F ]+);").unwrap();
+    // Entry with C (common), F (full), S (simple) or T (Turkic) status
+    let entry = Regex::new(r"^([0-9A-F]+); ([CFST]); ([0-9A-F ]+);").unwrap();
 
+    let mut full = Vec::new();
+    let mut simple = Vec::new();
+    let mut turkic = Vec::new();
     for line in lines {
-        if let Some(captures) = c_or_f_entry.captures(line) {
-            let from = &captures[1];
-            let to = captures[2]
-                .split(' ')
-                .map(hex_to_escaped)
-                .collect::<Vec<_>>();
+        if let Some(captures) = entry.captures(line) {
+            let from = hex_to_char(&captures[1]);
+            let to = captures[3].split(' ').map(hex_to_char).collect::<Vec<_>>();
             assert!(to.len() <= MAX_FOLDED_CODE_POINTS);
-            let blanks = MAX_FOLDED_CODE_POINTS - to.len();
-            let mut to = to.into_iter();
-            let first_to = to.next().unwrap();
-            print!("  ('{}', ['{}'", hex_to_escaped(from), first_to);
-            for c in to {
-                print!(", '{}'", c);
+            match &captures[2] {
+                "C" => {
+                    simple.push((from, to[0]));
+                    full.push((from, to));
+                }
+                "F" => full.push((from, to)),
+                "S" => {
+                    assert!(to.len() == 1);
+                    simple.push((from, to[0]));
+                }
+                "T" => turkic.push((from, to)),
+                _ => {}
             }
-            for _ in 0..blanks {
-                print!(", '\\0'");
+        }
+    }
+    CaseFolding {
+        version,
+        full,
+        simple,
+        turkic,
+    }
+}
+
+fn main() {
+    let CaseFolding {
+        version: (major, minor, patch),
+        full,
+        simple,
+        turkic,
+    } = parse(include_str!("../../CaseFolding.txt"));
+    println!(
+        "pub const UNICODE_VERSION: (u64, u64, u64) = ({}, {}, {});",
+        major, minor, patch
+    );
+
+    print_table("CASE_FOLDING_TABLE", &full);
+    print_perfect_hash(&full);
+    print_compact_tables(&full);
+    print_ascii_table(&full);
+    print_table("TURKIC_CASE_FOLDING_TABLE", &turkic);
+
+    println!("pub const SIMPLE_CASE_FOLDING_TABLE: &[(char, char)] = &[");
+    for (from, to) in &simple {
+        println!("  ('{}', '{}'),", escape(*from), escape(*to));
+    }
+    println!("];");
+
+    // Inverse of the single-character full foldings, sorted by folded character.
+    let mut inverse = full
+        .iter()
+        .filter(|&(_, to)| to.len() == 1)
+        .map(|&(from, ref to)| (to[0], from))
+        .collect::<Vec<_>>();
+    inverse.sort();
+    println!("pub const INVERSE_CASE_FOLDING_TABLE: &[(char, char)] = &[");
+    for (to, from) in inverse {
+        println!("  ('{}', '{}'),", escape(to), escape(from));
+    }
+    println!("];");
+
+    // U+0345 COMBINING GREEK YPOGEGRAMMENI and characters with it
+    // in their canonical decomposition need NFD before case folding
+    // for canonical caseless matching.
+    println!("pub const NFD_BEFORE_FOLDING: &[char] = &[");
+    for c in (0..=char::MAX as u32).filter_map(char::from_u32) {
+        if iter::once(c).nfd().any(|d| d == '\u{345}') {
+            println!("  '{}',", escape(c));
+        }
+    }
+    println!("];");
+
+    // Older versions, for callers that need stable results.
+    let v10 = parse(include_str!("../../CaseFolding-10.0.0.txt"));
+    assert_eq!(v10.version, (10, 0, 0));
+    println!("#[cfg(feature = \"unicode-10\")]");
+    print_table("CASE_FOLDING_TABLE_10_0_0", &v10.full);
+}
+
+fn print_table(name: &str, entries: &[(char, Vec<char>)]) {
+    println!("pub const {}: &[(char, [char; 3])] = &[", name);
+    for (from, to) in entries {
+        let blanks = MAX_FOLDED_CODE_POINTS - to.len();
+        let mut to = to.iter();
+        let first_to = to.next().unwrap();
+        print!("  ('{}', ['{}'", escape(*from), escape(*first_to));
+        for &c in to {
+            print!(", '{}'", escape(c));
+        }
+        for _ in 0..blanks {
+            print!(", '\\0'");
+        }
+        println!("]),");
+    }
+    println!("];");
+}
+
+/// Print salts and indices into CASE_FOLDING_TABLE for a perfect hash function,
+/// using the same construction as unicode-normalization: the first hash picks
+/// a salt, and the salted hash picks a slot holding the index of the entry.
+fn print_perfect_hash(entries: &[(char, Vec<char>)]) {
+    let n = entries.len();
+    let mut buckets = vec![Vec::new(); n];
+    for (index, &(key, _)) in entries.iter().enumerate() {
+        buckets[phf_hash(key as u32, 0, n)].push(index);
+    }
+    let mut order = (0..n).collect::<Vec<_>>();
+    order.sort_by_key(|&bucket| std::cmp::Reverse(buckets[bucket].len()));
+
+    let mut salts = vec![0u16; n];
+    let mut slots = vec![None; n];
+    for bucket in order {
+        let indices = &buckets[bucket];
+        if indices.is_empty() {
+            continue;
+        }
+        'salts: for salt in 1..=u16::MAX {
+            let mut claimed = Vec::new();
+            for &index in indices {
+                let slot = phf_hash(entries[index].0 as u32, salt as u32, n);
+                if slots[slot].is_some() || claimed.contains(&slot) {
+                    continue 'salts;
+                }
+                claimed.push(slot);
+            }
+            for (&index, slot) in indices.iter().zip(claimed) {
+                slots[slot] = Some(index as u16);
             }
-            print!("]),\n");
+            salts[bucket] = salt;
+            break;
         }
+        assert!(salts[bucket] != 0, "no salt found");
     }
-    print!("];\n");
+
+    println!("#[cfg(not(feature = \"compact-tables\"))]");
+    println!("pub const CASE_FOLDING_SALTS: &[u16] = &[");
+    for salt in salts {
+        println!("  {},", salt);
+    }
+    println!("];");
+    println!("#[cfg(not(feature = \"compact-tables\"))]");
+    println!("pub const CASE_FOLDING_INDICES: &[u16] = &[");
+    for slot in slots {
+        println!("  {},", slot.unwrap());
+    }
+    println!("];");
 }
 
-fn hex_to_escaped(hex: &str) -> String {
+/// Print the full foldings in a smaller form, for the compact-tables feature:
+/// foldings to several characters as a table, and foldings to one character
+/// as runs of `(start, length, stride, delta)` where `length` characters from
+/// `start`, `stride` apart, fold to themselves plus `delta`.
+fn print_compact_tables(entries: &[(char, Vec<char>)]) {
+    let expansions = entries
+        .iter()
+        .filter(|&(_, to)| to.len() > 1)
+        .cloned()
+        .collect::<Vec<_>>();
+    println!("#[cfg(feature = \"compact-tables\")]");
+    print_table("CASE_FOLDING_EXPANSIONS", &expansions);
+
+    let mut runs: Vec<(char, u8, u8, i32)> = Vec::new();
+    for &(from, ref to) in entries.iter().filter(|&(_, to)| to.len() == 1) {
+        let delta = to[0] as i32 - from as i32;
+        if let Some(run) = runs.last_mut() {
+            let last = run.0 as u32 + (run.1 as u32 - 1) * run.2 as u32;
+            let step = from as u32 - last;
+            if run.3 == delta
+                && run.1 < u8::MAX
+                && (step == run.2 as u32 || run.1 == 1 && step == 2)
+            {
+                run.1 += 1;
+                run.2 = step as u8;
+                continue;
+            }
+        }
+        runs.push((from, 1, 1, delta));
+    }
+    println!("#[cfg(feature = \"compact-tables\")]");
+    println!("pub const CASE_FOLDING_RUNS: &[(char, u8, u8, i32)] = &[");
+    for (start, length, stride, delta) in runs {
+        println!(
+            "  ('{}', {}, {}, {}),",
+            escape(start),
+            length,
+            stride,
+            delta
+        );
+    }
+    println!("];");
+}
+
+/// Print the folding of each ASCII character as a byte,
+/// for lookups without searching `CASE_FOLDING_TABLE`.
+fn print_ascii_table(entries: &[(char, Vec<char>)]) {
+    println!("pub const ASCII_CASE_FOLDING_TABLE: [u8; 128] = [");
+    for b in 0..0x80u8 {
+        let folded = match entries.iter().find(|&&(from, _)| from == b as char) {
+            Some((_, to)) => {
+                assert!(to.len() == 1 && to[0].is_ascii());
+                to[0] as u8
+            }
+            None => b,
+        };
+        println!("  {},", folded);
+    }
+    println!("];");
+}
+
+// Must be kept in sync with phf_hash in src/lib.rs
+fn phf_hash(key: u32, salt: u32, n: usize) -> usize {
+    let y = key.wrapping_add(salt).wrapping_mul(2654435769);
+    let y = y ^ key.wrapping_mul(0x31415926);
+    (((y as u64) * (n as u64)) >> 32) as usize
+}
+
+fn hex_to_char(hex: &str) -> char {
     let c = u32::from_str_radix(hex, 16).unwrap();
     assert!(c != 0);
-    char::from_u32(c).unwrap().escape_default().collect()
+    char::from_u32(c).unwrap()
+}
+
+fn escape(c: char) -> String {
+    c.escape_default().collect()
 }
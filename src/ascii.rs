@@ -0,0 +1,110 @@
+//! Default case folding that handles runs of ASCII 16 bytes at a time,
+//! with SSE2 on x86_64 and a scalar loop elsewhere.
+
+use alloc::string::String;
+use core::convert::TryFrom;
+use core::str;
+
+use super::Caseless;
+
+const BLOCK: usize = 16;
+
+/// Append the default case folding of `s` to `out`.
+pub fn case_fold_into(mut s: &str, out: &mut String) {
+    out.reserve(s.len());
+    while !s.is_empty() {
+        let ascii = fold_ascii_prefix(s.as_bytes(), out);
+        s = &s[ascii..];
+        let non_ascii = s.find(|c: char| c.is_ascii()).unwrap_or(s.len());
+        out.extend(s[..non_ascii].chars().default_case_fold());
+        s = &s[non_ascii..];
+    }
+}
+
+/// Append the folding of the longest ASCII prefix of `bytes` to `out`,
+/// and return its length.
+fn fold_ascii_prefix(bytes: &[u8], out: &mut String) -> usize {
+    let mut folded = [0; BLOCK];
+    let mut i = 0;
+    while let Some(block) = bytes.get(i..i + BLOCK) {
+        let block = <&[u8; BLOCK]>::try_from(block).unwrap();
+        if !fold_block(block, &mut folded) {
+            break;
+        }
+        out.push_str(str::from_utf8(&folded).unwrap());
+        i += BLOCK;
+    }
+    for &b in &bytes[i..] {
+        if !b.is_ascii() {
+            break;
+        }
+        out.push(b.to_ascii_lowercase() as char);
+        i += 1;
+    }
+    i
+}
+
+/// Lowercase `block` into `folded` and return true if it is all ASCII,
+/// or return false.
+#[cfg(target_arch = "x86_64")]
+fn fold_block(block: &[u8; BLOCK], folded: &mut [u8; BLOCK]) -> bool {
+    use core::arch::x86_64::*;
+
+    // SAFETY: SSE2 is always available on x86_64,
+    // and both pointers are valid for 16 unaligned bytes.
+    unsafe {
+        let v = _mm_loadu_si128(block.as_ptr() as *const __m128i);
+        if _mm_movemask_epi8(v) != 0 {
+            return false;
+        }
+        // All bytes are below 0x80, so signed comparisons work.
+        let upper = _mm_and_si128(
+            _mm_cmpgt_epi8(v, _mm_set1_epi8(b'A' as i8 - 1)),
+            _mm_cmplt_epi8(v, _mm_set1_epi8(b'Z' as i8 + 1)),
+        );
+        let lowered = _mm_or_si128(v, _mm_and_si128(upper, _mm_set1_epi8(0x20)));
+        _mm_storeu_si128(folded.as_mut_ptr() as *mut __m128i, lowered);
+    }
+    true
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn fold_block(block: &[u8; BLOCK], folded: &mut [u8; BLOCK]) -> bool {
+    if !block.is_ascii() {
+        return false;
+    }
+    for (f, b) in folded.iter_mut().zip(block) {
+        *f = b.to_ascii_lowercase();
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fold(s: &str) -> String {
+        let mut out = String::new();
+        case_fold_into(s, &mut out);
+        out
+    }
+
+    #[test]
+    fn test_fold_matches_scalar() {
+        let strings = [
+            "",
+            "Hello",
+            "The Quick Brown Fox Jumps Over The Lazy Dog, 0123456789 @[`{",
+            "ABCDEFGHIJKLMNOPQRSTUVWXYZ straße ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+            "ŉŉŉŉŉŉŉŉŉŉŉŉŉŉŉŉŉŉŉŉ",
+            "0123456789ABCDEFĞ0123456789ABCDEFİ",
+        ];
+        for s in &strings {
+            assert_eq!(fold(s), s.chars().default_case_fold().collect::<String>());
+        }
+        for b in 0..0x80u8 {
+            let s = String::from(b as char).repeat(40);
+            assert_eq!(fold(&s), s.to_ascii_lowercase());
+        }
+    }
+}
@@ -1,5 +1,5 @@
 pub const UNICODE_VERSION: (u64, u64, u64) = (16, 0, 0);
-pub const CASE_FOLDING_TABLE: &'static [(char, [char; 3])] = &[
+pub const CASE_FOLDING_TABLE: &[(char, [char; 3])] = &[
   ('A', ['a', '\0', '\0']),
   ('B', ['b', '\0', '\0']),
   ('C', ['c', '\0', '\0']),
@@ -1558,3 +1558,7978 @@ pub const CASE_FOLDING_TABLE: &'static [(char, [char; 3])] = &[
   ('\u{1e920}', ['\u{1e942}', '\0', '\0']),
   ('\u{1e921}', ['\u{1e943}', '\0', '\0']),
 ];
+#[cfg(not(feature = "compact-tables"))]
+pub const CASE_FOLDING_SALTS: &[u16] = &[
+  0,
+  2,
+  1,
+  13,
+  0,
+  3,
+  2,
+  0,
+  0,
+  1,
+  1,
+  4,
+  1,
+  2,
+  0,
+  1,
+  1,
+  1,
+  2,
+  1,
+  0,
+  2,
+  3,
+  1,
+  0,
+  7,
+  1,
+  0,
+  2,
+  0,
+  0,
+  2,
+  2,
+  4,
+  0,
+  8,
+  2,
+  7,
+  0,
+  1,
+  0,
+  3,
+  0,
+  1,
+  0,
+  1,
+  0,
+  3,
+  1,
+  0,
+  2,
+  1,
+  0,
+  7,
+  1,
+  1,
+  0,
+  0,
+  4,
+  1,
+  3,
+  3,
+  5,
+  2,
+  14,
+  0,
+  0,
+  2,
+  5,
+  0,
+  0,
+  0,
+  2,
+  0,
+  0,
+  1,
+  0,
+  9,
+  0,
+  1,
+  0,
+  0,
+  0,
+  0,
+  2,
+  3,
+  1,
+  2,
+  0,
+  1,
+  0,
+  0,
+  1,
+  1,
+  1,
+  3,
+  7,
+  2,
+  1,
+  5,
+  2,
+  1,
+  0,
+  3,
+  0,
+  3,
+  0,
+  2,
+  1,
+  3,
+  0,
+  1,
+  1,
+  3,
+  0,
+  0,
+  0,
+  1,
+  1,
+  1,
+  1,
+  1,
+  0,
+  0,
+  0,
+  4,
+  1,
+  1,
+  1,
+  1,
+  1,
+  0,
+  1,
+  0,
+  2,
+  0,
+  1,
+  4,
+  0,
+  2,
+  0,
+  3,
+  2,
+  0,
+  1,
+  2,
+  5,
+  4,
+  1,
+  4,
+  0,
+  0,
+  8,
+  4,
+  0,
+  2,
+  1,
+  0,
+  2,
+  1,
+  2,
+  1,
+  5,
+  1,
+  3,
+  1,
+  0,
+  0,
+  0,
+  1,
+  5,
+  0,
+  4,
+  0,
+  0,
+  1,
+  2,
+  6,
+  1,
+  3,
+  0,
+  2,
+  0,
+  0,
+  0,
+  4,
+  0,
+  1,
+  1,
+  3,
+  8,
+  1,
+  1,
+  1,
+  4,
+  5,
+  2,
+  0,
+  2,
+  1,
+  6,
+  0,
+  0,
+  0,
+  1,
+  2,
+  1,
+  0,
+  1,
+  0,
+  0,
+  1,
+  0,
+  2,
+  3,
+  2,
+  3,
+  0,
+  0,
+  0,
+  2,
+  2,
+  2,
+  1,
+  2,
+  0,
+  1,
+  5,
+  0,
+  0,
+  0,
+  9,
+  1,
+  0,
+  0,
+  1,
+  1,
+  7,
+  1,
+  2,
+  0,
+  4,
+  0,
+  3,
+  1,
+  0,
+  1,
+  1,
+  2,
+  0,
+  0,
+  0,
+  0,
+  0,
+  1,
+  5,
+  2,
+  0,
+  0,
+  0,
+  6,
+  6,
+  1,
+  0,
+  1,
+  0,
+  1,
+  0,
+  0,
+  2,
+  2,
+  1,
+  2,
+  2,
+  0,
+  4,
+  0,
+  1,
+  0,
+  1,
+  3,
+  7,
+  0,
+  3,
+  0,
+  5,
+  8,
+  0,
+  0,
+  1,
+  0,
+  0,
+  0,
+  0,
+  1,
+  1,
+  1,
+  3,
+  0,
+  1,
+  3,
+  0,
+  6,
+  0,
+  3,
+  0,
+  0,
+  7,
+  0,
+  0,
+  3,
+  0,
+  1,
+  0,
+  2,
+  1,
+  0,
+  1,
+  3,
+  0,
+  2,
+  2,
+  2,
+  1,
+  0,
+  0,
+  2,
+  0,
+  0,
+  0,
+  2,
+  1,
+  0,
+  1,
+  1,
+  4,
+  1,
+  0,
+  1,
+  5,
+  4,
+  1,
+  0,
+  0,
+  1,
+  5,
+  2,
+  1,
+  4,
+  6,
+  0,
+  0,
+  0,
+  2,
+  0,
+  1,
+  3,
+  1,
+  10,
+  9,
+  0,
+  2,
+  1,
+  0,
+  0,
+  3,
+  5,
+  0,
+  0,
+  1,
+  8,
+  3,
+  0,
+  0,
+  1,
+  2,
+  8,
+  0,
+  2,
+  0,
+  0,
+  0,
+  3,
+  11,
+  1,
+  1,
+  0,
+  1,
+  0,
+  1,
+  3,
+  0,
+  0,
+  0,
+  1,
+  0,
+  3,
+  1,
+  1,
+  2,
+  1,
+  7,
+  4,
+  0,
+  5,
+  1,
+  0,
+  0,
+  1,
+  0,
+  1,
+  0,
+  0,
+  0,
+  2,
+  2,
+  0,
+  3,
+  1,
+  3,
+  3,
+  3,
+  3,
+  8,
+  0,
+  2,
+  2,
+  1,
+  2,
+  1,
+  5,
+  0,
+  2,
+  1,
+  0,
+  0,
+  3,
+  1,
+  1,
+  2,
+  1,
+  1,
+  0,
+  1,
+  2,
+  1,
+  5,
+  0,
+  2,
+  5,
+  0,
+  4,
+  1,
+  6,
+  3,
+  1,
+  0,
+  0,
+  2,
+  2,
+  1,
+  2,
+  0,
+  0,
+  1,
+  4,
+  4,
+  0,
+  2,
+  0,
+  1,
+  0,
+  0,
+  2,
+  7,
+  0,
+  1,
+  3,
+  3,
+  0,
+  0,
+  2,
+  2,
+  0,
+  1,
+  0,
+  6,
+  7,
+  0,
+  2,
+  0,
+  1,
+  1,
+  1,
+  0,
+  0,
+  2,
+  1,
+  1,
+  0,
+  3,
+  0,
+  7,
+  3,
+  0,
+  0,
+  1,
+  0,
+  0,
+  1,
+  1,
+  2,
+  0,
+  1,
+  0,
+  0,
+  0,
+  1,
+  0,
+  1,
+  5,
+  2,
+  1,
+  1,
+  0,
+  0,
+  0,
+  1,
+  0,
+  1,
+  1,
+  3,
+  0,
+  1,
+  0,
+  0,
+  2,
+  1,
+  1,
+  3,
+  5,
+  1,
+  0,
+  0,
+  2,
+  0,
+  3,
+  0,
+  2,
+  1,
+  1,
+  3,
+  1,
+  0,
+  0,
+  17,
+  6,
+  2,
+  6,
+  0,
+  18,
+  6,
+  2,
+  1,
+  13,
+  0,
+  1,
+  0,
+  1,
+  2,
+  0,
+  0,
+  0,
+  2,
+  10,
+  2,
+  1,
+  0,
+  0,
+  2,
+  3,
+  5,
+  4,
+  0,
+  2,
+  0,
+  6,
+  3,
+  1,
+  0,
+  0,
+  5,
+  17,
+  2,
+  4,
+  0,
+  8,
+  5,
+  1,
+  0,
+  4,
+  0,
+  5,
+  7,
+  0,
+  2,
+  0,
+  3,
+  12,
+  0,
+  0,
+  7,
+  0,
+  2,
+  2,
+  5,
+  4,
+  12,
+  0,
+  0,
+  0,
+  0,
+  0,
+  3,
+  1,
+  1,
+  1,
+  2,
+  3,
+  0,
+  0,
+  0,
+  0,
+  4,
+  4,
+  0,
+  12,
+  4,
+  5,
+  8,
+  0,
+  2,
+  4,
+  0,
+  3,
+  2,
+  1,
+  0,
+  1,
+  0,
+  8,
+  1,
+  0,
+  9,
+  0,
+  0,
+  2,
+  2,
+  4,
+  0,
+  1,
+  0,
+  1,
+  0,
+  1,
+  3,
+  6,
+  3,
+  0,
+  6,
+  1,
+  2,
+  3,
+  5,
+  0,
+  5,
+  15,
+  4,
+  1,
+  13,
+  5,
+  9,
+  1,
+  0,
+  0,
+  2,
+  0,
+  0,
+  0,
+  3,
+  1,
+  0,
+  1,
+  1,
+  5,
+  0,
+  0,
+  17,
+  7,
+  13,
+  0,
+  6,
+  1,
+  0,
+  1,
+  5,
+  9,
+  0,
+  1,
+  1,
+  11,
+  11,
+  0,
+  0,
+  0,
+  1,
+  3,
+  2,
+  1,
+  0,
+  3,
+  7,
+  2,
+  0,
+  0,
+  4,
+  0,
+  15,
+  2,
+  0,
+  10,
+  2,
+  4,
+  1,
+  0,
+  2,
+  0,
+  0,
+  4,
+  1,
+  0,
+  1,
+  1,
+  9,
+  1,
+  1,
+  1,
+  3,
+  0,
+  5,
+  0,
+  2,
+  1,
+  39,
+  2,
+  1,
+  2,
+  3,
+  0,
+  0,
+  1,
+  1,
+  1,
+  9,
+  9,
+  5,
+  14,
+  0,
+  0,
+  2,
+  1,
+  0,
+  7,
+  3,
+  5,
+  2,
+  0,
+  0,
+  5,
+  5,
+  0,
+  5,
+  2,
+  0,
+  2,
+  0,
+  0,
+  0,
+  3,
+  2,
+  8,
+  0,
+  0,
+  4,
+  1,
+  16,
+  2,
+  0,
+  0,
+  8,
+  4,
+  3,
+  0,
+  2,
+  8,
+  4,
+  0,
+  0,
+  0,
+  3,
+  10,
+  1,
+  4,
+  0,
+  5,
+  0,
+  2,
+  0,
+  0,
+  0,
+  6,
+  7,
+  2,
+  4,
+  0,
+  0,
+  2,
+  4,
+  1,
+  2,
+  5,
+  6,
+  0,
+  0,
+  6,
+  1,
+  1,
+  4,
+  0,
+  5,
+  4,
+  0,
+  7,
+  0,
+  5,
+  1,
+  0,
+  1,
+  0,
+  2,
+  0,
+  10,
+  4,
+  3,
+  0,
+  3,
+  2,
+  0,
+  6,
+  0,
+  2,
+  0,
+  17,
+  2,
+  2,
+  0,
+  8,
+  1,
+  0,
+  0,
+  0,
+  2,
+  0,
+  0,
+  1,
+  0,
+  0,
+  0,
+  6,
+  0,
+  0,
+  7,
+  3,
+  3,
+  3,
+  3,
+  3,
+  0,
+  2,
+  2,
+  0,
+  18,
+  0,
+  1,
+  5,
+  15,
+  4,
+  4,
+  0,
+  0,
+  3,
+  3,
+  1,
+  0,
+  0,
+  2,
+  2,
+  0,
+  3,
+  2,
+  1,
+  13,
+  2,
+  0,
+  0,
+  0,
+  0,
+  4,
+  1,
+  0,
+  0,
+  0,
+  15,
+  9,
+  11,
+  2,
+  0,
+  2,
+  4,
+  1,
+  0,
+  0,
+  0,
+  1,
+  2,
+  6,
+  0,
+  2,
+  0,
+  2,
+  0,
+  4,
+  1,
+  0,
+  0,
+  0,
+  0,
+  0,
+  1,
+  0,
+  0,
+  1,
+  2,
+  0,
+  0,
+  2,
+  0,
+  2,
+  0,
+  8,
+  3,
+  1,
+  5,
+  0,
+  18,
+  1,
+  0,
+  5,
+  4,
+  0,
+  1,
+  1,
+  0,
+  0,
+  21,
+  9,
+  1,
+  5,
+  1,
+  1,
+  0,
+  8,
+  1,
+  0,
+  9,
+  3,
+  0,
+  3,
+  0,
+  2,
+  0,
+  6,
+  3,
+  7,
+  5,
+  0,
+  1,
+  2,
+  1,
+  0,
+  1,
+  12,
+  3,
+  3,
+  0,
+  0,
+  2,
+  9,
+  0,
+  1,
+  6,
+  4,
+  18,
+  0,
+  2,
+  1,
+  20,
+  17,
+  0,
+  1,
+  5,
+  10,
+  2,
+  1,
+  0,
+  11,
+  0,
+  0,
+  2,
+  0,
+  1,
+  5,
+  0,
+  0,
+  8,
+  15,
+  3,
+  10,
+  0,
+  5,
+  0,
+  6,
+  0,
+  0,
+  0,
+  1,
+  1,
+  0,
+  0,
+  3,
+  1,
+  6,
+  0,
+  0,
+  1,
+  2,
+  0,
+  3,
+  7,
+  0,
+  4,
+  1,
+  2,
+  6,
+  0,
+  0,
+  0,
+  2,
+  3,
+  4,
+  3,
+  2,
+  2,
+  1,
+  17,
+  2,
+  4,
+  2,
+  23,
+  0,
+  3,
+  6,
+  10,
+  1,
+  9,
+  0,
+  7,
+  6,
+  0,
+  4,
+  0,
+  9,
+  1,
+  3,
+  0,
+  10,
+  2,
+  9,
+  0,
+  4,
+  1,
+  8,
+  1,
+  0,
+  1,
+  0,
+  1,
+  20,
+  4,
+  26,
+  1,
+  9,
+  0,
+  0,
+  0,
+  0,
+  1,
+  0,
+  0,
+  2,
+  0,
+  7,
+  0,
+  6,
+  10,
+  18,
+  0,
+  0,
+  0,
+  9,
+  0,
+  10,
+  1,
+  11,
+  1,
+  2,
+  0,
+  7,
+  0,
+  5,
+  2,
+  6,
+  0,
+  4,
+  0,
+  8,
+  1,
+  9,
+  0,
+  4,
+  0,
+  0,
+  9,
+  12,
+  8,
+  8,
+  8,
+  0,
+  2,
+  1,
+  5,
+  0,
+  8,
+  2,
+  0,
+  9,
+  1,
+  2,
+  9,
+  0,
+  0,
+  0,
+  1,
+  6,
+  2,
+  0,
+  0,
+  1,
+  0,
+  0,
+  2,
+  0,
+  0,
+  0,
+  0,
+  0,
+  1,
+  1,
+  1,
+  0,
+  0,
+  1,
+  7,
+  1,
+  35,
+  1,
+  1,
+  4,
+  38,
+  16,
+  0,
+  3,
+  11,
+  1,
+  4,
+  14,
+  5,
+  4,
+  0,
+  2,
+  2,
+  16,
+  0,
+  4,
+  0,
+  5,
+  0,
+  1,
+  39,
+  1,
+  0,
+  0,
+  0,
+  5,
+  0,
+  0,
+  15,
+  12,
+  2,
+  0,
+  1,
+  11,
+  7,
+  0,
+  3,
+  1,
+  0,
+  5,
+  10,
+  1,
+  0,
+  15,
+  29,
+  23,
+  1,
+  4,
+  0,
+  0,
+  0,
+  0,
+  1,
+  26,
+  3,
+  10,
+  2,
+  0,
+  1,
+  1,
+  4,
+  0,
+  4,
+  15,
+  14,
+  5,
+  3,
+  3,
+  38,
+  2,
+  2,
+  0,
+  16,
+  0,
+  0,
+  0,
+  1,
+  2,
+  18,
+  17,
+  2,
+  35,
+  0,
+  4,
+  16,
+  0,
+  0,
+  3,
+  5,
+  9,
+  4,
+  5,
+  1,
+  4,
+  0,
+  13,
+  2,
+  4,
+  2,
+  3,
+  0,
+  0,
+  15,
+  11,
+  0,
+  2,
+  11,
+  16,
+  0,
+  3,
+  0,
+  6,
+  0,
+  6,
+  4,
+  1,
+  28,
+  0,
+  8,
+  46,
+  0,
+  7,
+  31,
+  0,
+  0,
+  0,
+  0,
+  9,
+  13,
+  20,
+  3,
+  1,
+  4,
+  28,
+  0,
+  3,
+  0,
+  13,
+  0,
+  0,
+  5,
+  23,
+  1,
+  0,
+  0,
+  7,
+  0,
+  6,
+  3,
+  34,
+  2,
+  0,
+  7,
+  0,
+  1,
+  13,
+  13,
+  42,
+  0,
+  1,
+  6,
+  3,
+  0,
+  0,
+  20,
+  3,
+  1,
+  4,
+  0,
+  0,
+  6,
+  16,
+  0,
+  6,
+  11,
+  0,
+  5,
+  3,
+  2,
+  3,
+  10,
+  9,
+  0,
+  20,
+  6,
+  16,
+  0,
+  0,
+  7,
+  2,
+  38,
+  9,
+  18,
+  16,
+  3,
+  0,
+  0,
+  0,
+  0,
+  0,
+  2,
+  11,
+  9,
+  39,
+  0,
+  1,
+  2,
+  21,
+  0,
+  0,
+  14,
+  51,
+  7,
+  53,
+  18,
+  0,
+  8,
+  54,
+  6,
+  19,
+  0,
+  4,
+  2,
+  35,
+  1,
+  22,
+  5,
+  0,
+  0,
+  16,
+  0,
+  16,
+  6,
+  8,
+  16,
+  3,
+  9,
+  1,
+  22,
+  14,
+  6,
+  82,
+  0,
+  35,
+  0,
+  0,
+  1,
+  43,
+  0,
+  0,
+  0,
+  39,
+  0,
+  5,
+  0,
+  54,
+  144,
+  0,
+  0,
+  5,
+  63,
+  0,
+  94,
+  0,
+  1,
+  7,
+  46,
+  0,
+  60,
+  0,
+  0,
+  0,
+  1,
+  71,
+  1,
+  7,
+  2,
+  2,
+  12,
+  0,
+  9,
+  0,
+  2,
+  0,
+  48,
+  24,
+  2,
+  17,
+  0,
+  17,
+  99,
+  0,
+  5,
+  7,
+  35,
+  0,
+  0,
+  5,
+  13,
+  5,
+  123,
+  0,
+  40,
+  4,
+  11,
+  15,
+  7,
+  0,
+  191,
+  9,
+  0,
+  30,
+  80,
+  2,
+  0,
+  0,
+  20,
+  48,
+  0,
+  0,
+  2,
+  0,
+  0,
+  0,
+  0,
+  9,
+  2,
+  34,
+  31,
+  182,
+  0,
+  99,
+  35,
+  0,
+  53,
+  443,
+  0,
+  0,
+  14,
+  3,
+  414,
+  8,
+  1,
+  0,
+  0,
+  220,
+  1,
+  385,
+  2,
+  139,
+  725,
+  11,
+  0,
+  0,
+  0,
+  8,
+  1,
+  3,
+  2763,
+];
+#[cfg(not(feature = "compact-tables"))]
+pub const CASE_FOLDING_INDICES: &[u16] = &[
+  1103,
+  1403,
+  1298,
+  430,
+  580,
+  43,
+  1200,
+  709,
+  616,
+  1401,
+  1230,
+  1377,
+  539,
+  1003,
+  151,
+  884,
+  429,
+  955,
+  809,
+  940,
+  1162,
+  270,
+  12,
+  931,
+  1355,
+  208,
+  878,
+  262,
+  356,
+  113,
+  1203,
+  1291,
+  1408,
+  1207,
+  1289,
+  1373,
+  694,
+  584,
+  881,
+  933,
+  829,
+  41,
+  1270,
+  514,
+  893,
+  254,
+  469,
+  892,
+  1527,
+  1486,
+  5,
+  1024,
+  956,
+  1084,
+  416,
+  757,
+  456,
+  216,
+  891,
+  30,
+  1169,
+  1448,
+  1184,
+  591,
+  608,
+  702,
+  60,
+  95,
+  1113,
+  793,
+  705,
+  1081,
+  536,
+  360,
+  1423,
+  1541,
+  1453,
+  1350,
+  974,
+  1396,
+  1167,
+  390,
+  141,
+  1077,
+  1476,
+  976,
+  1482,
+  1020,
+  1325,
+  188,
+  834,
+  1319,
+  1407,
+  159,
+  938,
+  384,
+  1233,
+  1114,
+  777,
+  1522,
+  500,
+  39,
+  1237,
+  1242,
+  1045,
+  755,
+  796,
+  873,
+  495,
+  1367,
+  470,
+  1360,
+  69,
+  1139,
+  347,
+  605,
+  1112,
+  379,
+  432,
+  1144,
+  862,
+  1186,
+  116,
+  290,
+  1141,
+  1297,
+  438,
+  1406,
+  778,
+  1397,
+  1273,
+  771,
+  1228,
+  874,
+  315,
+  1052,
+  1027,
+  1011,
+  68,
+  810,
+  102,
+  253,
+  740,
+  1472,
+  222,
+  405,
+  471,
+  1072,
+  1025,
+  361,
+  945,
+  947,
+  656,
+  338,
+  107,
+  751,
+  1069,
+  1460,
+  818,
+  856,
+  42,
+  1498,
+  979,
+  120,
+  801,
+  91,
+  1079,
+  164,
+  1276,
+  926,
+  1193,
+  160,
+  996,
+  173,
+  326,
+  624,
+  522,
+  1262,
+  97,
+  305,
+  789,
+  593,
+  1316,
+  574,
+  563,
+  729,
+  1535,
+  1201,
+  324,
+  437,
+  820,
+  1330,
+  1256,
+  1006,
+  575,
+  150,
+  882,
+  1288,
+  994,
+  650,
+  1180,
+  1426,
+  261,
+  904,
+  332,
+  1343,
+  40,
+  780,
+  592,
+  1049,
+  587,
+  928,
+  133,
+  688,
+  350,
+  1326,
+  1337,
+  248,
+  103,
+  693,
+  1380,
+  1531,
+  714,
+  784,
+  1479,
+  214,
+  1359,
+  507,
+  420,
+  1340,
+  920,
+  1032,
+  617,
+  1217,
+  998,
+  813,
+  516,
+  1496,
+  1494,
+  296,
+  424,
+  1512,
+  1548,
+  578,
+  1155,
+  659,
+  306,
+  1399,
+  86,
+  1542,
+  603,
+  314,
+  961,
+  744,
+  190,
+  1254,
+  987,
+  523,
+  47,
+  1300,
+  1546,
+  632,
+  1394,
+  1202,
+  373,
+  754,
+  1348,
+  333,
+  498,
+  110,
+  1108,
+  1034,
+  637,
+  191,
+  1281,
+  201,
+  924,
+  691,
+  1098,
+  325,
+  824,
+  1454,
+  825,
+  989,
+  1501,
+  788,
+  530,
+  511,
+  534,
+  1323,
+  472,
+  1503,
+  1400,
+  1312,
+  194,
+  753,
+  1505,
+  505,
+  549,
+  20,
+  1028,
+  1434,
+  206,
+  1283,
+  1371,
+  954,
+  1140,
+  1125,
+  1477,
+  567,
+  686,
+  37,
+  84,
+  1515,
+  1252,
+  1517,
+  1134,
+  970,
+  695,
+  985,
+  762,
+  100,
+  610,
+  852,
+  576,
+  898,
+  1150,
+  1302,
+  1409,
+  380,
+  582,
+  1168,
+  701,
+  465,
+  249,
+  213,
+  750,
+  411,
+  848,
+  51,
+  1353,
+  806,
+  785,
+  1376,
+  901,
+  497,
+  4,
+  613,
+  78,
+  1181,
+  1416,
+  1047,
+  1437,
+  418,
+  1405,
+  1089,
+  366,
+  397,
+  1106,
+  1552,
+  868,
+  1250,
+  975,
+  1191,
+  1247,
+  537,
+  34,
+  1358,
+  782,
+  787,
+  658,
+  450,
+  271,
+  1449,
+  907,
+  1128,
+  1284,
+  376,
+  489,
+  1441,
+  463,
+  109,
+  504,
+  1090,
+  108,
+  589,
+  804,
+  1199,
+  1091,
+  1172,
+  1335,
+  872,
+  660,
+  814,
+  1178,
+  1320,
+  439,
+  766,
+  209,
+  317,
+  61,
+  130,
+  1393,
+  1463,
+  35,
+  454,
+  1099,
+  1446,
+  799,
+  372,
+  519,
+  699,
+  1131,
+  756,
+  342,
+  779,
+  506,
+  633,
+  1313,
+  807,
+  215,
+  1158,
+  912,
+  155,
+  503,
+  1488,
+  1012,
+  408,
+  139,
+  481,
+  250,
+  1523,
+  1475,
+  1345,
+  1123,
+  1461,
+  219,
+  344,
+  244,
+  286,
+  96,
+  1519,
+  1339,
+  486,
+  551,
+  368,
+  259,
+  1124,
+  458,
+  492,
+  1239,
+  474,
+  278,
+  665,
+  533,
+  743,
+  1041,
+  919,
+  877,
+  232,
+  447,
+  1499,
+  348,
+  1259,
+  672,
+  1147,
+  428,
+  1428,
+  1271,
+  855,
+  865,
+  126,
+  524,
+  1544,
+  138,
+  218,
+  728,
+  185,
+  726,
+  1357,
+  815,
+  21,
+  1382,
+  913,
+  1424,
+  399,
+  704,
+  715,
+  1130,
+  1269,
+  647,
+  1351,
+  538,
+  626,
+  845,
+  718,
+  1251,
+  44,
+  1549,
+  440,
+  1018,
+  986,
+  981,
+  1143,
+  1059,
+  235,
+  666,
+  1002,
+  1075,
+  883,
+  59,
+  1177,
+  675,
+  197,
+  364,
+  1520,
+  615,
+  805,
+  706,
+  496,
+  162,
+  822,
+  321,
+  808,
+  663,
+  1015,
+  1236,
+  1058,
+  513,
+  1470,
+  55,
+  345,
+  1102,
+  725,
+  571,
+  134,
+  1280,
+  1301,
+  121,
+  914,
+  623,
+  394,
+  1268,
+  303,
+  1318,
+  525,
+  291,
+  161,
+  343,
+  546,
+  566,
+  10,
+  54,
+  70,
+  152,
+  1023,
+  1347,
+  309,
+  234,
+  973,
+  1117,
+  972,
+  1116,
+  143,
+  951,
+  604,
+  823,
+  1506,
+  668,
+  1008,
+  657,
+  835,
+  1537,
+  1226,
+  1352,
+  1096,
+  758,
+  1044,
+  1451,
+  1222,
+  960,
+  716,
+  263,
+  499,
+  229,
+  977,
+  300,
+  863,
+  983,
+  880,
+  1067,
+  1110,
+  742,
+  733,
+  678,
+  860,
+  527,
+  826,
+  1555,
+  607,
+  1516,
+  485,
+  1082,
+  1235,
+  526,
+  1066,
+  1321,
+  449,
+  1188,
+  1308,
+  1411,
+  802,
+  967,
+  26,
+  56,
+  565,
+  1388,
+  752,
+  459,
+  1111,
+  64,
+  199,
+  200,
+  1255,
+  349,
+  1293,
+  602,
+  351,
+  601,
+  847,
+  1344,
+  67,
+  319,
+  281,
+  457,
+  1415,
+  304,
+  434,
+  811,
+  147,
+  124,
+  1192,
+  255,
+  606,
+  1333,
+  643,
+  453,
+  346,
+  1504,
+  1551,
+  1208,
+  854,
+  969,
+  902,
+  1165,
+  310,
+  15,
+  435,
+  1074,
+  1459,
+  104,
+  992,
+  480,
+  1425,
+  634,
+  1017,
+  352,
+  358,
+  1196,
+  101,
+  1159,
+  403,
+  331,
+  284,
+  1398,
+  1285,
+  1071,
+  1171,
+  1534,
+  638,
+  1243,
+  256,
+  993,
+  670,
+  287,
+  13,
+  329,
+  741,
+  1214,
+  106,
+  294,
+  1467,
+  1266,
+  23,
+  721,
+  922,
+  866,
+  1304,
+  1554,
+  876,
+  1447,
+  1445,
+  1420,
+  1132,
+  1031,
+  140,
+  579,
+  667,
+  369,
+  990,
+  1364,
+  1524,
+  1500,
+  385,
+  696,
+  927,
+  49,
+  460,
+  738,
+  1414,
+  1404,
+  1493,
+  31,
+  630,
+  1410,
+  167,
+  1509,
+  276,
+  1218,
+  111,
+  1176,
+  795,
+  312,
+  285,
+  840,
+  577,
+  1354,
+  378,
+  1511,
+  1213,
+  1389,
+  734,
+  115,
+  357,
+  258,
+  45,
+  681,
+  381,
+  1240,
+  493,
+  225,
+  36,
+  260,
+  1514,
+  32,
+  1492,
+  1379,
+  1051,
+  558,
+  1174,
+  905,
+  1153,
+  1471,
+  446,
+  207,
+  145,
+  679,
+  127,
+  731,
+  594,
+  236,
+  1305,
+  297,
+  984,
+  76,
+  939,
+  75,
+  620,
+  448,
+  24,
+  625,
+  1315,
+  1368,
+  772,
+  149,
+  52,
+  1392,
+  1366,
+  484,
+  245,
+  320,
+  1543,
+  1413,
+  844,
+  1528,
+  128,
+  1391,
+  628,
+  553,
+  850,
+  157,
+  38,
+  33,
+  1457,
+  1122,
+  464,
+  723,
+  210,
+  857,
+  192,
+  978,
+  1464,
+  48,
+  1014,
+  1436,
+  419,
+  1279,
+  1043,
+  749,
+  1126,
+  618,
+  1210,
+  1550,
+  708,
+  114,
+  72,
+  487,
+  1458,
+  1253,
+  573,
+  1497,
+  635,
+  1107,
+  1381,
+  1390,
+  561,
+  831,
+  431,
+  1342,
+  1127,
+  1462,
+  1395,
+  1480,
+  268,
+  572,
+  677,
+  99,
+  798,
+  732,
+  203,
+  950,
+  959,
+  283,
+  221,
+  402,
+  1078,
+  1375,
+  1227,
+  17,
+  186,
+  946,
+  1054,
+  1206,
+  1329,
+  1261,
+  1138,
+  1507,
+  639,
+  1046,
+  597,
+  1478,
+  838,
+  1173,
+  570,
+  478,
+  63,
+  28,
+  932,
+  277,
+  177,
+  1518,
+  375,
+  224,
+  171,
+  652,
+  196,
+  949,
+  1022,
+  1295,
+  1209,
+  581,
+  861,
+  837,
+  1249,
+  422,
+  136,
+  1282,
+  763,
+  488,
+  1502,
+  117,
+  1146,
+  1331,
+  995,
+  443,
+  58,
+  776,
+  1030,
+  640,
+  274,
+  1324,
+  1299,
+  515,
+  764,
+  781,
+  542,
+  1040,
+  711,
+  318,
+  1430,
+  794,
+  1187,
+  1135,
+  1440,
+  1290,
+  1115,
+  156,
+  1296,
+  710,
+  712,
+  622,
+  886,
+  1334,
+  1100,
+  1384,
+  651,
+  968,
+  1338,
+  690,
+  468,
+  269,
+  875,
+  1442,
+  436,
+  1443,
+  1039,
+  889,
+  761,
+  792,
+  386,
+  680,
+  518,
+  683,
+  79,
+  1278,
+  1455,
+  1004,
+  483,
+  1133,
+  389,
+  175,
+  531,
+  1232,
+  1539,
+  1238,
+  1029,
+  14,
+  655,
+  92,
+  265,
+  595,
+  382,
+  105,
+  383,
+  204,
+  918,
+  176,
+  569,
+  476,
+  19,
+  1311,
+  1194,
+  1468,
+  295,
+  371,
+  521,
+  724,
+  783,
+  1272,
+  327,
+  1129,
+  340,
+  629,
+  74,
+  1104,
+  367,
+  168,
+  687,
+  172,
+  272,
+  183,
+  1185,
+  1526,
+  1452,
+  89,
+  568,
+  1161,
+  1365,
+  1062,
+  774,
+  293,
+  775,
+  1418,
+  502,
+  273,
+  645,
+  2,
+  1083,
+  1183,
+  1491,
+  1356,
+  900,
+  1378,
+  118,
+  1248,
+  611,
+  735,
+  586,
+  540,
+  1152,
+  182,
+  393,
+  915,
+  962,
+  1170,
+  1016,
+  146,
+  925,
+  226,
+  698,
+  1421,
+  508,
+  1275,
+  11,
+  1362,
+  547,
+  1327,
+  843,
+  1265,
+  413,
+  193,
+  641,
+  1540,
+  906,
+  337,
+  790,
+  1037,
+  642,
+  963,
+  887,
+  1456,
+  425,
+  609,
+  62,
+  1080,
+  427,
+  851,
+  745,
+  280,
+  991,
+  888,
+  246,
+  334,
+  1489,
+  1149,
+  1484,
+  1429,
+  510,
+  944,
+  1061,
+  3,
+  1529,
+  433,
+  412,
+  1160,
+  646,
+  1053,
+  1525,
+  842,
+  894,
+  410,
+  621,
+  163,
+  1361,
+  241,
+  1064,
+  25,
+  1042,
+  830,
+  890,
+  1310,
+  769,
+  600,
+  1245,
+  1294,
+  697,
+  908,
+  588,
+  1370,
+  211,
+  832,
+  27,
+  929,
+  53,
+  1163,
+  316,
+  895,
+  8,
+  748,
+  737,
+  122,
+  583,
+  562,
+  1553,
+  1438,
+  841,
+  1005,
+  400,
+  1119,
+  442,
+  997,
+  359,
+  228,
+  187,
+  398,
+  83,
+  477,
+  1419,
+  1481,
+  828,
+  717,
+  816,
+  941,
+  552,
+  911,
+  612,
+  1402,
+  1085,
+  237,
+  354,
+  479,
+  1332,
+  596,
+  689,
+  1510,
+  768,
+  1093,
+  1286,
+  387,
+  388,
+  1545,
+  174,
+  93,
+  417,
+  94,
+  80,
+  195,
+  1050,
+  1556,
+  404,
+  257,
+  441,
+  685,
+  77,
+  1033,
+  322,
+  1260,
+  46,
+  1225,
+  644,
+  1223,
+  953,
+  767,
+  1363,
+  279,
+  833,
+  948,
+  90,
+  355,
+  220,
+  1435,
+  966,
+  135,
+  198,
+  154,
+  9,
+  664,
+  247,
+  550,
+  1530,
+  1026,
+  1190,
+  1431,
+  923,
+  1485,
+  885,
+  746,
+  230,
+  879,
+  461,
+  1466,
+  1010,
+  1386,
+  289,
+  7,
+  982,
+  739,
+  1474,
+  1057,
+  1076,
+  1521,
+  451,
+  1056,
+  936,
+  1109,
+  1007,
+  676,
+  971,
+  158,
+  362,
+  800,
+  669,
+  952,
+  299,
+  1246,
+  426,
+  170,
+  910,
+  786,
+  365,
+  671,
+  1257,
+  1038,
+  673,
+  308,
+  636,
+  528,
+  1536,
+  125,
+  1433,
+  301,
+  1490,
+  1287,
+  282,
+  1019,
+  1263,
+  819,
+  1241,
+  1204,
+  1547,
+  1314,
+  189,
+  1035,
+  180,
+  302,
+  1120,
+  238,
+  421,
+  311,
+  719,
+  1156,
+  251,
+  370,
+  509,
+  559,
+  166,
+  227,
+  1303,
+  490,
+  445,
+  1211,
+  965,
+  1036,
+  288,
+  1021,
+  178,
+  1422,
+  455,
+  391,
+  674,
+  1175,
+  1292,
+  363,
+  964,
+  223,
+  85,
+  512,
+  184,
+  217,
+  1412,
+  467,
+  1068,
+  544,
+  462,
+  6,
+  1216,
+  1013,
+  1212,
+  87,
+  1179,
+  896,
+  614,
+  29,
+  1157,
+  1097,
+  1092,
+  205,
+  532,
+  1328,
+  1432,
+  682,
+  165,
+  1487,
+  406,
+  556,
+  957,
+  119,
+  692,
+  1,
+  541,
+  88,
+  713,
+  491,
+  1000,
+  202,
+  336,
+  864,
+  585,
+  1105,
+  1307,
+  1439,
+  1309,
+  414,
+  943,
+  653,
+  1198,
+  71,
+  1349,
+  1533,
+  903,
+  909,
+  22,
+  16,
+  18,
+  727,
+  548,
+  1197,
+  1387,
+  123,
+  1088,
+  1322,
+  239,
+  494,
+  409,
+  1205,
+  131,
+  112,
+  543,
+  264,
+  1087,
+  181,
+  1145,
+  654,
+  899,
+  1086,
+  242,
+  396,
+  415,
+  307,
+  98,
+  560,
+  231,
+  1483,
+  870,
+  821,
+  1195,
+  999,
+  1346,
+  392,
+  1267,
+  1244,
+  897,
+  1063,
+  988,
+  452,
+  266,
+  661,
+  1538,
+  1234,
+  1070,
+  466,
+  736,
+  1215,
+  1048,
+  1219,
+  545,
+  554,
+  846,
+  148,
+  930,
+  252,
+  65,
+  853,
+  1465,
+  817,
+  1136,
+  730,
+  0,
+  57,
+  313,
+  1473,
+  1306,
+  142,
+  1142,
+  958,
+  869,
+  169,
+  700,
+  836,
+  1148,
+  747,
+  942,
+  1118,
+  649,
+  1060,
+  275,
+  1450,
+  66,
+  1532,
+  1317,
+  839,
+  137,
+  760,
+  555,
+  1513,
+  1383,
+  482,
+  377,
+  934,
+  1055,
+  335,
+  921,
+  599,
+  1495,
+  50,
+  703,
+  1444,
+  1137,
+  401,
+  341,
+  240,
+  1374,
+  374,
+  803,
+  662,
+  1189,
+  917,
+  212,
+  1341,
+  328,
+  1221,
+  1121,
+  1101,
+  407,
+  132,
+  631,
+  323,
+  1220,
+  520,
+  1277,
+  1336,
+  564,
+  444,
+  797,
+  720,
+  1231,
+  517,
+  1372,
+  773,
+  1182,
+  423,
+  707,
+  1001,
+  858,
+  330,
+  827,
+  1264,
+  937,
+  1154,
+  1385,
+  153,
+  1417,
+  298,
+  765,
+  648,
+  353,
+  179,
+  1369,
+  529,
+  129,
+  722,
+  243,
+  849,
+  791,
+  1258,
+  627,
+  73,
+  1274,
+  619,
+  1229,
+  812,
+  590,
+  935,
+  233,
+  395,
+  557,
+  81,
+  1073,
+  598,
+  1427,
+  980,
+  770,
+  1469,
+  1065,
+  339,
+  501,
+  1166,
+  1224,
+  1009,
+  867,
+  871,
+  82,
+  684,
+  292,
+  1151,
+  1095,
+  916,
+  1094,
+  473,
+  859,
+  1508,
+  475,
+  1164,
+  267,
+  759,
+  144,
+  535,
+];
+#[cfg(feature = "compact-tables")]
+pub const CASE_FOLDING_EXPANSIONS: &[(char, [char; 3])] = &[
+  ('\u{df}', ['s', 's', '\0']),
+  ('\u{130}', ['i', '\u{307}', '\0']),
+  ('\u{149}', ['\u{2bc}', 'n', '\0']),
+  ('\u{1f0}', ['j', '\u{30c}', '\0']),
+  ('\u{390}', ['\u{3b9}', '\u{308}', '\u{301}']),
+  ('\u{3b0}', ['\u{3c5}', '\u{308}', '\u{301}']),
+  ('\u{587}', ['\u{565}', '\u{582}', '\0']),
+  ('\u{1e96}', ['h', '\u{331}', '\0']),
+  ('\u{1e97}', ['t', '\u{308}', '\0']),
+  ('\u{1e98}', ['w', '\u{30a}', '\0']),
+  ('\u{1e99}', ['y', '\u{30a}', '\0']),
+  ('\u{1e9a}', ['a', '\u{2be}', '\0']),
+  ('\u{1e9e}', ['s', 's', '\0']),
+  ('\u{1f50}', ['\u{3c5}', '\u{313}', '\0']),
+  ('\u{1f52}', ['\u{3c5}', '\u{313}', '\u{300}']),
+  ('\u{1f54}', ['\u{3c5}', '\u{313}', '\u{301}']),
+  ('\u{1f56}', ['\u{3c5}', '\u{313}', '\u{342}']),
+  ('\u{1f80}', ['\u{1f00}', '\u{3b9}', '\0']),
+  ('\u{1f81}', ['\u{1f01}', '\u{3b9}', '\0']),
+  ('\u{1f82}', ['\u{1f02}', '\u{3b9}', '\0']),
+  ('\u{1f83}', ['\u{1f03}', '\u{3b9}', '\0']),
+  ('\u{1f84}', ['\u{1f04}', '\u{3b9}', '\0']),
+  ('\u{1f85}', ['\u{1f05}', '\u{3b9}', '\0']),
+  ('\u{1f86}', ['\u{1f06}', '\u{3b9}', '\0']),
+  ('\u{1f87}', ['\u{1f07}', '\u{3b9}', '\0']),
+  ('\u{1f88}', ['\u{1f00}', '\u{3b9}', '\0']),
+  ('\u{1f89}', ['\u{1f01}', '\u{3b9}', '\0']),
+  ('\u{1f8a}', ['\u{1f02}', '\u{3b9}', '\0']),
+  ('\u{1f8b}', ['\u{1f03}', '\u{3b9}', '\0']),
+  ('\u{1f8c}', ['\u{1f04}', '\u{3b9}', '\0']),
+  ('\u{1f8d}', ['\u{1f05}', '\u{3b9}', '\0']),
+  ('\u{1f8e}', ['\u{1f06}', '\u{3b9}', '\0']),
+  ('\u{1f8f}', ['\u{1f07}', '\u{3b9}', '\0']),
+  ('\u{1f90}', ['\u{1f20}', '\u{3b9}', '\0']),
+  ('\u{1f91}', ['\u{1f21}', '\u{3b9}', '\0']),
+  ('\u{1f92}', ['\u{1f22}', '\u{3b9}', '\0']),
+  ('\u{1f93}', ['\u{1f23}', '\u{3b9}', '\0']),
+  ('\u{1f94}', ['\u{1f24}', '\u{3b9}', '\0']),
+  ('\u{1f95}', ['\u{1f25}', '\u{3b9}', '\0']),
+  ('\u{1f96}', ['\u{1f26}', '\u{3b9}', '\0']),
+  ('\u{1f97}', ['\u{1f27}', '\u{3b9}', '\0']),
+  ('\u{1f98}', ['\u{1f20}', '\u{3b9}', '\0']),
+  ('\u{1f99}', ['\u{1f21}', '\u{3b9}', '\0']),
+  ('\u{1f9a}', ['\u{1f22}', '\u{3b9}', '\0']),
+  ('\u{1f9b}', ['\u{1f23}', '\u{3b9}', '\0']),
+  ('\u{1f9c}', ['\u{1f24}', '\u{3b9}', '\0']),
+  ('\u{1f9d}', ['\u{1f25}', '\u{3b9}', '\0']),
+  ('\u{1f9e}', ['\u{1f26}', '\u{3b9}', '\0']),
+  ('\u{1f9f}', ['\u{1f27}', '\u{3b9}', '\0']),
+  ('\u{1fa0}', ['\u{1f60}', '\u{3b9}', '\0']),
+  ('\u{1fa1}', ['\u{1f61}', '\u{3b9}', '\0']),
+  ('\u{1fa2}', ['\u{1f62}', '\u{3b9}', '\0']),
+  ('\u{1fa3}', ['\u{1f63}', '\u{3b9}', '\0']),
+  ('\u{1fa4}', ['\u{1f64}', '\u{3b9}', '\0']),
+  ('\u{1fa5}', ['\u{1f65}', '\u{3b9}', '\0']),
+  ('\u{1fa6}', ['\u{1f66}', '\u{3b9}', '\0']),
+  ('\u{1fa7}', ['\u{1f67}', '\u{3b9}', '\0']),
+  ('\u{1fa8}', ['\u{1f60}', '\u{3b9}', '\0']),
+  ('\u{1fa9}', ['\u{1f61}', '\u{3b9}', '\0']),
+  ('\u{1faa}', ['\u{1f62}', '\u{3b9}', '\0']),
+  ('\u{1fab}', ['\u{1f63}', '\u{3b9}', '\0']),
+  ('\u{1fac}', ['\u{1f64}', '\u{3b9}', '\0']),
+  ('\u{1fad}', ['\u{1f65}', '\u{3b9}', '\0']),
+  ('\u{1fae}', ['\u{1f66}', '\u{3b9}', '\0']),
+  ('\u{1faf}', ['\u{1f67}', '\u{3b9}', '\0']),
+  ('\u{1fb2}', ['\u{1f70}', '\u{3b9}', '\0']),
+  ('\u{1fb3}', ['\u{3b1}', '\u{3b9}', '\0']),
+  ('\u{1fb4}', ['\u{3ac}', '\u{3b9}', '\0']),
+  ('\u{1fb6}', ['\u{3b1}', '\u{342}', '\0']),
+  ('\u{1fb7}', ['\u{3b1}', '\u{342}', '\u{3b9}']),
+  ('\u{1fbc}', ['\u{3b1}', '\u{3b9}', '\0']),
+  ('\u{1fc2}', ['\u{1f74}', '\u{3b9}', '\0']),
+  ('\u{1fc3}', ['\u{3b7}', '\u{3b9}', '\0']),
+  ('\u{1fc4}', ['\u{3ae}', '\u{3b9}', '\0']),
+  ('\u{1fc6}', ['\u{3b7}', '\u{342}', '\0']),
+  ('\u{1fc7}', ['\u{3b7}', '\u{342}', '\u{3b9}']),
+  ('\u{1fcc}', ['\u{3b7}', '\u{3b9}', '\0']),
+  ('\u{1fd2}', ['\u{3b9}', '\u{308}', '\u{300}']),
+  ('\u{1fd3}', ['\u{3b9}', '\u{308}', '\u{301}']),
+  ('\u{1fd6}', ['\u{3b9}', '\u{342}', '\0']),
+  ('\u{1fd7}', ['\u{3b9}', '\u{308}', '\u{342}']),
+  ('\u{1fe2}', ['\u{3c5}', '\u{308}', '\u{300}']),
+  ('\u{1fe3}', ['\u{3c5}', '\u{308}', '\u{301}']),
+  ('\u{1fe4}', ['\u{3c1}', '\u{313}', '\0']),
+  ('\u{1fe6}', ['\u{3c5}', '\u{342}', '\0']),
+  ('\u{1fe7}', ['\u{3c5}', '\u{308}', '\u{342}']),
+  ('\u{1ff2}', ['\u{1f7c}', '\u{3b9}', '\0']),
+  ('\u{1ff3}', ['\u{3c9}', '\u{3b9}', '\0']),
+  ('\u{1ff4}', ['\u{3ce}', '\u{3b9}', '\0']),
+  ('\u{1ff6}', ['\u{3c9}', '\u{342}', '\0']),
+  ('\u{1ff7}', ['\u{3c9}', '\u{342}', '\u{3b9}']),
+  ('\u{1ffc}', ['\u{3c9}', '\u{3b9}', '\0']),
+  ('\u{fb00}', ['f', 'f', '\0']),
+  ('\u{fb01}', ['f', 'i', '\0']),
+  ('\u{fb02}', ['f', 'l', '\0']),
+  ('\u{fb03}', ['f', 'f', 'i']),
+  ('\u{fb04}', ['f', 'f', 'l']),
+  ('\u{fb05}', ['s', 't', '\0']),
+  ('\u{fb06}', ['s', 't', '\0']),
+  ('\u{fb13}', ['\u{574}', '\u{576}', '\0']),
+  ('\u{fb14}', ['\u{574}', '\u{565}', '\0']),
+  ('\u{fb15}', ['\u{574}', '\u{56b}', '\0']),
+  ('\u{fb16}', ['\u{57e}', '\u{576}', '\0']),
+  ('\u{fb17}', ['\u{574}', '\u{56d}', '\0']),
+];
+#[cfg(feature = "compact-tables")]
+pub const CASE_FOLDING_RUNS: &[(char, u8, u8, i32)] = &[
+  ('A', 26, 1, 32),
+  ('\u{b5}', 1, 1, 775),
+  ('\u{c0}', 23, 1, 32),
+  ('\u{d8}', 7, 1, 32),
+  ('\u{100}', 24, 2, 1),
+  ('\u{132}', 3, 2, 1),
+  ('\u{139}', 8, 2, 1),
+  ('\u{14a}', 23, 2, 1),
+  ('\u{178}', 1, 1, -121),
+  ('\u{179}', 3, 2, 1),
+  ('\u{17f}', 1, 1, -268),
+  ('\u{181}', 1, 1, 210),
+  ('\u{182}', 2, 2, 1),
+  ('\u{186}', 1, 1, 206),
+  ('\u{187}', 1, 1, 1),
+  ('\u{189}', 2, 1, 205),
+  ('\u{18b}', 1, 1, 1),
+  ('\u{18e}', 1, 1, 79),
+  ('\u{18f}', 1, 1, 202),
+  ('\u{190}', 1, 1, 203),
+  ('\u{191}', 1, 1, 1),
+  ('\u{193}', 1, 1, 205),
+  ('\u{194}', 1, 1, 207),
+  ('\u{196}', 1, 1, 211),
+  ('\u{197}', 1, 1, 209),
+  ('\u{198}', 1, 1, 1),
+  ('\u{19c}', 1, 1, 211),
+  ('\u{19d}', 1, 1, 213),
+  ('\u{19f}', 1, 1, 214),
+  ('\u{1a0}', 3, 2, 1),
+  ('\u{1a6}', 1, 1, 218),
+  ('\u{1a7}', 1, 1, 1),
+  ('\u{1a9}', 1, 1, 218),
+  ('\u{1ac}', 1, 1, 1),
+  ('\u{1ae}', 1, 1, 218),
+  ('\u{1af}', 1, 1, 1),
+  ('\u{1b1}', 2, 1, 217),
+  ('\u{1b3}', 2, 2, 1),
+  ('\u{1b7}', 1, 1, 219),
+  ('\u{1b8}', 1, 1, 1),
+  ('\u{1bc}', 1, 1, 1),
+  ('\u{1c4}', 1, 1, 2),
+  ('\u{1c5}', 1, 1, 1),
+  ('\u{1c7}', 1, 1, 2),
+  ('\u{1c8}', 1, 1, 1),
+  ('\u{1ca}', 1, 1, 2),
+  ('\u{1cb}', 9, 2, 1),
+  ('\u{1de}', 9, 2, 1),
+  ('\u{1f1}', 1, 1, 2),
+  ('\u{1f2}', 2, 2, 1),
+  ('\u{1f6}', 1, 1, -97),
+  ('\u{1f7}', 1, 1, -56),
+  ('\u{1f8}', 20, 2, 1),
+  ('\u{220}', 1, 1, -130),
+  ('\u{222}', 9, 2, 1),
+  ('\u{23a}', 1, 1, 10795),
+  ('\u{23b}', 1, 1, 1),
+  ('\u{23d}', 1, 1, -163),
+  ('\u{23e}', 1, 1, 10792),
+  ('\u{241}', 1, 1, 1),
+  ('\u{243}', 1, 1, -195),
+  ('\u{244}', 1, 1, 69),
+  ('\u{245}', 1, 1, 71),
+  ('\u{246}', 5, 2, 1),
+  ('\u{345}', 1, 1, 116),
+  ('\u{370}', 2, 2, 1),
+  ('\u{376}', 1, 1, 1),
+  ('\u{37f}', 1, 1, 116),
+  ('\u{386}', 1, 1, 38),
+  ('\u{388}', 3, 1, 37),
+  ('\u{38c}', 1, 1, 64),
+  ('\u{38e}', 2, 1, 63),
+  ('\u{391}', 17, 1, 32),
+  ('\u{3a3}', 9, 1, 32),
+  ('\u{3c2}', 1, 1, 1),
+  ('\u{3cf}', 1, 1, 8),
+  ('\u{3d0}', 1, 1, -30),
+  ('\u{3d1}', 1, 1, -25),
+  ('\u{3d5}', 1, 1, -15),
+  ('\u{3d6}', 1, 1, -22),
+  ('\u{3d8}', 12, 2, 1),
+  ('\u{3f0}', 1, 1, -54),
+  ('\u{3f1}', 1, 1, -48),
+  ('\u{3f4}', 1, 1, -60),
+  ('\u{3f5}', 1, 1, -64),
+  ('\u{3f7}', 1, 1, 1),
+  ('\u{3f9}', 1, 1, -7),
+  ('\u{3fa}', 1, 1, 1),
+  ('\u{3fd}', 3, 1, -130),
+  ('\u{400}', 16, 1, 80),
+  ('\u{410}', 32, 1, 32),
+  ('\u{460}', 17, 2, 1),
+  ('\u{48a}', 27, 2, 1),
+  ('\u{4c0}', 1, 1, 15),
+  ('\u{4c1}', 7, 2, 1),
+  ('\u{4d0}', 48, 2, 1),
+  ('\u{531}', 38, 1, 48),
+  ('\u{10a0}', 38, 1, 7264),
+  ('\u{10c7}', 1, 1, 7264),
+  ('\u{10cd}', 1, 1, 7264),
+  ('\u{13f8}', 6, 1, -8),
+  ('\u{1c80}', 1, 1, -6222),
+  ('\u{1c81}', 1, 1, -6221),
+  ('\u{1c82}', 1, 1, -6212),
+  ('\u{1c83}', 2, 1, -6210),
+  ('\u{1c85}', 1, 1, -6211),
+  ('\u{1c86}', 1, 1, -6204),
+  ('\u{1c87}', 1, 1, -6180),
+  ('\u{1c88}', 1, 1, 35267),
+  ('\u{1c89}', 1, 1, 1),
+  ('\u{1c90}', 43, 1, -3008),
+  ('\u{1cbd}', 3, 1, -3008),
+  ('\u{1e00}', 75, 2, 1),
+  ('\u{1e9b}', 1, 1, -58),
+  ('\u{1ea0}', 48, 2, 1),
+  ('\u{1f08}', 8, 1, -8),
+  ('\u{1f18}', 6, 1, -8),
+  ('\u{1f28}', 8, 1, -8),
+  ('\u{1f38}', 8, 1, -8),
+  ('\u{1f48}', 6, 1, -8),
+  ('\u{1f59}', 4, 2, -8),
+  ('\u{1f68}', 8, 1, -8),
+  ('\u{1fb8}', 2, 1, -8),
+  ('\u{1fba}', 2, 1, -74),
+  ('\u{1fbe}', 1, 1, -7173),
+  ('\u{1fc8}', 4, 1, -86),
+  ('\u{1fd8}', 2, 1, -8),
+  ('\u{1fda}', 2, 1, -100),
+  ('\u{1fe8}', 2, 1, -8),
+  ('\u{1fea}', 2, 1, -112),
+  ('\u{1fec}', 1, 1, -7),
+  ('\u{1ff8}', 2, 1, -128),
+  ('\u{1ffa}', 2, 1, -126),
+  ('\u{2126}', 1, 1, -7517),
+  ('\u{212a}', 1, 1, -8383),
+  ('\u{212b}', 1, 1, -8262),
+  ('\u{2132}', 1, 1, 28),
+  ('\u{2160}', 16, 1, 16),
+  ('\u{2183}', 1, 1, 1),
+  ('\u{24b6}', 26, 1, 26),
+  ('\u{2c00}', 48, 1, 48),
+  ('\u{2c60}', 1, 1, 1),
+  ('\u{2c62}', 1, 1, -10743),
+  ('\u{2c63}', 1, 1, -3814),
+  ('\u{2c64}', 1, 1, -10727),
+  ('\u{2c67}', 3, 2, 1),
+  ('\u{2c6d}', 1, 1, -10780),
+  ('\u{2c6e}', 1, 1, -10749),
+  ('\u{2c6f}', 1, 1, -10783),
+  ('\u{2c70}', 1, 1, -10782),
+  ('\u{2c72}', 1, 1, 1),
+  ('\u{2c75}', 1, 1, 1),
+  ('\u{2c7e}', 2, 1, -10815),
+  ('\u{2c80}', 50, 2, 1),
+  ('\u{2ceb}', 2, 2, 1),
+  ('\u{2cf2}', 1, 1, 1),
+  ('\u{a640}', 23, 2, 1),
+  ('\u{a680}', 14, 2, 1),
+  ('\u{a722}', 7, 2, 1),
+  ('\u{a732}', 31, 2, 1),
+  ('\u{a779}', 2, 2, 1),
+  ('\u{a77d}', 1, 1, -35332),
+  ('\u{a77e}', 5, 2, 1),
+  ('\u{a78b}', 1, 1, 1),
+  ('\u{a78d}', 1, 1, -42280),
+  ('\u{a790}', 2, 2, 1),
+  ('\u{a796}', 10, 2, 1),
+  ('\u{a7aa}', 1, 1, -42308),
+  ('\u{a7ab}', 1, 1, -42319),
+  ('\u{a7ac}', 1, 1, -42315),
+  ('\u{a7ad}', 1, 1, -42305),
+  ('\u{a7ae}', 1, 1, -42308),
+  ('\u{a7b0}', 1, 1, -42258),
+  ('\u{a7b1}', 1, 1, -42282),
+  ('\u{a7b2}', 1, 1, -42261),
+  ('\u{a7b3}', 1, 1, 928),
+  ('\u{a7b4}', 8, 2, 1),
+  ('\u{a7c4}', 1, 1, -48),
+  ('\u{a7c5}', 1, 1, -42307),
+  ('\u{a7c6}', 1, 1, -35384),
+  ('\u{a7c7}', 2, 2, 1),
+  ('\u{a7cb}', 1, 1, -42343),
+  ('\u{a7cc}', 1, 1, 1),
+  ('\u{a7d0}', 1, 1, 1),
+  ('\u{a7d6}', 3, 2, 1),
+  ('\u{a7dc}', 1, 1, -42561),
+  ('\u{a7f5}', 1, 1, 1),
+  ('\u{ab70}', 80, 1, -38864),
+  ('\u{ff21}', 26, 1, 32),
+  ('\u{10400}', 40, 1, 40),
+  ('\u{104b0}', 36, 1, 40),
+  ('\u{10570}', 11, 1, 39),
+  ('\u{1057c}', 15, 1, 39),
+  ('\u{1058c}', 7, 1, 39),
+  ('\u{10594}', 2, 1, 39),
+  ('\u{10c80}', 51, 1, 64),
+  ('\u{10d50}', 22, 1, 32),
+  ('\u{118a0}', 32, 1, 32),
+  ('\u{16e40}', 32, 1, 32),
+  ('\u{1e900}', 34, 1, 34),
+];
+pub const ASCII_CASE_FOLDING_TABLE: [u8; 128] = [
+  0,
+  1,
+  2,
+  3,
+  4,
+  5,
+  6,
+  7,
+  8,
+  9,
+  10,
+  11,
+  12,
+  13,
+  14,
+  15,
+  16,
+  17,
+  18,
+  19,
+  20,
+  21,
+  22,
+  23,
+  24,
+  25,
+  26,
+  27,
+  28,
+  29,
+  30,
+  31,
+  32,
+  33,
+  34,
+  35,
+  36,
+  37,
+  38,
+  39,
+  40,
+  41,
+  42,
+  43,
+  44,
+  45,
+  46,
+  47,
+  48,
+  49,
+  50,
+  51,
+  52,
+  53,
+  54,
+  55,
+  56,
+  57,
+  58,
+  59,
+  60,
+  61,
+  62,
+  63,
+  64,
+  97,
+  98,
+  99,
+  100,
+  101,
+  102,
+  103,
+  104,
+  105,
+  106,
+  107,
+  108,
+  109,
+  110,
+  111,
+  112,
+  113,
+  114,
+  115,
+  116,
+  117,
+  118,
+  119,
+  120,
+  121,
+  122,
+  91,
+  92,
+  93,
+  94,
+  95,
+  96,
+  97,
+  98,
+  99,
+  100,
+  101,
+  102,
+  103,
+  104,
+  105,
+  106,
+  107,
+  108,
+  109,
+  110,
+  111,
+  112,
+  113,
+  114,
+  115,
+  116,
+  117,
+  118,
+  119,
+  120,
+  121,
+  122,
+  123,
+  124,
+  125,
+  126,
+  127,
+];
+pub const TURKIC_CASE_FOLDING_TABLE: &[(char, [char; 3])] = &[
+  ('I', ['\u{131}', '\0', '\0']),
+  ('\u{130}', ['i', '\0', '\0']),
+];
+pub const SIMPLE_CASE_FOLDING_TABLE: &[(char, char)] = &[
+  ('A', 'a'),
+  ('B', 'b'),
+  ('C', 'c'),
+  ('D', 'd'),
+  ('E', 'e'),
+  ('F', 'f'),
+  ('G', 'g'),
+  ('H', 'h'),
+  ('I', 'i'),
+  ('J', 'j'),
+  ('K', 'k'),
+  ('L', 'l'),
+  ('M', 'm'),
+  ('N', 'n'),
+  ('O', 'o'),
+  ('P', 'p'),
+  ('Q', 'q'),
+  ('R', 'r'),
+  ('S', 's'),
+  ('T', 't'),
+  ('U', 'u'),
+  ('V', 'v'),
+  ('W', 'w'),
+  ('X', 'x'),
+  ('Y', 'y'),
+  ('Z', 'z'),
+  ('\u{b5}', '\u{3bc}'),
+  ('\u{c0}', '\u{e0}'),
+  ('\u{c1}', '\u{e1}'),
+  ('\u{c2}', '\u{e2}'),
+  ('\u{c3}', '\u{e3}'),
+  ('\u{c4}', '\u{e4}'),
+  ('\u{c5}', '\u{e5}'),
+  ('\u{c6}', '\u{e6}'),
+  ('\u{c7}', '\u{e7}'),
+  ('\u{c8}', '\u{e8}'),
+  ('\u{c9}', '\u{e9}'),
+  ('\u{ca}', '\u{ea}'),
+  ('\u{cb}', '\u{eb}'),
+  ('\u{cc}', '\u{ec}'),
+  ('\u{cd}', '\u{ed}'),
+  ('\u{ce}', '\u{ee}'),
+  ('\u{cf}', '\u{ef}'),
+  ('\u{d0}', '\u{f0}'),
+  ('\u{d1}', '\u{f1}'),
+  ('\u{d2}', '\u{f2}'),
+  ('\u{d3}', '\u{f3}'),
+  ('\u{d4}', '\u{f4}'),
+  ('\u{d5}', '\u{f5}'),
+  ('\u{d6}', '\u{f6}'),
+  ('\u{d8}', '\u{f8}'),
+  ('\u{d9}', '\u{f9}'),
+  ('\u{da}', '\u{fa}'),
+  ('\u{db}', '\u{fb}'),
+  ('\u{dc}', '\u{fc}'),
+  ('\u{dd}', '\u{fd}'),
+  ('\u{de}', '\u{fe}'),
+  ('\u{100}', '\u{101}'),
+  ('\u{102}', '\u{103}'),
+  ('\u{104}', '\u{105}'),
+  ('\u{106}', '\u{107}'),
+  ('\u{108}', '\u{109}'),
+  ('\u{10a}', '\u{10b}'),
+  ('\u{10c}', '\u{10d}'),
+  ('\u{10e}', '\u{10f}'),
+  ('\u{110}', '\u{111}'),
+  ('\u{112}', '\u{113}'),
+  ('\u{114}', '\u{115}'),
+  ('\u{116}', '\u{117}'),
+  ('\u{118}', '\u{119}'),
+  ('\u{11a}', '\u{11b}'),
+  ('\u{11c}', '\u{11d}'),
+  ('\u{11e}', '\u{11f}'),
+  ('\u{120}', '\u{121}'),
+  ('\u{122}', '\u{123}'),
+  ('\u{124}', '\u{125}'),
+  ('\u{126}', '\u{127}'),
+  ('\u{128}', '\u{129}'),
+  ('\u{12a}', '\u{12b}'),
+  ('\u{12c}', '\u{12d}'),
+  ('\u{12e}', '\u{12f}'),
+  ('\u{132}', '\u{133}'),
+  ('\u{134}', '\u{135}'),
+  ('\u{136}', '\u{137}'),
+  ('\u{139}', '\u{13a}'),
+  ('\u{13b}', '\u{13c}'),
+  ('\u{13d}', '\u{13e}'),
+  ('\u{13f}', '\u{140}'),
+  ('\u{141}', '\u{142}'),
+  ('\u{143}', '\u{144}'),
+  ('\u{145}', '\u{146}'),
+  ('\u{147}', '\u{148}'),
+  ('\u{14a}', '\u{14b}'),
+  ('\u{14c}', '\u{14d}'),
+  ('\u{14e}', '\u{14f}'),
+  ('\u{150}', '\u{151}'),
+  ('\u{152}', '\u{153}'),
+  ('\u{154}', '\u{155}'),
+  ('\u{156}', '\u{157}'),
+  ('\u{158}', '\u{159}'),
+  ('\u{15a}', '\u{15b}'),
+  ('\u{15c}', '\u{15d}'),
+  ('\u{15e}', '\u{15f}'),
+  ('\u{160}', '\u{161}'),
+  ('\u{162}', '\u{163}'),
+  ('\u{164}', '\u{165}'),
+  ('\u{166}', '\u{167}'),
+  ('\u{168}', '\u{169}'),
+  ('\u{16a}', '\u{16b}'),
+  ('\u{16c}', '\u{16d}'),
+  ('\u{16e}', '\u{16f}'),
+  ('\u{170}', '\u{171}'),
+  ('\u{172}', '\u{173}'),
+  ('\u{174}', '\u{175}'),
+  ('\u{176}', '\u{177}'),
+  ('\u{178}', '\u{ff}'),
+  ('\u{179}', '\u{17a}'),
+  ('\u{17b}', '\u{17c}'),
+  ('\u{17d}', '\u{17e}'),
+  ('\u{17f}', 's'),
+  ('\u{181}', '\u{253}'),
+  ('\u{182}', '\u{183}'),
+  ('\u{184}', '\u{185}'),
+  ('\u{186}', '\u{254}'),
+  ('\u{187}', '\u{188}'),
+  ('\u{189}', '\u{256}'),
+  ('\u{18a}', '\u{257}'),
+  ('\u{18b}', '\u{18c}'),
+  ('\u{18e}', '\u{1dd}'),
+  ('\u{18f}', '\u{259}'),
+  ('\u{190}', '\u{25b}'),
+  ('\u{191}', '\u{192}'),
+  ('\u{193}', '\u{260}'),
+  ('\u{194}', '\u{263}'),
+  ('\u{196}', '\u{269}'),
+  ('\u{197}', '\u{268}'),
+  ('\u{198}', '\u{199}'),
+  ('\u{19c}', '\u{26f}'),
+  ('\u{19d}', '\u{272}'),
+  ('\u{19f}', '\u{275}'),
+  ('\u{1a0}', '\u{1a1}'),
+  ('\u{1a2}', '\u{1a3}'),
+  ('\u{1a4}', '\u{1a5}'),
+  ('\u{1a6}', '\u{280}'),
+  ('\u{1a7}', '\u{1a8}'),
+  ('\u{1a9}', '\u{283}'),
+  ('\u{1ac}', '\u{1ad}'),
+  ('\u{1ae}', '\u{288}'),
+  ('\u{1af}', '\u{1b0}'),
+  ('\u{1b1}', '\u{28a}'),
+  ('\u{1b2}', '\u{28b}'),
+  ('\u{1b3}', '\u{1b4}'),
+  ('\u{1b5}', '\u{1b6}'),
+  ('\u{1b7}', '\u{292}'),
+  ('\u{1b8}', '\u{1b9}'),
+  ('\u{1bc}', '\u{1bd}'),
+  ('\u{1c4}', '\u{1c6}'),
+  ('\u{1c5}', '\u{1c6}'),
+  ('\u{1c7}', '\u{1c9}'),
+  ('\u{1c8}', '\u{1c9}'),
+  ('\u{1ca}', '\u{1cc}'),
+  ('\u{1cb}', '\u{1cc}'),
+  ('\u{1cd}', '\u{1ce}'),
+  ('\u{1cf}', '\u{1d0}'),
+  ('\u{1d1}', '\u{1d2}'),
+  ('\u{1d3}', '\u{1d4}'),
+  ('\u{1d5}', '\u{1d6}'),
+  ('\u{1d7}', '\u{1d8}'),
+  ('\u{1d9}', '\u{1da}'),
+  ('\u{1db}', '\u{1dc}'),
+  ('\u{1de}', '\u{1df}'),
+  ('\u{1e0}', '\u{1e1}'),
+  ('\u{1e2}', '\u{1e3}'),
+  ('\u{1e4}', '\u{1e5}'),
+  ('\u{1e6}', '\u{1e7}'),
+  ('\u{1e8}', '\u{1e9}'),
+  ('\u{1ea}', '\u{1eb}'),
+  ('\u{1ec}', '\u{1ed}'),
+  ('\u{1ee}', '\u{1ef}'),
+  ('\u{1f1}', '\u{1f3}'),
+  ('\u{1f2}', '\u{1f3}'),
+  ('\u{1f4}', '\u{1f5}'),
+  ('\u{1f6}', '\u{195}'),
+  ('\u{1f7}', '\u{1bf}'),
+  ('\u{1f8}', '\u{1f9}'),
+  ('\u{1fa}', '\u{1fb}'),
+  ('\u{1fc}', '\u{1fd}'),
+  ('\u{1fe}', '\u{1ff}'),
+  ('\u{200}', '\u{201}'),
+  ('\u{202}', '\u{203}'),
+  ('\u{204}', '\u{205}'),
+  ('\u{206}', '\u{207}'),
+  ('\u{208}', '\u{209}'),
+  ('\u{20a}', '\u{20b}'),
+  ('\u{20c}', '\u{20d}'),
+  ('\u{20e}', '\u{20f}'),
+  ('\u{210}', '\u{211}'),
+  ('\u{212}', '\u{213}'),
+  ('\u{214}', '\u{215}'),
+  ('\u{216}', '\u{217}'),
+  ('\u{218}', '\u{219}'),
+  ('\u{21a}', '\u{21b}'),
+  ('\u{21c}', '\u{21d}'),
+  ('\u{21e}', '\u{21f}'),
+  ('\u{220}', '\u{19e}'),
+  ('\u{222}', '\u{223}'),
+  ('\u{224}', '\u{225}'),
+  ('\u{226}', '\u{227}'),
+  ('\u{228}', '\u{229}'),
+  ('\u{22a}', '\u{22b}'),
+  ('\u{22c}', '\u{22d}'),
+  ('\u{22e}', '\u{22f}'),
+  ('\u{230}', '\u{231}'),
+  ('\u{232}', '\u{233}'),
+  ('\u{23a}', '\u{2c65}'),
+  ('\u{23b}', '\u{23c}'),
+  ('\u{23d}', '\u{19a}'),
+  ('\u{23e}', '\u{2c66}'),
+  ('\u{241}', '\u{242}'),
+  ('\u{243}', '\u{180}'),
+  ('\u{244}', '\u{289}'),
+  ('\u{245}', '\u{28c}'),
+  ('\u{246}', '\u{247}'),
+  ('\u{248}', '\u{249}'),
+  ('\u{24a}', '\u{24b}'),
+  ('\u{24c}', '\u{24d}'),
+  ('\u{24e}', '\u{24f}'),
+  ('\u{345}', '\u{3b9}'),
+  ('\u{370}', '\u{371}'),
+  ('\u{372}', '\u{373}'),
+  ('\u{376}', '\u{377}'),
+  ('\u{37f}', '\u{3f3}'),
+  ('\u{386}', '\u{3ac}'),
+  ('\u{388}', '\u{3ad}'),
+  ('\u{389}', '\u{3ae}'),
+  ('\u{38a}', '\u{3af}'),
+  ('\u{38c}', '\u{3cc}'),
+  ('\u{38e}', '\u{3cd}'),
+  ('\u{38f}', '\u{3ce}'),
+  ('\u{391}', '\u{3b1}'),
+  ('\u{392}', '\u{3b2}'),
+  ('\u{393}', '\u{3b3}'),
+  ('\u{394}', '\u{3b4}'),
+  ('\u{395}', '\u{3b5}'),
+  ('\u{396}', '\u{3b6}'),
+  ('\u{397}', '\u{3b7}'),
+  ('\u{398}', '\u{3b8}'),
+  ('\u{399}', '\u{3b9}'),
+  ('\u{39a}', '\u{3ba}'),
+  ('\u{39b}', '\u{3bb}'),
+  ('\u{39c}', '\u{3bc}'),
+  ('\u{39d}', '\u{3bd}'),
+  ('\u{39e}', '\u{3be}'),
+  ('\u{39f}', '\u{3bf}'),
+  ('\u{3a0}', '\u{3c0}'),
+  ('\u{3a1}', '\u{3c1}'),
+  ('\u{3a3}', '\u{3c3}'),
+  ('\u{3a4}', '\u{3c4}'),
+  ('\u{3a5}', '\u{3c5}'),
+  ('\u{3a6}', '\u{3c6}'),
+  ('\u{3a7}', '\u{3c7}'),
+  ('\u{3a8}', '\u{3c8}'),
+  ('\u{3a9}', '\u{3c9}'),
+  ('\u{3aa}', '\u{3ca}'),
+  ('\u{3ab}', '\u{3cb}'),
+  ('\u{3c2}', '\u{3c3}'),
+  ('\u{3cf}', '\u{3d7}'),
+  ('\u{3d0}', '\u{3b2}'),
+  ('\u{3d1}', '\u{3b8}'),
+  ('\u{3d5}', '\u{3c6}'),
+  ('\u{3d6}', '\u{3c0}'),
+  ('\u{3d8}', '\u{3d9}'),
+  ('\u{3da}', '\u{3db}'),
+  ('\u{3dc}', '\u{3dd}'),
+  ('\u{3de}', '\u{3df}'),
+  ('\u{3e0}', '\u{3e1}'),
+  ('\u{3e2}', '\u{3e3}'),
+  ('\u{3e4}', '\u{3e5}'),
+  ('\u{3e6}', '\u{3e7}'),
+  ('\u{3e8}', '\u{3e9}'),
+  ('\u{3ea}', '\u{3eb}'),
+  ('\u{3ec}', '\u{3ed}'),
+  ('\u{3ee}', '\u{3ef}'),
+  ('\u{3f0}', '\u{3ba}'),
+  ('\u{3f1}', '\u{3c1}'),
+  ('\u{3f4}', '\u{3b8}'),
+  ('\u{3f5}', '\u{3b5}'),
+  ('\u{3f7}', '\u{3f8}'),
+  ('\u{3f9}', '\u{3f2}'),
+  ('\u{3fa}', '\u{3fb}'),
+  ('\u{3fd}', '\u{37b}'),
+  ('\u{3fe}', '\u{37c}'),
+  ('\u{3ff}', '\u{37d}'),
+  ('\u{400}', '\u{450}'),
+  ('\u{401}', '\u{451}'),
+  ('\u{402}', '\u{452}'),
+  ('\u{403}', '\u{453}'),
+  ('\u{404}', '\u{454}'),
+  ('\u{405}', '\u{455}'),
+  ('\u{406}', '\u{456}'),
+  ('\u{407}', '\u{457}'),
+  ('\u{408}', '\u{458}'),
+  ('\u{409}', '\u{459}'),
+  ('\u{40a}', '\u{45a}'),
+  ('\u{40b}', '\u{45b}'),
+  ('\u{40c}', '\u{45c}'),
+  ('\u{40d}', '\u{45d}'),
+  ('\u{40e}', '\u{45e}'),
+  ('\u{40f}', '\u{45f}'),
+  ('\u{410}', '\u{430}'),
+  ('\u{411}', '\u{431}'),
+  ('\u{412}', '\u{432}'),
+  ('\u{413}', '\u{433}'),
+  ('\u{414}', '\u{434}'),
+  ('\u{415}', '\u{435}'),
+  ('\u{416}', '\u{436}'),
+  ('\u{417}', '\u{437}'),
+  ('\u{418}', '\u{438}'),
+  ('\u{419}', '\u{439}'),
+  ('\u{41a}', '\u{43a}'),
+  ('\u{41b}', '\u{43b}'),
+  ('\u{41c}', '\u{43c}'),
+  ('\u{41d}', '\u{43d}'),
+  ('\u{41e}', '\u{43e}'),
+  ('\u{41f}', '\u{43f}'),
+  ('\u{420}', '\u{440}'),
+  ('\u{421}', '\u{441}'),
+  ('\u{422}', '\u{442}'),
+  ('\u{423}', '\u{443}'),
+  ('\u{424}', '\u{444}'),
+  ('\u{425}', '\u{445}'),
+  ('\u{426}', '\u{446}'),
+  ('\u{427}', '\u{447}'),
+  ('\u{428}', '\u{448}'),
+  ('\u{429}', '\u{449}'),
+  ('\u{42a}', '\u{44a}'),
+  ('\u{42b}', '\u{44b}'),
+  ('\u{42c}', '\u{44c}'),
+  ('\u{42d}', '\u{44d}'),
+  ('\u{42e}', '\u{44e}'),
+  ('\u{42f}', '\u{44f}'),
+  ('\u{460}', '\u{461}'),
+  ('\u{462}', '\u{463}'),
+  ('\u{464}', '\u{465}'),
+  ('\u{466}', '\u{467}'),
+  ('\u{468}', '\u{469}'),
+  ('\u{46a}', '\u{46b}'),
+  ('\u{46c}', '\u{46d}'),
+  ('\u{46e}', '\u{46f}'),
+  ('\u{470}', '\u{471}'),
+  ('\u{472}', '\u{473}'),
+  ('\u{474}', '\u{475}'),
+  ('\u{476}', '\u{477}'),
+  ('\u{478}', '\u{479}'),
+  ('\u{47a}', '\u{47b}'),
+  ('\u{47c}', '\u{47d}'),
+  ('\u{47e}', '\u{47f}'),
+  ('\u{480}', '\u{481}'),
+  ('\u{48a}', '\u{48b}'),
+  ('\u{48c}', '\u{48d}'),
+  ('\u{48e}', '\u{48f}'),
+  ('\u{490}', '\u{491}'),
+  ('\u{492}', '\u{493}'),
+  ('\u{494}', '\u{495}'),
+  ('\u{496}', '\u{497}'),
+  ('\u{498}', '\u{499}'),
+  ('\u{49a}', '\u{49b}'),
+  ('\u{49c}', '\u{49d}'),
+  ('\u{49e}', '\u{49f}'),
+  ('\u{4a0}', '\u{4a1}'),
+  ('\u{4a2}', '\u{4a3}'),
+  ('\u{4a4}', '\u{4a5}'),
+  ('\u{4a6}', '\u{4a7}'),
+  ('\u{4a8}', '\u{4a9}'),
+  ('\u{4aa}', '\u{4ab}'),
+  ('\u{4ac}', '\u{4ad}'),
+  ('\u{4ae}', '\u{4af}'),
+  ('\u{4b0}', '\u{4b1}'),
+  ('\u{4b2}', '\u{4b3}'),
+  ('\u{4b4}', '\u{4b5}'),
+  ('\u{4b6}', '\u{4b7}'),
+  ('\u{4b8}', '\u{4b9}'),
+  ('\u{4ba}', '\u{4bb}'),
+  ('\u{4bc}', '\u{4bd}'),
+  ('\u{4be}', '\u{4bf}'),
+  ('\u{4c0}', '\u{4cf}'),
+  ('\u{4c1}', '\u{4c2}'),
+  ('\u{4c3}', '\u{4c4}'),
+  ('\u{4c5}', '\u{4c6}'),
+  ('\u{4c7}', '\u{4c8}'),
+  ('\u{4c9}', '\u{4ca}'),
+  ('\u{4cb}', '\u{4cc}'),
+  ('\u{4cd}', '\u{4ce}'),
+  ('\u{4d0}', '\u{4d1}'),
+  ('\u{4d2}', '\u{4d3}'),
+  ('\u{4d4}', '\u{4d5}'),
+  ('\u{4d6}', '\u{4d7}'),
+  ('\u{4d8}', '\u{4d9}'),
+  ('\u{4da}', '\u{4db}'),
+  ('\u{4dc}', '\u{4dd}'),
+  ('\u{4de}', '\u{4df}'),
+  ('\u{4e0}', '\u{4e1}'),
+  ('\u{4e2}', '\u{4e3}'),
+  ('\u{4e4}', '\u{4e5}'),
+  ('\u{4e6}', '\u{4e7}'),
+  ('\u{4e8}', '\u{4e9}'),
+  ('\u{4ea}', '\u{4eb}'),
+  ('\u{4ec}', '\u{4ed}'),
+  ('\u{4ee}', '\u{4ef}'),
+  ('\u{4f0}', '\u{4f1}'),
+  ('\u{4f2}', '\u{4f3}'),
+  ('\u{4f4}', '\u{4f5}'),
+  ('\u{4f6}', '\u{4f7}'),
+  ('\u{4f8}', '\u{4f9}'),
+  ('\u{4fa}', '\u{4fb}'),
+  ('\u{4fc}', '\u{4fd}'),
+  ('\u{4fe}', '\u{4ff}'),
+  ('\u{500}', '\u{501}'),
+  ('\u{502}', '\u{503}'),
+  ('\u{504}', '\u{505}'),
+  ('\u{506}', '\u{507}'),
+  ('\u{508}', '\u{509}'),
+  ('\u{50a}', '\u{50b}'),
+  ('\u{50c}', '\u{50d}'),
+  ('\u{50e}', '\u{50f}'),
+  ('\u{510}', '\u{511}'),
+  ('\u{512}', '\u{513}'),
+  ('\u{514}', '\u{515}'),
+  ('\u{516}', '\u{517}'),
+  ('\u{518}', '\u{519}'),
+  ('\u{51a}', '\u{51b}'),
+  ('\u{51c}', '\u{51d}'),
+  ('\u{51e}', '\u{51f}'),
+  ('\u{520}', '\u{521}'),
+  ('\u{522}', '\u{523}'),
+  ('\u{524}', '\u{525}'),
+  ('\u{526}', '\u{527}'),
+  ('\u{528}', '\u{529}'),
+  ('\u{52a}', '\u{52b}'),
+  ('\u{52c}', '\u{52d}'),
+  ('\u{52e}', '\u{52f}'),
+  ('\u{531}', '\u{561}'),
+  ('\u{532}', '\u{562}'),
+  ('\u{533}', '\u{563}'),
+  ('\u{534}', '\u{564}'),
+  ('\u{535}', '\u{565}'),
+  ('\u{536}', '\u{566}'),
+  ('\u{537}', '\u{567}'),
+  ('\u{538}', '\u{568}'),
+  ('\u{539}', '\u{569}'),
+  ('\u{53a}', '\u{56a}'),
+  ('\u{53b}', '\u{56b}'),
+  ('\u{53c}', '\u{56c}'),
+  ('\u{53d}', '\u{56d}'),
+  ('\u{53e}', '\u{56e}'),
+  ('\u{53f}', '\u{56f}'),
+  ('\u{540}', '\u{570}'),
+  ('\u{541}', '\u{571}'),
+  ('\u{542}', '\u{572}'),
+  ('\u{543}', '\u{573}'),
+  ('\u{544}', '\u{574}'),
+  ('\u{545}', '\u{575}'),
+  ('\u{546}', '\u{576}'),
+  ('\u{547}', '\u{577}'),
+  ('\u{548}', '\u{578}'),
+  ('\u{549}', '\u{579}'),
+  ('\u{54a}', '\u{57a}'),
+  ('\u{54b}', '\u{57b}'),
+  ('\u{54c}', '\u{57c}'),
+  ('\u{54d}', '\u{57d}'),
+  ('\u{54e}', '\u{57e}'),
+  ('\u{54f}', '\u{57f}'),
+  ('\u{550}', '\u{580}'),
+  ('\u{551}', '\u{581}'),
+  ('\u{552}', '\u{582}'),
+  ('\u{553}', '\u{583}'),
+  ('\u{554}', '\u{584}'),
+  ('\u{555}', '\u{585}'),
+  ('\u{556}', '\u{586}'),
+  ('\u{10a0}', '\u{2d00}'),
+  ('\u{10a1}', '\u{2d01}'),
+  ('\u{10a2}', '\u{2d02}'),
+  ('\u{10a3}', '\u{2d03}'),
+  ('\u{10a4}', '\u{2d04}'),
+  ('\u{10a5}', '\u{2d05}'),
+  ('\u{10a6}', '\u{2d06}'),
+  ('\u{10a7}', '\u{2d07}'),
+  ('\u{10a8}', '\u{2d08}'),
+  ('\u{10a9}', '\u{2d09}'),
+  ('\u{10aa}', '\u{2d0a}'),
+  ('\u{10ab}', '\u{2d0b}'),
+  ('\u{10ac}', '\u{2d0c}'),
+  ('\u{10ad}', '\u{2d0d}'),
+  ('\u{10ae}', '\u{2d0e}'),
+  ('\u{10af}', '\u{2d0f}'),
+  ('\u{10b0}', '\u{2d10}'),
+  ('\u{10b1}', '\u{2d11}'),
+  ('\u{10b2}', '\u{2d12}'),
+  ('\u{10b3}', '\u{2d13}'),
+  ('\u{10b4}', '\u{2d14}'),
+  ('\u{10b5}', '\u{2d15}'),
+  ('\u{10b6}', '\u{2d16}'),
+  ('\u{10b7}', '\u{2d17}'),
+  ('\u{10b8}', '\u{2d18}'),
+  ('\u{10b9}', '\u{2d19}'),
+  ('\u{10ba}', '\u{2d1a}'),
+  ('\u{10bb}', '\u{2d1b}'),
+  ('\u{10bc}', '\u{2d1c}'),
+  ('\u{10bd}', '\u{2d1d}'),
+  ('\u{10be}', '\u{2d1e}'),
+  ('\u{10bf}', '\u{2d1f}'),
+  ('\u{10c0}', '\u{2d20}'),
+  ('\u{10c1}', '\u{2d21}'),
+  ('\u{10c2}', '\u{2d22}'),
+  ('\u{10c3}', '\u{2d23}'),
+  ('\u{10c4}', '\u{2d24}'),
+  ('\u{10c5}', '\u{2d25}'),
+  ('\u{10c7}', '\u{2d27}'),
+  ('\u{10cd}', '\u{2d2d}'),
+  ('\u{13f8}', '\u{13f0}'),
+  ('\u{13f9}', '\u{13f1}'),
+  ('\u{13fa}', '\u{13f2}'),
+  ('\u{13fb}', '\u{13f3}'),
+  ('\u{13fc}', '\u{13f4}'),
+  ('\u{13fd}', '\u{13f5}'),
+  ('\u{1c80}', '\u{432}'),
+  ('\u{1c81}', '\u{434}'),
+  ('\u{1c82}', '\u{43e}'),
+  ('\u{1c83}', '\u{441}'),
+  ('\u{1c84}', '\u{442}'),
+  ('\u{1c85}', '\u{442}'),
+  ('\u{1c86}', '\u{44a}'),
+  ('\u{1c87}', '\u{463}'),
+  ('\u{1c88}', '\u{a64b}'),
+  ('\u{1c89}', '\u{1c8a}'),
+  ('\u{1c90}', '\u{10d0}'),
+  ('\u{1c91}', '\u{10d1}'),
+  ('\u{1c92}', '\u{10d2}'),
+  ('\u{1c93}', '\u{10d3}'),
+  ('\u{1c94}', '\u{10d4}'),
+  ('\u{1c95}', '\u{10d5}'),
+  ('\u{1c96}', '\u{10d6}'),
+  ('\u{1c97}', '\u{10d7}'),
+  ('\u{1c98}', '\u{10d8}'),
+  ('\u{1c99}', '\u{10d9}'),
+  ('\u{1c9a}', '\u{10da}'),
+  ('\u{1c9b}', '\u{10db}'),
+  ('\u{1c9c}', '\u{10dc}'),
+  ('\u{1c9d}', '\u{10dd}'),
+  ('\u{1c9e}', '\u{10de}'),
+  ('\u{1c9f}', '\u{10df}'),
+  ('\u{1ca0}', '\u{10e0}'),
+  ('\u{1ca1}', '\u{10e1}'),
+  ('\u{1ca2}', '\u{10e2}'),
+  ('\u{1ca3}', '\u{10e3}'),
+  ('\u{1ca4}', '\u{10e4}'),
+  ('\u{1ca5}', '\u{10e5}'),
+  ('\u{1ca6}', '\u{10e6}'),
+  ('\u{1ca7}', '\u{10e7}'),
+  ('\u{1ca8}', '\u{10e8}'),
+  ('\u{1ca9}', '\u{10e9}'),
+  ('\u{1caa}', '\u{10ea}'),
+  ('\u{1cab}', '\u{10eb}'),
+  ('\u{1cac}', '\u{10ec}'),
+  ('\u{1cad}', '\u{10ed}'),
+  ('\u{1cae}', '\u{10ee}'),
+  ('\u{1caf}', '\u{10ef}'),
+  ('\u{1cb0}', '\u{10f0}'),
+  ('\u{1cb1}', '\u{10f1}'),
+  ('\u{1cb2}', '\u{10f2}'),
+  ('\u{1cb3}', '\u{10f3}'),
+  ('\u{1cb4}', '\u{10f4}'),
+  ('\u{1cb5}', '\u{10f5}'),
+  ('\u{1cb6}', '\u{10f6}'),
+  ('\u{1cb7}', '\u{10f7}'),
+  ('\u{1cb8}', '\u{10f8}'),
+  ('\u{1cb9}', '\u{10f9}'),
+  ('\u{1cba}', '\u{10fa}'),
+  ('\u{1cbd}', '\u{10fd}'),
+  ('\u{1cbe}', '\u{10fe}'),
+  ('\u{1cbf}', '\u{10ff}'),
+  ('\u{1e00}', '\u{1e01}'),
+  ('\u{1e02}', '\u{1e03}'),
+  ('\u{1e04}', '\u{1e05}'),
+  ('\u{1e06}', '\u{1e07}'),
+  ('\u{1e08}', '\u{1e09}'),
+  ('\u{1e0a}', '\u{1e0b}'),
+  ('\u{1e0c}', '\u{1e0d}'),
+  ('\u{1e0e}', '\u{1e0f}'),
+  ('\u{1e10}', '\u{1e11}'),
+  ('\u{1e12}', '\u{1e13}'),
+  ('\u{1e14}', '\u{1e15}'),
+  ('\u{1e16}', '\u{1e17}'),
+  ('\u{1e18}', '\u{1e19}'),
+  ('\u{1e1a}', '\u{1e1b}'),
+  ('\u{1e1c}', '\u{1e1d}'),
+  ('\u{1e1e}', '\u{1e1f}'),
+  ('\u{1e20}', '\u{1e21}'),
+  ('\u{1e22}', '\u{1e23}'),
+  ('\u{1e24}', '\u{1e25}'),
+  ('\u{1e26}', '\u{1e27}'),
+  ('\u{1e28}', '\u{1e29}'),
+  ('\u{1e2a}', '\u{1e2b}'),
+  ('\u{1e2c}', '\u{1e2d}'),
+  ('\u{1e2e}', '\u{1e2f}'),
+  ('\u{1e30}', '\u{1e31}'),
+  ('\u{1e32}', '\u{1e33}'),
+  ('\u{1e34}', '\u{1e35}'),
+  ('\u{1e36}', '\u{1e37}'),
+  ('\u{1e38}', '\u{1e39}'),
+  ('\u{1e3a}', '\u{1e3b}'),
+  ('\u{1e3c}', '\u{1e3d}'),
+  ('\u{1e3e}', '\u{1e3f}'),
+  ('\u{1e40}', '\u{1e41}'),
+  ('\u{1e42}', '\u{1e43}'),
+  ('\u{1e44}', '\u{1e45}'),
+  ('\u{1e46}', '\u{1e47}'),
+  ('\u{1e48}', '\u{1e49}'),
+  ('\u{1e4a}', '\u{1e4b}'),
+  ('\u{1e4c}', '\u{1e4d}'),
+  ('\u{1e4e}', '\u{1e4f}'),
+  ('\u{1e50}', '\u{1e51}'),
+  ('\u{1e52}', '\u{1e53}'),
+  ('\u{1e54}', '\u{1e55}'),
+  ('\u{1e56}', '\u{1e57}'),
+  ('\u{1e58}', '\u{1e59}'),
+  ('\u{1e5a}', '\u{1e5b}'),
+  ('\u{1e5c}', '\u{1e5d}'),
+  ('\u{1e5e}', '\u{1e5f}'),
+  ('\u{1e60}', '\u{1e61}'),
+  ('\u{1e62}', '\u{1e63}'),
+  ('\u{1e64}', '\u{1e65}'),
+  ('\u{1e66}', '\u{1e67}'),
+  ('\u{1e68}', '\u{1e69}'),
+  ('\u{1e6a}', '\u{1e6b}'),
+  ('\u{1e6c}', '\u{1e6d}'),
+  ('\u{1e6e}', '\u{1e6f}'),
+  ('\u{1e70}', '\u{1e71}'),
+  ('\u{1e72}', '\u{1e73}'),
+  ('\u{1e74}', '\u{1e75}'),
+  ('\u{1e76}', '\u{1e77}'),
+  ('\u{1e78}', '\u{1e79}'),
+  ('\u{1e7a}', '\u{1e7b}'),
+  ('\u{1e7c}', '\u{1e7d}'),
+  ('\u{1e7e}', '\u{1e7f}'),
+  ('\u{1e80}', '\u{1e81}'),
+  ('\u{1e82}', '\u{1e83}'),
+  ('\u{1e84}', '\u{1e85}'),
+  ('\u{1e86}', '\u{1e87}'),
+  ('\u{1e88}', '\u{1e89}'),
+  ('\u{1e8a}', '\u{1e8b}'),
+  ('\u{1e8c}', '\u{1e8d}'),
+  ('\u{1e8e}', '\u{1e8f}'),
+  ('\u{1e90}', '\u{1e91}'),
+  ('\u{1e92}', '\u{1e93}'),
+  ('\u{1e94}', '\u{1e95}'),
+  ('\u{1e9b}', '\u{1e61}'),
+  ('\u{1e9e}', '\u{df}'),
+  ('\u{1ea0}', '\u{1ea1}'),
+  ('\u{1ea2}', '\u{1ea3}'),
+  ('\u{1ea4}', '\u{1ea5}'),
+  ('\u{1ea6}', '\u{1ea7}'),
+  ('\u{1ea8}', '\u{1ea9}'),
+  ('\u{1eaa}', '\u{1eab}'),
+  ('\u{1eac}', '\u{1ead}'),
+  ('\u{1eae}', '\u{1eaf}'),
+  ('\u{1eb0}', '\u{1eb1}'),
+  ('\u{1eb2}', '\u{1eb3}'),
+  ('\u{1eb4}', '\u{1eb5}'),
+  ('\u{1eb6}', '\u{1eb7}'),
+  ('\u{1eb8}', '\u{1eb9}'),
+  ('\u{1eba}', '\u{1ebb}'),
+  ('\u{1ebc}', '\u{1ebd}'),
+  ('\u{1ebe}', '\u{1ebf}'),
+  ('\u{1ec0}', '\u{1ec1}'),
+  ('\u{1ec2}', '\u{1ec3}'),
+  ('\u{1ec4}', '\u{1ec5}'),
+  ('\u{1ec6}', '\u{1ec7}'),
+  ('\u{1ec8}', '\u{1ec9}'),
+  ('\u{1eca}', '\u{1ecb}'),
+  ('\u{1ecc}', '\u{1ecd}'),
+  ('\u{1ece}', '\u{1ecf}'),
+  ('\u{1ed0}', '\u{1ed1}'),
+  ('\u{1ed2}', '\u{1ed3}'),
+  ('\u{1ed4}', '\u{1ed5}'),
+  ('\u{1ed6}', '\u{1ed7}'),
+  ('\u{1ed8}', '\u{1ed9}'),
+  ('\u{1eda}', '\u{1edb}'),
+  ('\u{1edc}', '\u{1edd}'),
+  ('\u{1ede}', '\u{1edf}'),
+  ('\u{1ee0}', '\u{1ee1}'),
+  ('\u{1ee2}', '\u{1ee3}'),
+  ('\u{1ee4}', '\u{1ee5}'),
+  ('\u{1ee6}', '\u{1ee7}'),
+  ('\u{1ee8}', '\u{1ee9}'),
+  ('\u{1eea}', '\u{1eeb}'),
+  ('\u{1eec}', '\u{1eed}'),
+  ('\u{1eee}', '\u{1eef}'),
+  ('\u{1ef0}', '\u{1ef1}'),
+  ('\u{1ef2}', '\u{1ef3}'),
+  ('\u{1ef4}', '\u{1ef5}'),
+  ('\u{1ef6}', '\u{1ef7}'),
+  ('\u{1ef8}', '\u{1ef9}'),
+  ('\u{1efa}', '\u{1efb}'),
+  ('\u{1efc}', '\u{1efd}'),
+  ('\u{1efe}', '\u{1eff}'),
+  ('\u{1f08}', '\u{1f00}'),
+  ('\u{1f09}', '\u{1f01}'),
+  ('\u{1f0a}', '\u{1f02}'),
+  ('\u{1f0b}', '\u{1f03}'),
+  ('\u{1f0c}', '\u{1f04}'),
+  ('\u{1f0d}', '\u{1f05}'),
+  ('\u{1f0e}', '\u{1f06}'),
+  ('\u{1f0f}', '\u{1f07}'),
+  ('\u{1f18}', '\u{1f10}'),
+  ('\u{1f19}', '\u{1f11}'),
+  ('\u{1f1a}', '\u{1f12}'),
+  ('\u{1f1b}', '\u{1f13}'),
+  ('\u{1f1c}', '\u{1f14}'),
+  ('\u{1f1d}', '\u{1f15}'),
+  ('\u{1f28}', '\u{1f20}'),
+  ('\u{1f29}', '\u{1f21}'),
+  ('\u{1f2a}', '\u{1f22}'),
+  ('\u{1f2b}', '\u{1f23}'),
+  ('\u{1f2c}', '\u{1f24}'),
+  ('\u{1f2d}', '\u{1f25}'),
+  ('\u{1f2e}', '\u{1f26}'),
+  ('\u{1f2f}', '\u{1f27}'),
+  ('\u{1f38}', '\u{1f30}'),
+  ('\u{1f39}', '\u{1f31}'),
+  ('\u{1f3a}', '\u{1f32}'),
+  ('\u{1f3b}', '\u{1f33}'),
+  ('\u{1f3c}', '\u{1f34}'),
+  ('\u{1f3d}', '\u{1f35}'),
+  ('\u{1f3e}', '\u{1f36}'),
+  ('\u{1f3f}', '\u{1f37}'),
+  ('\u{1f48}', '\u{1f40}'),
+  ('\u{1f49}', '\u{1f41}'),
+  ('\u{1f4a}', '\u{1f42}'),
+  ('\u{1f4b}', '\u{1f43}'),
+  ('\u{1f4c}', '\u{1f44}'),
+  ('\u{1f4d}', '\u{1f45}'),
+  ('\u{1f59}', '\u{1f51}'),
+  ('\u{1f5b}', '\u{1f53}'),
+  ('\u{1f5d}', '\u{1f55}'),
+  ('\u{1f5f}', '\u{1f57}'),
+  ('\u{1f68}', '\u{1f60}'),
+  ('\u{1f69}', '\u{1f61}'),
+  ('\u{1f6a}', '\u{1f62}'),
+  ('\u{1f6b}', '\u{1f63}'),
+  ('\u{1f6c}', '\u{1f64}'),
+  ('\u{1f6d}', '\u{1f65}'),
+  ('\u{1f6e}', '\u{1f66}'),
+  ('\u{1f6f}', '\u{1f67}'),
+  ('\u{1f88}', '\u{1f80}'),
+  ('\u{1f89}', '\u{1f81}'),
+  ('\u{1f8a}', '\u{1f82}'),
+  ('\u{1f8b}', '\u{1f83}'),
+  ('\u{1f8c}', '\u{1f84}'),
+  ('\u{1f8d}', '\u{1f85}'),
+  ('\u{1f8e}', '\u{1f86}'),
+  ('\u{1f8f}', '\u{1f87}'),
+  ('\u{1f98}', '\u{1f90}'),
+  ('\u{1f99}', '\u{1f91}'),
+  ('\u{1f9a}', '\u{1f92}'),
+  ('\u{1f9b}', '\u{1f93}'),
+  ('\u{1f9c}', '\u{1f94}'),
+  ('\u{1f9d}', '\u{1f95}'),
+  ('\u{1f9e}', '\u{1f96}'),
+  ('\u{1f9f}', '\u{1f97}'),
+  ('\u{1fa8}', '\u{1fa0}'),
+  ('\u{1fa9}', '\u{1fa1}'),
+  ('\u{1faa}', '\u{1fa2}'),
+  ('\u{1fab}', '\u{1fa3}'),
+  ('\u{1fac}', '\u{1fa4}'),
+  ('\u{1fad}', '\u{1fa5}'),
+  ('\u{1fae}', '\u{1fa6}'),
+  ('\u{1faf}', '\u{1fa7}'),
+  ('\u{1fb8}', '\u{1fb0}'),
+  ('\u{1fb9}', '\u{1fb1}'),
+  ('\u{1fba}', '\u{1f70}'),
+  ('\u{1fbb}', '\u{1f71}'),
+  ('\u{1fbc}', '\u{1fb3}'),
+  ('\u{1fbe}', '\u{3b9}'),
+  ('\u{1fc8}', '\u{1f72}'),
+  ('\u{1fc9}', '\u{1f73}'),
+  ('\u{1fca}', '\u{1f74}'),
+  ('\u{1fcb}', '\u{1f75}'),
+  ('\u{1fcc}', '\u{1fc3}'),
+  ('\u{1fd3}', '\u{390}'),
+  ('\u{1fd8}', '\u{1fd0}'),
+  ('\u{1fd9}', '\u{1fd1}'),
+  ('\u{1fda}', '\u{1f76}'),
+  ('\u{1fdb}', '\u{1f77}'),
+  ('\u{1fe3}', '\u{3b0}'),
+  ('\u{1fe8}', '\u{1fe0}'),
+  ('\u{1fe9}', '\u{1fe1}'),
+  ('\u{1fea}', '\u{1f7a}'),
+  ('\u{1feb}', '\u{1f7b}'),
+  ('\u{1fec}', '\u{1fe5}'),
+  ('\u{1ff8}', '\u{1f78}'),
+  ('\u{1ff9}', '\u{1f79}'),
+  ('\u{1ffa}', '\u{1f7c}'),
+  ('\u{1ffb}', '\u{1f7d}'),
+  ('\u{1ffc}', '\u{1ff3}'),
+  ('\u{2126}', '\u{3c9}'),
+  ('\u{212a}', 'k'),
+  ('\u{212b}', '\u{e5}'),
+  ('\u{2132}', '\u{214e}'),
+  ('\u{2160}', '\u{2170}'),
+  ('\u{2161}', '\u{2171}'),
+  ('\u{2162}', '\u{2172}'),
+  ('\u{2163}', '\u{2173}'),
+  ('\u{2164}', '\u{2174}'),
+  ('\u{2165}', '\u{2175}'),
+  ('\u{2166}', '\u{2176}'),
+  ('\u{2167}', '\u{2177}'),
+  ('\u{2168}', '\u{2178}'),
+  ('\u{2169}', '\u{2179}'),
+  ('\u{216a}', '\u{217a}'),
+  ('\u{216b}', '\u{217b}'),
+  ('\u{216c}', '\u{217c}'),
+  ('\u{216d}', '\u{217d}'),
+  ('\u{216e}', '\u{217e}'),
+  ('\u{216f}', '\u{217f}'),
+  ('\u{2183}', '\u{2184}'),
+  ('\u{24b6}', '\u{24d0}'),
+  ('\u{24b7}', '\u{24d1}'),
+  ('\u{24b8}', '\u{24d2}'),
+  ('\u{24b9}', '\u{24d3}'),
+  ('\u{24ba}', '\u{24d4}'),
+  ('\u{24bb}', '\u{24d5}'),
+  ('\u{24bc}', '\u{24d6}'),
+  ('\u{24bd}', '\u{24d7}'),
+  ('\u{24be}', '\u{24d8}'),
+  ('\u{24bf}', '\u{24d9}'),
+  ('\u{24c0}', '\u{24da}'),
+  ('\u{24c1}', '\u{24db}'),
+  ('\u{24c2}', '\u{24dc}'),
+  ('\u{24c3}', '\u{24dd}'),
+  ('\u{24c4}', '\u{24de}'),
+  ('\u{24c5}', '\u{24df}'),
+  ('\u{24c6}', '\u{24e0}'),
+  ('\u{24c7}', '\u{24e1}'),
+  ('\u{24c8}', '\u{24e2}'),
+  ('\u{24c9}', '\u{24e3}'),
+  ('\u{24ca}', '\u{24e4}'),
+  ('\u{24cb}', '\u{24e5}'),
+  ('\u{24cc}', '\u{24e6}'),
+  ('\u{24cd}', '\u{24e7}'),
+  ('\u{24ce}', '\u{24e8}'),
+  ('\u{24cf}', '\u{24e9}'),
+  ('\u{2c00}', '\u{2c30}'),
+  ('\u{2c01}', '\u{2c31}'),
+  ('\u{2c02}', '\u{2c32}'),
+  ('\u{2c03}', '\u{2c33}'),
+  ('\u{2c04}', '\u{2c34}'),
+  ('\u{2c05}', '\u{2c35}'),
+  ('\u{2c06}', '\u{2c36}'),
+  ('\u{2c07}', '\u{2c37}'),
+  ('\u{2c08}', '\u{2c38}'),
+  ('\u{2c09}', '\u{2c39}'),
+  ('\u{2c0a}', '\u{2c3a}'),
+  ('\u{2c0b}', '\u{2c3b}'),
+  ('\u{2c0c}', '\u{2c3c}'),
+  ('\u{2c0d}', '\u{2c3d}'),
+  ('\u{2c0e}', '\u{2c3e}'),
+  ('\u{2c0f}', '\u{2c3f}'),
+  ('\u{2c10}', '\u{2c40}'),
+  ('\u{2c11}', '\u{2c41}'),
+  ('\u{2c12}', '\u{2c42}'),
+  ('\u{2c13}', '\u{2c43}'),
+  ('\u{2c14}', '\u{2c44}'),
+  ('\u{2c15}', '\u{2c45}'),
+  ('\u{2c16}', '\u{2c46}'),
+  ('\u{2c17}', '\u{2c47}'),
+  ('\u{2c18}', '\u{2c48}'),
+  ('\u{2c19}', '\u{2c49}'),
+  ('\u{2c1a}', '\u{2c4a}'),
+  ('\u{2c1b}', '\u{2c4b}'),
+  ('\u{2c1c}', '\u{2c4c}'),
+  ('\u{2c1d}', '\u{2c4d}'),
+  ('\u{2c1e}', '\u{2c4e}'),
+  ('\u{2c1f}', '\u{2c4f}'),
+  ('\u{2c20}', '\u{2c50}'),
+  ('\u{2c21}', '\u{2c51}'),
+  ('\u{2c22}', '\u{2c52}'),
+  ('\u{2c23}', '\u{2c53}'),
+  ('\u{2c24}', '\u{2c54}'),
+  ('\u{2c25}', '\u{2c55}'),
+  ('\u{2c26}', '\u{2c56}'),
+  ('\u{2c27}', '\u{2c57}'),
+  ('\u{2c28}', '\u{2c58}'),
+  ('\u{2c29}', '\u{2c59}'),
+  ('\u{2c2a}', '\u{2c5a}'),
+  ('\u{2c2b}', '\u{2c5b}'),
+  ('\u{2c2c}', '\u{2c5c}'),
+  ('\u{2c2d}', '\u{2c5d}'),
+  ('\u{2c2e}', '\u{2c5e}'),
+  ('\u{2c2f}', '\u{2c5f}'),
+  ('\u{2c60}', '\u{2c61}'),
+  ('\u{2c62}', '\u{26b}'),
+  ('\u{2c63}', '\u{1d7d}'),
+  ('\u{2c64}', '\u{27d}'),
+  ('\u{2c67}', '\u{2c68}'),
+  ('\u{2c69}', '\u{2c6a}'),
+  ('\u{2c6b}', '\u{2c6c}'),
+  ('\u{2c6d}', '\u{251}'),
+  ('\u{2c6e}', '\u{271}'),
+  ('\u{2c6f}', '\u{250}'),
+  ('\u{2c70}', '\u{252}'),
+  ('\u{2c72}', '\u{2c73}'),
+  ('\u{2c75}', '\u{2c76}'),
+  ('\u{2c7e}', '\u{23f}'),
+  ('\u{2c7f}', '\u{240}'),
+  ('\u{2c80}', '\u{2c81}'),
+  ('\u{2c82}', '\u{2c83}'),
+  ('\u{2c84}', '\u{2c85}'),
+  ('\u{2c86}', '\u{2c87}'),
+  ('\u{2c88}', '\u{2c89}'),
+  ('\u{2c8a}', '\u{2c8b}'),
+  ('\u{2c8c}', '\u{2c8d}'),
+  ('\u{2c8e}', '\u{2c8f}'),
+  ('\u{2c90}', '\u{2c91}'),
+  ('\u{2c92}', '\u{2c93}'),
+  ('\u{2c94}', '\u{2c95}'),
+  ('\u{2c96}', '\u{2c97}'),
+  ('\u{2c98}', '\u{2c99}'),
+  ('\u{2c9a}', '\u{2c9b}'),
+  ('\u{2c9c}', '\u{2c9d}'),
+  ('\u{2c9e}', '\u{2c9f}'),
+  ('\u{2ca0}', '\u{2ca1}'),
+  ('\u{2ca2}', '\u{2ca3}'),
+  ('\u{2ca4}', '\u{2ca5}'),
+  ('\u{2ca6}', '\u{2ca7}'),
+  ('\u{2ca8}', '\u{2ca9}'),
+  ('\u{2caa}', '\u{2cab}'),
+  ('\u{2cac}', '\u{2cad}'),
+  ('\u{2cae}', '\u{2caf}'),
+  ('\u{2cb0}', '\u{2cb1}'),
+  ('\u{2cb2}', '\u{2cb3}'),
+  ('\u{2cb4}', '\u{2cb5}'),
+  ('\u{2cb6}', '\u{2cb7}'),
+  ('\u{2cb8}', '\u{2cb9}'),
+  ('\u{2cba}', '\u{2cbb}'),
+  ('\u{2cbc}', '\u{2cbd}'),
+  ('\u{2cbe}', '\u{2cbf}'),
+  ('\u{2cc0}', '\u{2cc1}'),
+  ('\u{2cc2}', '\u{2cc3}'),
+  ('\u{2cc4}', '\u{2cc5}'),
+  ('\u{2cc6}', '\u{2cc7}'),
+  ('\u{2cc8}', '\u{2cc9}'),
+  ('\u{2cca}', '\u{2ccb}'),
+  ('\u{2ccc}', '\u{2ccd}'),
+  ('\u{2cce}', '\u{2ccf}'),
+  ('\u{2cd0}', '\u{2cd1}'),
+  ('\u{2cd2}', '\u{2cd3}'),
+  ('\u{2cd4}', '\u{2cd5}'),
+  ('\u{2cd6}', '\u{2cd7}'),
+  ('\u{2cd8}', '\u{2cd9}'),
+  ('\u{2cda}', '\u{2cdb}'),
+  ('\u{2cdc}', '\u{2cdd}'),
+  ('\u{2cde}', '\u{2cdf}'),
+  ('\u{2ce0}', '\u{2ce1}'),
+  ('\u{2ce2}', '\u{2ce3}'),
+  ('\u{2ceb}', '\u{2cec}'),
+  ('\u{2ced}', '\u{2cee}'),
+  ('\u{2cf2}', '\u{2cf3}'),
+  ('\u{a640}', '\u{a641}'),
+  ('\u{a642}', '\u{a643}'),
+  ('\u{a644}', '\u{a645}'),
+  ('\u{a646}', '\u{a647}'),
+  ('\u{a648}', '\u{a649}'),
+  ('\u{a64a}', '\u{a64b}'),
+  ('\u{a64c}', '\u{a64d}'),
+  ('\u{a64e}', '\u{a64f}'),
+  ('\u{a650}', '\u{a651}'),
+  ('\u{a652}', '\u{a653}'),
+  ('\u{a654}', '\u{a655}'),
+  ('\u{a656}', '\u{a657}'),
+  ('\u{a658}', '\u{a659}'),
+  ('\u{a65a}', '\u{a65b}'),
+  ('\u{a65c}', '\u{a65d}'),
+  ('\u{a65e}', '\u{a65f}'),
+  ('\u{a660}', '\u{a661}'),
+  ('\u{a662}', '\u{a663}'),
+  ('\u{a664}', '\u{a665}'),
+  ('\u{a666}', '\u{a667}'),
+  ('\u{a668}', '\u{a669}'),
+  ('\u{a66a}', '\u{a66b}'),
+  ('\u{a66c}', '\u{a66d}'),
+  ('\u{a680}', '\u{a681}'),
+  ('\u{a682}', '\u{a683}'),
+  ('\u{a684}', '\u{a685}'),
+  ('\u{a686}', '\u{a687}'),
+  ('\u{a688}', '\u{a689}'),
+  ('\u{a68a}', '\u{a68b}'),
+  ('\u{a68c}', '\u{a68d}'),
+  ('\u{a68e}', '\u{a68f}'),
+  ('\u{a690}', '\u{a691}'),
+  ('\u{a692}', '\u{a693}'),
+  ('\u{a694}', '\u{a695}'),
+  ('\u{a696}', '\u{a697}'),
+  ('\u{a698}', '\u{a699}'),
+  ('\u{a69a}', '\u{a69b}'),
+  ('\u{a722}', '\u{a723}'),
+  ('\u{a724}', '\u{a725}'),
+  ('\u{a726}', '\u{a727}'),
+  ('\u{a728}', '\u{a729}'),
+  ('\u{a72a}', '\u{a72b}'),
+  ('\u{a72c}', '\u{a72d}'),
+  ('\u{a72e}', '\u{a72f}'),
+  ('\u{a732}', '\u{a733}'),
+  ('\u{a734}', '\u{a735}'),
+  ('\u{a736}', '\u{a737}'),
+  ('\u{a738}', '\u{a739}'),
+  ('\u{a73a}', '\u{a73b}'),
+  ('\u{a73c}', '\u{a73d}'),
+  ('\u{a73e}', '\u{a73f}'),
+  ('\u{a740}', '\u{a741}'),
+  ('\u{a742}', '\u{a743}'),
+  ('\u{a744}', '\u{a745}'),
+  ('\u{a746}', '\u{a747}'),
+  ('\u{a748}', '\u{a749}'),
+  ('\u{a74a}', '\u{a74b}'),
+  ('\u{a74c}', '\u{a74d}'),
+  ('\u{a74e}', '\u{a74f}'),
+  ('\u{a750}', '\u{a751}'),
+  ('\u{a752}', '\u{a753}'),
+  ('\u{a754}', '\u{a755}'),
+  ('\u{a756}', '\u{a757}'),
+  ('\u{a758}', '\u{a759}'),
+  ('\u{a75a}', '\u{a75b}'),
+  ('\u{a75c}', '\u{a75d}'),
+  ('\u{a75e}', '\u{a75f}'),
+  ('\u{a760}', '\u{a761}'),
+  ('\u{a762}', '\u{a763}'),
+  ('\u{a764}', '\u{a765}'),
+  ('\u{a766}', '\u{a767}'),
+  ('\u{a768}', '\u{a769}'),
+  ('\u{a76a}', '\u{a76b}'),
+  ('\u{a76c}', '\u{a76d}'),
+  ('\u{a76e}', '\u{a76f}'),
+  ('\u{a779}', '\u{a77a}'),
+  ('\u{a77b}', '\u{a77c}'),
+  ('\u{a77d}', '\u{1d79}'),
+  ('\u{a77e}', '\u{a77f}'),
+  ('\u{a780}', '\u{a781}'),
+  ('\u{a782}', '\u{a783}'),
+  ('\u{a784}', '\u{a785}'),
+  ('\u{a786}', '\u{a787}'),
+  ('\u{a78b}', '\u{a78c}'),
+  ('\u{a78d}', '\u{265}'),
+  ('\u{a790}', '\u{a791}'),
+  ('\u{a792}', '\u{a793}'),
+  ('\u{a796}', '\u{a797}'),
+  ('\u{a798}', '\u{a799}'),
+  ('\u{a79a}', '\u{a79b}'),
+  ('\u{a79c}', '\u{a79d}'),
+  ('\u{a79e}', '\u{a79f}'),
+  ('\u{a7a0}', '\u{a7a1}'),
+  ('\u{a7a2}', '\u{a7a3}'),
+  ('\u{a7a4}', '\u{a7a5}'),
+  ('\u{a7a6}', '\u{a7a7}'),
+  ('\u{a7a8}', '\u{a7a9}'),
+  ('\u{a7aa}', '\u{266}'),
+  ('\u{a7ab}', '\u{25c}'),
+  ('\u{a7ac}', '\u{261}'),
+  ('\u{a7ad}', '\u{26c}'),
+  ('\u{a7ae}', '\u{26a}'),
+  ('\u{a7b0}', '\u{29e}'),
+  ('\u{a7b1}', '\u{287}'),
+  ('\u{a7b2}', '\u{29d}'),
+  ('\u{a7b3}', '\u{ab53}'),
+  ('\u{a7b4}', '\u{a7b5}'),
+  ('\u{a7b6}', '\u{a7b7}'),
+  ('\u{a7b8}', '\u{a7b9}'),
+  ('\u{a7ba}', '\u{a7bb}'),
+  ('\u{a7bc}', '\u{a7bd}'),
+  ('\u{a7be}', '\u{a7bf}'),
+  ('\u{a7c0}', '\u{a7c1}'),
+  ('\u{a7c2}', '\u{a7c3}'),
+  ('\u{a7c4}', '\u{a794}'),
+  ('\u{a7c5}', '\u{282}'),
+  ('\u{a7c6}', '\u{1d8e}'),
+  ('\u{a7c7}', '\u{a7c8}'),
+  ('\u{a7c9}', '\u{a7ca}'),
+  ('\u{a7cb}', '\u{264}'),
+  ('\u{a7cc}', '\u{a7cd}'),
+  ('\u{a7d0}', '\u{a7d1}'),
+  ('\u{a7d6}', '\u{a7d7}'),
+  ('\u{a7d8}', '\u{a7d9}'),
+  ('\u{a7da}', '\u{a7db}'),
+  ('\u{a7dc}', '\u{19b}'),
+  ('\u{a7f5}', '\u{a7f6}'),
+  ('\u{ab70}', '\u{13a0}'),
+  ('\u{ab71}', '\u{13a1}'),
+  ('\u{ab72}', '\u{13a2}'),
+  ('\u{ab73}', '\u{13a3}'),
+  ('\u{ab74}', '\u{13a4}'),
+  ('\u{ab75}', '\u{13a5}'),
+  ('\u{ab76}', '\u{13a6}'),
+  ('\u{ab77}', '\u{13a7}'),
+  ('\u{ab78}', '\u{13a8}'),
+  ('\u{ab79}', '\u{13a9}'),
+  ('\u{ab7a}', '\u{13aa}'),
+  ('\u{ab7b}', '\u{13ab}'),
+  ('\u{ab7c}', '\u{13ac}'),
+  ('\u{ab7d}', '\u{13ad}'),
+  ('\u{ab7e}', '\u{13ae}'),
+  ('\u{ab7f}', '\u{13af}'),
+  ('\u{ab80}', '\u{13b0}'),
+  ('\u{ab81}', '\u{13b1}'),
+  ('\u{ab82}', '\u{13b2}'),
+  ('\u{ab83}', '\u{13b3}'),
+  ('\u{ab84}', '\u{13b4}'),
+  ('\u{ab85}', '\u{13b5}'),
+  ('\u{ab86}', '\u{13b6}'),
+  ('\u{ab87}', '\u{13b7}'),
+  ('\u{ab88}', '\u{13b8}'),
+  ('\u{ab89}', '\u{13b9}'),
+  ('\u{ab8a}', '\u{13ba}'),
+  ('\u{ab8b}', '\u{13bb}'),
+  ('\u{ab8c}', '\u{13bc}'),
+  ('\u{ab8d}', '\u{13bd}'),
+  ('\u{ab8e}', '\u{13be}'),
+  ('\u{ab8f}', '\u{13bf}'),
+  ('\u{ab90}', '\u{13c0}'),
+  ('\u{ab91}', '\u{13c1}'),
+  ('\u{ab92}', '\u{13c2}'),
+  ('\u{ab93}', '\u{13c3}'),
+  ('\u{ab94}', '\u{13c4}'),
+  ('\u{ab95}', '\u{13c5}'),
+  ('\u{ab96}', '\u{13c6}'),
+  ('\u{ab97}', '\u{13c7}'),
+  ('\u{ab98}', '\u{13c8}'),
+  ('\u{ab99}', '\u{13c9}'),
+  ('\u{ab9a}', '\u{13ca}'),
+  ('\u{ab9b}', '\u{13cb}'),
+  ('\u{ab9c}', '\u{13cc}'),
+  ('\u{ab9d}', '\u{13cd}'),
+  ('\u{ab9e}', '\u{13ce}'),
+  ('\u{ab9f}', '\u{13cf}'),
+  ('\u{aba0}', '\u{13d0}'),
+  ('\u{aba1}', '\u{13d1}'),
+  ('\u{aba2}', '\u{13d2}'),
+  ('\u{aba3}', '\u{13d3}'),
+  ('\u{aba4}', '\u{13d4}'),
+  ('\u{aba5}', '\u{13d5}'),
+  ('\u{aba6}', '\u{13d6}'),
+  ('\u{aba7}', '\u{13d7}'),
+  ('\u{aba8}', '\u{13d8}'),
+  ('\u{aba9}', '\u{13d9}'),
+  ('\u{abaa}', '\u{13da}'),
+  ('\u{abab}', '\u{13db}'),
+  ('\u{abac}', '\u{13dc}'),
+  ('\u{abad}', '\u{13dd}'),
+  ('\u{abae}', '\u{13de}'),
+  ('\u{abaf}', '\u{13df}'),
+  ('\u{abb0}', '\u{13e0}'),
+  ('\u{abb1}', '\u{13e1}'),
+  ('\u{abb2}', '\u{13e2}'),
+  ('\u{abb3}', '\u{13e3}'),
+  ('\u{abb4}', '\u{13e4}'),
+  ('\u{abb5}', '\u{13e5}'),
+  ('\u{abb6}', '\u{13e6}'),
+  ('\u{abb7}', '\u{13e7}'),
+  ('\u{abb8}', '\u{13e8}'),
+  ('\u{abb9}', '\u{13e9}'),
+  ('\u{abba}', '\u{13ea}'),
+  ('\u{abbb}', '\u{13eb}'),
+  ('\u{abbc}', '\u{13ec}'),
+  ('\u{abbd}', '\u{13ed}'),
+  ('\u{abbe}', '\u{13ee}'),
+  ('\u{abbf}', '\u{13ef}'),
+  ('\u{fb05}', '\u{fb06}'),
+  ('\u{ff21}', '\u{ff41}'),
+  ('\u{ff22}', '\u{ff42}'),
+  ('\u{ff23}', '\u{ff43}'),
+  ('\u{ff24}', '\u{ff44}'),
+  ('\u{ff25}', '\u{ff45}'),
+  ('\u{ff26}', '\u{ff46}'),
+  ('\u{ff27}', '\u{ff47}'),
+  ('\u{ff28}', '\u{ff48}'),
+  ('\u{ff29}', '\u{ff49}'),
+  ('\u{ff2a}', '\u{ff4a}'),
+  ('\u{ff2b}', '\u{ff4b}'),
+  ('\u{ff2c}', '\u{ff4c}'),
+  ('\u{ff2d}', '\u{ff4d}'),
+  ('\u{ff2e}', '\u{ff4e}'),
+  ('\u{ff2f}', '\u{ff4f}'),
+  ('\u{ff30}', '\u{ff50}'),
+  ('\u{ff31}', '\u{ff51}'),
+  ('\u{ff32}', '\u{ff52}'),
+  ('\u{ff33}', '\u{ff53}'),
+  ('\u{ff34}', '\u{ff54}'),
+  ('\u{ff35}', '\u{ff55}'),
+  ('\u{ff36}', '\u{ff56}'),
+  ('\u{ff37}', '\u{ff57}'),
+  ('\u{ff38}', '\u{ff58}'),
+  ('\u{ff39}', '\u{ff59}'),
+  ('\u{ff3a}', '\u{ff5a}'),
+  ('\u{10400}', '\u{10428}'),
+  ('\u{10401}', '\u{10429}'),
+  ('\u{10402}', '\u{1042a}'),
+  ('\u{10403}', '\u{1042b}'),
+  ('\u{10404}', '\u{1042c}'),
+  ('\u{10405}', '\u{1042d}'),
+  ('\u{10406}', '\u{1042e}'),
+  ('\u{10407}', '\u{1042f}'),
+  ('\u{10408}', '\u{10430}'),
+  ('\u{10409}', '\u{10431}'),
+  ('\u{1040a}', '\u{10432}'),
+  ('\u{1040b}', '\u{10433}'),
+  ('\u{1040c}', '\u{10434}'),
+  ('\u{1040d}', '\u{10435}'),
+  ('\u{1040e}', '\u{10436}'),
+  ('\u{1040f}', '\u{10437}'),
+  ('\u{10410}', '\u{10438}'),
+  ('\u{10411}', '\u{10439}'),
+  ('\u{10412}', '\u{1043a}'),
+  ('\u{10413}', '\u{1043b}'),
+  ('\u{10414}', '\u{1043c}'),
+  ('\u{10415}', '\u{1043d}'),
+  ('\u{10416}', '\u{1043e}'),
+  ('\u{10417}', '\u{1043f}'),
+  ('\u{10418}', '\u{10440}'),
+  ('\u{10419}', '\u{10441}'),
+  ('\u{1041a}', '\u{10442}'),
+  ('\u{1041b}', '\u{10443}'),
+  ('\u{1041c}', '\u{10444}'),
+  ('\u{1041d}', '\u{10445}'),
+  ('\u{1041e}', '\u{10446}'),
+  ('\u{1041f}', '\u{10447}'),
+  ('\u{10420}', '\u{10448}'),
+  ('\u{10421}', '\u{10449}'),
+  ('\u{10422}', '\u{1044a}'),
+  ('\u{10423}', '\u{1044b}'),
+  ('\u{10424}', '\u{1044c}'),
+  ('\u{10425}', '\u{1044d}'),
+  ('\u{10426}', '\u{1044e}'),
+  ('\u{10427}', '\u{1044f}'),
+  ('\u{104b0}', '\u{104d8}'),
+  ('\u{104b1}', '\u{104d9}'),
+  ('\u{104b2}', '\u{104da}'),
+  ('\u{104b3}', '\u{104db}'),
+  ('\u{104b4}', '\u{104dc}'),
+  ('\u{104b5}', '\u{104dd}'),
+  ('\u{104b6}', '\u{104de}'),
+  ('\u{104b7}', '\u{104df}'),
+  ('\u{104b8}', '\u{104e0}'),
+  ('\u{104b9}', '\u{104e1}'),
+  ('\u{104ba}', '\u{104e2}'),
+  ('\u{104bb}', '\u{104e3}'),
+  ('\u{104bc}', '\u{104e4}'),
+  ('\u{104bd}', '\u{104e5}'),
+  ('\u{104be}', '\u{104e6}'),
+  ('\u{104bf}', '\u{104e7}'),
+  ('\u{104c0}', '\u{104e8}'),
+  ('\u{104c1}', '\u{104e9}'),
+  ('\u{104c2}', '\u{104ea}'),
+  ('\u{104c3}', '\u{104eb}'),
+  ('\u{104c4}', '\u{104ec}'),
+  ('\u{104c5}', '\u{104ed}'),
+  ('\u{104c6}', '\u{104ee}'),
+  ('\u{104c7}', '\u{104ef}'),
+  ('\u{104c8}', '\u{104f0}'),
+  ('\u{104c9}', '\u{104f1}'),
+  ('\u{104ca}', '\u{104f2}'),
+  ('\u{104cb}', '\u{104f3}'),
+  ('\u{104cc}', '\u{104f4}'),
+  ('\u{104cd}', '\u{104f5}'),
+  ('\u{104ce}', '\u{104f6}'),
+  ('\u{104cf}', '\u{104f7}'),
+  ('\u{104d0}', '\u{104f8}'),
+  ('\u{104d1}', '\u{104f9}'),
+  ('\u{104d2}', '\u{104fa}'),
+  ('\u{104d3}', '\u{104fb}'),
+  ('\u{10570}', '\u{10597}'),
+  ('\u{10571}', '\u{10598}'),
+  ('\u{10572}', '\u{10599}'),
+  ('\u{10573}', '\u{1059a}'),
+  ('\u{10574}', '\u{1059b}'),
+  ('\u{10575}', '\u{1059c}'),
+  ('\u{10576}', '\u{1059d}'),
+  ('\u{10577}', '\u{1059e}'),
+  ('\u{10578}', '\u{1059f}'),
+  ('\u{10579}', '\u{105a0}'),
+  ('\u{1057a}', '\u{105a1}'),
+  ('\u{1057c}', '\u{105a3}'),
+  ('\u{1057d}', '\u{105a4}'),
+  ('\u{1057e}', '\u{105a5}'),
+  ('\u{1057f}', '\u{105a6}'),
+  ('\u{10580}', '\u{105a7}'),
+  ('\u{10581}', '\u{105a8}'),
+  ('\u{10582}', '\u{105a9}'),
+  ('\u{10583}', '\u{105aa}'),
+  ('\u{10584}', '\u{105ab}'),
+  ('\u{10585}', '\u{105ac}'),
+  ('\u{10586}', '\u{105ad}'),
+  ('\u{10587}', '\u{105ae}'),
+  ('\u{10588}', '\u{105af}'),
+  ('\u{10589}', '\u{105b0}'),
+  ('\u{1058a}', '\u{105b1}'),
+  ('\u{1058c}', '\u{105b3}'),
+  ('\u{1058d}', '\u{105b4}'),
+  ('\u{1058e}', '\u{105b5}'),
+  ('\u{1058f}', '\u{105b6}'),
+  ('\u{10590}', '\u{105b7}'),
+  ('\u{10591}', '\u{105b8}'),
+  ('\u{10592}', '\u{105b9}'),
+  ('\u{10594}', '\u{105bb}'),
+  ('\u{10595}', '\u{105bc}'),
+  ('\u{10c80}', '\u{10cc0}'),
+  ('\u{10c81}', '\u{10cc1}'),
+  ('\u{10c82}', '\u{10cc2}'),
+  ('\u{10c83}', '\u{10cc3}'),
+  ('\u{10c84}', '\u{10cc4}'),
+  ('\u{10c85}', '\u{10cc5}'),
+  ('\u{10c86}', '\u{10cc6}'),
+  ('\u{10c87}', '\u{10cc7}'),
+  ('\u{10c88}', '\u{10cc8}'),
+  ('\u{10c89}', '\u{10cc9}'),
+  ('\u{10c8a}', '\u{10cca}'),
+  ('\u{10c8b}', '\u{10ccb}'),
+  ('\u{10c8c}', '\u{10ccc}'),
+  ('\u{10c8d}', '\u{10ccd}'),
+  ('\u{10c8e}', '\u{10cce}'),
+  ('\u{10c8f}', '\u{10ccf}'),
+  ('\u{10c90}', '\u{10cd0}'),
+  ('\u{10c91}', '\u{10cd1}'),
+  ('\u{10c92}', '\u{10cd2}'),
+  ('\u{10c93}', '\u{10cd3}'),
+  ('\u{10c94}', '\u{10cd4}'),
+  ('\u{10c95}', '\u{10cd5}'),
+  ('\u{10c96}', '\u{10cd6}'),
+  ('\u{10c97}', '\u{10cd7}'),
+  ('\u{10c98}', '\u{10cd8}'),
+  ('\u{10c99}', '\u{10cd9}'),
+  ('\u{10c9a}', '\u{10cda}'),
+  ('\u{10c9b}', '\u{10cdb}'),
+  ('\u{10c9c}', '\u{10cdc}'),
+  ('\u{10c9d}', '\u{10cdd}'),
+  ('\u{10c9e}', '\u{10cde}'),
+  ('\u{10c9f}', '\u{10cdf}'),
+  ('\u{10ca0}', '\u{10ce0}'),
+  ('\u{10ca1}', '\u{10ce1}'),
+  ('\u{10ca2}', '\u{10ce2}'),
+  ('\u{10ca3}', '\u{10ce3}'),
+  ('\u{10ca4}', '\u{10ce4}'),
+  ('\u{10ca5}', '\u{10ce5}'),
+  ('\u{10ca6}', '\u{10ce6}'),
+  ('\u{10ca7}', '\u{10ce7}'),
+  ('\u{10ca8}', '\u{10ce8}'),
+  ('\u{10ca9}', '\u{10ce9}'),
+  ('\u{10caa}', '\u{10cea}'),
+  ('\u{10cab}', '\u{10ceb}'),
+  ('\u{10cac}', '\u{10cec}'),
+  ('\u{10cad}', '\u{10ced}'),
+  ('\u{10cae}', '\u{10cee}'),
+  ('\u{10caf}', '\u{10cef}'),
+  ('\u{10cb0}', '\u{10cf0}'),
+  ('\u{10cb1}', '\u{10cf1}'),
+  ('\u{10cb2}', '\u{10cf2}'),
+  ('\u{10d50}', '\u{10d70}'),
+  ('\u{10d51}', '\u{10d71}'),
+  ('\u{10d52}', '\u{10d72}'),
+  ('\u{10d53}', '\u{10d73}'),
+  ('\u{10d54}', '\u{10d74}'),
+  ('\u{10d55}', '\u{10d75}'),
+  ('\u{10d56}', '\u{10d76}'),
+  ('\u{10d57}', '\u{10d77}'),
+  ('\u{10d58}', '\u{10d78}'),
+  ('\u{10d59}', '\u{10d79}'),
+  ('\u{10d5a}', '\u{10d7a}'),
+  ('\u{10d5b}', '\u{10d7b}'),
+  ('\u{10d5c}', '\u{10d7c}'),
+  ('\u{10d5d}', '\u{10d7d}'),
+  ('\u{10d5e}', '\u{10d7e}'),
+  ('\u{10d5f}', '\u{10d7f}'),
+  ('\u{10d60}', '\u{10d80}'),
+  ('\u{10d61}', '\u{10d81}'),
+  ('\u{10d62}', '\u{10d82}'),
+  ('\u{10d63}', '\u{10d83}'),
+  ('\u{10d64}', '\u{10d84}'),
+  ('\u{10d65}', '\u{10d85}'),
+  ('\u{118a0}', '\u{118c0}'),
+  ('\u{118a1}', '\u{118c1}'),
+  ('\u{118a2}', '\u{118c2}'),
+  ('\u{118a3}', '\u{118c3}'),
+  ('\u{118a4}', '\u{118c4}'),
+  ('\u{118a5}', '\u{118c5}'),
+  ('\u{118a6}', '\u{118c6}'),
+  ('\u{118a7}', '\u{118c7}'),
+  ('\u{118a8}', '\u{118c8}'),
+  ('\u{118a9}', '\u{118c9}'),
+  ('\u{118aa}', '\u{118ca}'),
+  ('\u{118ab}', '\u{118cb}'),
+  ('\u{118ac}', '\u{118cc}'),
+  ('\u{118ad}', '\u{118cd}'),
+  ('\u{118ae}', '\u{118ce}'),
+  ('\u{118af}', '\u{118cf}'),
+  ('\u{118b0}', '\u{118d0}'),
+  ('\u{118b1}', '\u{118d1}'),
+  ('\u{118b2}', '\u{118d2}'),
+  ('\u{118b3}', '\u{118d3}'),
+  ('\u{118b4}', '\u{118d4}'),
+  ('\u{118b5}', '\u{118d5}'),
+  ('\u{118b6}', '\u{118d6}'),
+  ('\u{118b7}', '\u{118d7}'),
+  ('\u{118b8}', '\u{118d8}'),
+  ('\u{118b9}', '\u{118d9}'),
+  ('\u{118ba}', '\u{118da}'),
+  ('\u{118bb}', '\u{118db}'),
+  ('\u{118bc}', '\u{118dc}'),
+  ('\u{118bd}', '\u{118dd}'),
+  ('\u{118be}', '\u{118de}'),
+  ('\u{118bf}', '\u{118df}'),
+  ('\u{16e40}', '\u{16e60}'),
+  ('\u{16e41}', '\u{16e61}'),
+  ('\u{16e42}', '\u{16e62}'),
+  ('\u{16e43}', '\u{16e63}'),
+  ('\u{16e44}', '\u{16e64}'),
+  ('\u{16e45}', '\u{16e65}'),
+  ('\u{16e46}', '\u{16e66}'),
+  ('\u{16e47}', '\u{16e67}'),
+  ('\u{16e48}', '\u{16e68}'),
+  ('\u{16e49}', '\u{16e69}'),
+  ('\u{16e4a}', '\u{16e6a}'),
+  ('\u{16e4b}', '\u{16e6b}'),
+  ('\u{16e4c}', '\u{16e6c}'),
+  ('\u{16e4d}', '\u{16e6d}'),
+  ('\u{16e4e}', '\u{16e6e}'),
+  ('\u{16e4f}', '\u{16e6f}'),
+  ('\u{16e50}', '\u{16e70}'),
+  ('\u{16e51}', '\u{16e71}'),
+  ('\u{16e52}', '\u{16e72}'),
+  ('\u{16e53}', '\u{16e73}'),
+  ('\u{16e54}', '\u{16e74}'),
+  ('\u{16e55}', '\u{16e75}'),
+  ('\u{16e56}', '\u{16e76}'),
+  ('\u{16e57}', '\u{16e77}'),
+  ('\u{16e58}', '\u{16e78}'),
+  ('\u{16e59}', '\u{16e79}'),
+  ('\u{16e5a}', '\u{16e7a}'),
+  ('\u{16e5b}', '\u{16e7b}'),
+  ('\u{16e5c}', '\u{16e7c}'),
+  ('\u{16e5d}', '\u{16e7d}'),
+  ('\u{16e5e}', '\u{16e7e}'),
+  ('\u{16e5f}', '\u{16e7f}'),
+  ('\u{1e900}', '\u{1e922}'),
+  ('\u{1e901}', '\u{1e923}'),
+  ('\u{1e902}', '\u{1e924}'),
+  ('\u{1e903}', '\u{1e925}'),
+  ('\u{1e904}', '\u{1e926}'),
+  ('\u{1e905}', '\u{1e927}'),
+  ('\u{1e906}', '\u{1e928}'),
+  ('\u{1e907}', '\u{1e929}'),
+  ('\u{1e908}', '\u{1e92a}'),
+  ('\u{1e909}', '\u{1e92b}'),
+  ('\u{1e90a}', '\u{1e92c}'),
+  ('\u{1e90b}', '\u{1e92d}'),
+  ('\u{1e90c}', '\u{1e92e}'),
+  ('\u{1e90d}', '\u{1e92f}'),
+  ('\u{1e90e}', '\u{1e930}'),
+  ('\u{1e90f}', '\u{1e931}'),
+  ('\u{1e910}', '\u{1e932}'),
+  ('\u{1e911}', '\u{1e933}'),
+  ('\u{1e912}', '\u{1e934}'),
+  ('\u{1e913}', '\u{1e935}'),
+  ('\u{1e914}', '\u{1e936}'),
+  ('\u{1e915}', '\u{1e937}'),
+  ('\u{1e916}', '\u{1e938}'),
+  ('\u{1e917}', '\u{1e939}'),
+  ('\u{1e918}', '\u{1e93a}'),
+  ('\u{1e919}', '\u{1e93b}'),
+  ('\u{1e91a}', '\u{1e93c}'),
+  ('\u{1e91b}', '\u{1e93d}'),
+  ('\u{1e91c}', '\u{1e93e}'),
+  ('\u{1e91d}', '\u{1e93f}'),
+  ('\u{1e91e}', '\u{1e940}'),
+  ('\u{1e91f}', '\u{1e941}'),
+  ('\u{1e920}', '\u{1e942}'),
+  ('\u{1e921}', '\u{1e943}'),
+];
+pub const INVERSE_CASE_FOLDING_TABLE: &[(char, char)] = &[
+  ('a', 'A'),
+  ('b', 'B'),
+  ('c', 'C'),
+  ('d', 'D'),
+  ('e', 'E'),
+  ('f', 'F'),
+  ('g', 'G'),
+  ('h', 'H'),
+  ('i', 'I'),
+  ('j', 'J'),
+  ('k', 'K'),
+  ('k', '\u{212a}'),
+  ('l', 'L'),
+  ('m', 'M'),
+  ('n', 'N'),
+  ('o', 'O'),
+  ('p', 'P'),
+  ('q', 'Q'),
+  ('r', 'R'),
+  ('s', 'S'),
+  ('s', '\u{17f}'),
+  ('t', 'T'),
+  ('u', 'U'),
+  ('v', 'V'),
+  ('w', 'W'),
+  ('x', 'X'),
+  ('y', 'Y'),
+  ('z', 'Z'),
+  ('\u{e0}', '\u{c0}'),
+  ('\u{e1}', '\u{c1}'),
+  ('\u{e2}', '\u{c2}'),
+  ('\u{e3}', '\u{c3}'),
+  ('\u{e4}', '\u{c4}'),
+  ('\u{e5}', '\u{c5}'),
+  ('\u{e5}', '\u{212b}'),
+  ('\u{e6}', '\u{c6}'),
+  ('\u{e7}', '\u{c7}'),
+  ('\u{e8}', '\u{c8}'),
+  ('\u{e9}', '\u{c9}'),
+  ('\u{ea}', '\u{ca}'),
+  ('\u{eb}', '\u{cb}'),
+  ('\u{ec}', '\u{cc}'),
+  ('\u{ed}', '\u{cd}'),
+  ('\u{ee}', '\u{ce}'),
+  ('\u{ef}', '\u{cf}'),
+  ('\u{f0}', '\u{d0}'),
+  ('\u{f1}', '\u{d1}'),
+  ('\u{f2}', '\u{d2}'),
+  ('\u{f3}', '\u{d3}'),
+  ('\u{f4}', '\u{d4}'),
+  ('\u{f5}', '\u{d5}'),
+  ('\u{f6}', '\u{d6}'),
+  ('\u{f8}', '\u{d8}'),
+  ('\u{f9}', '\u{d9}'),
+  ('\u{fa}', '\u{da}'),
+  ('\u{fb}', '\u{db}'),
+  ('\u{fc}', '\u{dc}'),
+  ('\u{fd}', '\u{dd}'),
+  ('\u{fe}', '\u{de}'),
+  ('\u{ff}', '\u{178}'),
+  ('\u{101}', '\u{100}'),
+  ('\u{103}', '\u{102}'),
+  ('\u{105}', '\u{104}'),
+  ('\u{107}', '\u{106}'),
+  ('\u{109}', '\u{108}'),
+  ('\u{10b}', '\u{10a}'),
+  ('\u{10d}', '\u{10c}'),
+  ('\u{10f}', '\u{10e}'),
+  ('\u{111}', '\u{110}'),
+  ('\u{113}', '\u{112}'),
+  ('\u{115}', '\u{114}'),
+  ('\u{117}', '\u{116}'),
+  ('\u{119}', '\u{118}'),
+  ('\u{11b}', '\u{11a}'),
+  ('\u{11d}', '\u{11c}'),
+  ('\u{11f}', '\u{11e}'),
+  ('\u{121}', '\u{120}'),
+  ('\u{123}', '\u{122}'),
+  ('\u{125}', '\u{124}'),
+  ('\u{127}', '\u{126}'),
+  ('\u{129}', '\u{128}'),
+  ('\u{12b}', '\u{12a}'),
+  ('\u{12d}', '\u{12c}'),
+  ('\u{12f}', '\u{12e}'),
+  ('\u{133}', '\u{132}'),
+  ('\u{135}', '\u{134}'),
+  ('\u{137}', '\u{136}'),
+  ('\u{13a}', '\u{139}'),
+  ('\u{13c}', '\u{13b}'),
+  ('\u{13e}', '\u{13d}'),
+  ('\u{140}', '\u{13f}'),
+  ('\u{142}', '\u{141}'),
+  ('\u{144}', '\u{143}'),
+  ('\u{146}', '\u{145}'),
+  ('\u{148}', '\u{147}'),
+  ('\u{14b}', '\u{14a}'),
+  ('\u{14d}', '\u{14c}'),
+  ('\u{14f}', '\u{14e}'),
+  ('\u{151}', '\u{150}'),
+  ('\u{153}', '\u{152}'),
+  ('\u{155}', '\u{154}'),
+  ('\u{157}', '\u{156}'),
+  ('\u{159}', '\u{158}'),
+  ('\u{15b}', '\u{15a}'),
+  ('\u{15d}', '\u{15c}'),
+  ('\u{15f}', '\u{15e}'),
+  ('\u{161}', '\u{160}'),
+  ('\u{163}', '\u{162}'),
+  ('\u{165}', '\u{164}'),
+  ('\u{167}', '\u{166}'),
+  ('\u{169}', '\u{168}'),
+  ('\u{16b}', '\u{16a}'),
+  ('\u{16d}', '\u{16c}'),
+  ('\u{16f}', '\u{16e}'),
+  ('\u{171}', '\u{170}'),
+  ('\u{173}', '\u{172}'),
+  ('\u{175}', '\u{174}'),
+  ('\u{177}', '\u{176}'),
+  ('\u{17a}', '\u{179}'),
+  ('\u{17c}', '\u{17b}'),
+  ('\u{17e}', '\u{17d}'),
+  ('\u{180}', '\u{243}'),
+  ('\u{183}', '\u{182}'),
+  ('\u{185}', '\u{184}'),
+  ('\u{188}', '\u{187}'),
+  ('\u{18c}', '\u{18b}'),
+  ('\u{192}', '\u{191}'),
+  ('\u{195}', '\u{1f6}'),
+  ('\u{199}', '\u{198}'),
+  ('\u{19a}', '\u{23d}'),
+  ('\u{19b}', '\u{a7dc}'),
+  ('\u{19e}', '\u{220}'),
+  ('\u{1a1}', '\u{1a0}'),
+  ('\u{1a3}', '\u{1a2}'),
+  ('\u{1a5}', '\u{1a4}'),
+  ('\u{1a8}', '\u{1a7}'),
+  ('\u{1ad}', '\u{1ac}'),
+  ('\u{1b0}', '\u{1af}'),
+  ('\u{1b4}', '\u{1b3}'),
+  ('\u{1b6}', '\u{1b5}'),
+  ('\u{1b9}', '\u{1b8}'),
+  ('\u{1bd}', '\u{1bc}'),
+  ('\u{1bf}', '\u{1f7}'),
+  ('\u{1c6}', '\u{1c4}'),
+  ('\u{1c6}', '\u{1c5}'),
+  ('\u{1c9}', '\u{1c7}'),
+  ('\u{1c9}', '\u{1c8}'),
+  ('\u{1cc}', '\u{1ca}'),
+  ('\u{1cc}', '\u{1cb}'),
+  ('\u{1ce}', '\u{1cd}'),
+  ('\u{1d0}', '\u{1cf}'),
+  ('\u{1d2}', '\u{1d1}'),
+  ('\u{1d4}', '\u{1d3}'),
+  ('\u{1d6}', '\u{1d5}'),
+  ('\u{1d8}', '\u{1d7}'),
+  ('\u{1da}', '\u{1d9}'),
+  ('\u{1dc}', '\u{1db}'),
+  ('\u{1dd}', '\u{18e}'),
+  ('\u{1df}', '\u{1de}'),
+  ('\u{1e1}', '\u{1e0}'),
+  ('\u{1e3}', '\u{1e2}'),
+  ('\u{1e5}', '\u{1e4}'),
+  ('\u{1e7}', '\u{1e6}'),
+  ('\u{1e9}', '\u{1e8}'),
+  ('\u{1eb}', '\u{1ea}'),
+  ('\u{1ed}', '\u{1ec}'),
+  ('\u{1ef}', '\u{1ee}'),
+  ('\u{1f3}', '\u{1f1}'),
+  ('\u{1f3}', '\u{1f2}'),
+  ('\u{1f5}', '\u{1f4}'),
+  ('\u{1f9}', '\u{1f8}'),
+  ('\u{1fb}', '\u{1fa}'),
+  ('\u{1fd}', '\u{1fc}'),
+  ('\u{1ff}', '\u{1fe}'),
+  ('\u{201}', '\u{200}'),
+  ('\u{203}', '\u{202}'),
+  ('\u{205}', '\u{204}'),
+  ('\u{207}', '\u{206}'),
+  ('\u{209}', '\u{208}'),
+  ('\u{20b}', '\u{20a}'),
+  ('\u{20d}', '\u{20c}'),
+  ('\u{20f}', '\u{20e}'),
+  ('\u{211}', '\u{210}'),
+  ('\u{213}', '\u{212}'),
+  ('\u{215}', '\u{214}'),
+  ('\u{217}', '\u{216}'),
+  ('\u{219}', '\u{218}'),
+  ('\u{21b}', '\u{21a}'),
+  ('\u{21d}', '\u{21c}'),
+  ('\u{21f}', '\u{21e}'),
+  ('\u{223}', '\u{222}'),
+  ('\u{225}', '\u{224}'),
+  ('\u{227}', '\u{226}'),
+  ('\u{229}', '\u{228}'),
+  ('\u{22b}', '\u{22a}'),
+  ('\u{22d}', '\u{22c}'),
+  ('\u{22f}', '\u{22e}'),
+  ('\u{231}', '\u{230}'),
+  ('\u{233}', '\u{232}'),
+  ('\u{23c}', '\u{23b}'),
+  ('\u{23f}', '\u{2c7e}'),
+  ('\u{240}', '\u{2c7f}'),
+  ('\u{242}', '\u{241}'),
+  ('\u{247}', '\u{246}'),
+  ('\u{249}', '\u{248}'),
+  ('\u{24b}', '\u{24a}'),
+  ('\u{24d}', '\u{24c}'),
+  ('\u{24f}', '\u{24e}'),
+  ('\u{250}', '\u{2c6f}'),
+  ('\u{251}', '\u{2c6d}'),
+  ('\u{252}', '\u{2c70}'),
+  ('\u{253}', '\u{181}'),
+  ('\u{254}', '\u{186}'),
+  ('\u{256}', '\u{189}'),
+  ('\u{257}', '\u{18a}'),
+  ('\u{259}', '\u{18f}'),
+  ('\u{25b}', '\u{190}'),
+  ('\u{25c}', '\u{a7ab}'),
+  ('\u{260}', '\u{193}'),
+  ('\u{261}', '\u{a7ac}'),
+  ('\u{263}', '\u{194}'),
+  ('\u{264}', '\u{a7cb}'),
+  ('\u{265}', '\u{a78d}'),
+  ('\u{266}', '\u{a7aa}'),
+  ('\u{268}', '\u{197}'),
+  ('\u{269}', '\u{196}'),
+  ('\u{26a}', '\u{a7ae}'),
+  ('\u{26b}', '\u{2c62}'),
+  ('\u{26c}', '\u{a7ad}'),
+  ('\u{26f}', '\u{19c}'),
+  ('\u{271}', '\u{2c6e}'),
+  ('\u{272}', '\u{19d}'),
+  ('\u{275}', '\u{19f}'),
+  ('\u{27d}', '\u{2c64}'),
+  ('\u{280}', '\u{1a6}'),
+  ('\u{282}', '\u{a7c5}'),
+  ('\u{283}', '\u{1a9}'),
+  ('\u{287}', '\u{a7b1}'),
+  ('\u{288}', '\u{1ae}'),
+  ('\u{289}', '\u{244}'),
+  ('\u{28a}', '\u{1b1}'),
+  ('\u{28b}', '\u{1b2}'),
+  ('\u{28c}', '\u{245}'),
+  ('\u{292}', '\u{1b7}'),
+  ('\u{29d}', '\u{a7b2}'),
+  ('\u{29e}', '\u{a7b0}'),
+  ('\u{371}', '\u{370}'),
+  ('\u{373}', '\u{372}'),
+  ('\u{377}', '\u{376}'),
+  ('\u{37b}', '\u{3fd}'),
+  ('\u{37c}', '\u{3fe}'),
+  ('\u{37d}', '\u{3ff}'),
+  ('\u{3ac}', '\u{386}'),
+  ('\u{3ad}', '\u{388}'),
+  ('\u{3ae}', '\u{389}'),
+  ('\u{3af}', '\u{38a}'),
+  ('\u{3b1}', '\u{391}'),
+  ('\u{3b2}', '\u{392}'),
+  ('\u{3b2}', '\u{3d0}'),
+  ('\u{3b3}', '\u{393}'),
+  ('\u{3b4}', '\u{394}'),
+  ('\u{3b5}', '\u{395}'),
+  ('\u{3b5}', '\u{3f5}'),
+  ('\u{3b6}', '\u{396}'),
+  ('\u{3b7}', '\u{397}'),
+  ('\u{3b8}', '\u{398}'),
+  ('\u{3b8}', '\u{3d1}'),
+  ('\u{3b8}', '\u{3f4}'),
+  ('\u{3b9}', '\u{345}'),
+  ('\u{3b9}', '\u{399}'),
+  ('\u{3b9}', '\u{1fbe}'),
+  ('\u{3ba}', '\u{39a}'),
+  ('\u{3ba}', '\u{3f0}'),
+  ('\u{3bb}', '\u{39b}'),
+  ('\u{3bc}', '\u{b5}'),
+  ('\u{3bc}', '\u{39c}'),
+  ('\u{3bd}', '\u{39d}'),
+  ('\u{3be}', '\u{39e}'),
+  ('\u{3bf}', '\u{39f}'),
+  ('\u{3c0}', '\u{3a0}'),
+  ('\u{3c0}', '\u{3d6}'),
+  ('\u{3c1}', '\u{3a1}'),
+  ('\u{3c1}', '\u{3f1}'),
+  ('\u{3c3}', '\u{3a3}'),
+  ('\u{3c3}', '\u{3c2}'),
+  ('\u{3c4}', '\u{3a4}'),
+  ('\u{3c5}', '\u{3a5}'),
+  ('\u{3c6}', '\u{3a6}'),
+  ('\u{3c6}', '\u{3d5}'),
+  ('\u{3c7}', '\u{3a7}'),
+  ('\u{3c8}', '\u{3a8}'),
+  ('\u{3c9}', '\u{3a9}'),
+  ('\u{3c9}', '\u{2126}'),
+  ('\u{3ca}', '\u{3aa}'),
+  ('\u{3cb}', '\u{3ab}'),
+  ('\u{3cc}', '\u{38c}'),
+  ('\u{3cd}', '\u{38e}'),
+  ('\u{3ce}', '\u{38f}'),
+  ('\u{3d7}', '\u{3cf}'),
+  ('\u{3d9}', '\u{3d8}'),
+  ('\u{3db}', '\u{3da}'),
+  ('\u{3dd}', '\u{3dc}'),
+  ('\u{3df}', '\u{3de}'),
+  ('\u{3e1}', '\u{3e0}'),
+  ('\u{3e3}', '\u{3e2}'),
+  ('\u{3e5}', '\u{3e4}'),
+  ('\u{3e7}', '\u{3e6}'),
+  ('\u{3e9}', '\u{3e8}'),
+  ('\u{3eb}', '\u{3ea}'),
+  ('\u{3ed}', '\u{3ec}'),
+  ('\u{3ef}', '\u{3ee}'),
+  ('\u{3f2}', '\u{3f9}'),
+  ('\u{3f3}', '\u{37f}'),
+  ('\u{3f8}', '\u{3f7}'),
+  ('\u{3fb}', '\u{3fa}'),
+  ('\u{430}', '\u{410}'),
+  ('\u{431}', '\u{411}'),
+  ('\u{432}', '\u{412}'),
+  ('\u{432}', '\u{1c80}'),
+  ('\u{433}', '\u{413}'),
+  ('\u{434}', '\u{414}'),
+  ('\u{434}', '\u{1c81}'),
+  ('\u{435}', '\u{415}'),
+  ('\u{436}', '\u{416}'),
+  ('\u{437}', '\u{417}'),
+  ('\u{438}', '\u{418}'),
+  ('\u{439}', '\u{419}'),
+  ('\u{43a}', '\u{41a}'),
+  ('\u{43b}', '\u{41b}'),
+  ('\u{43c}', '\u{41c}'),
+  ('\u{43d}', '\u{41d}'),
+  ('\u{43e}', '\u{41e}'),
+  ('\u{43e}', '\u{1c82}'),
+  ('\u{43f}', '\u{41f}'),
+  ('\u{440}', '\u{420}'),
+  ('\u{441}', '\u{421}'),
+  ('\u{441}', '\u{1c83}'),
+  ('\u{442}', '\u{422}'),
+  ('\u{442}', '\u{1c84}'),
+  ('\u{442}', '\u{1c85}'),
+  ('\u{443}', '\u{423}'),
+  ('\u{444}', '\u{424}'),
+  ('\u{445}', '\u{425}'),
+  ('\u{446}', '\u{426}'),
+  ('\u{447}', '\u{427}'),
+  ('\u{448}', '\u{428}'),
+  ('\u{449}', '\u{429}'),
+  ('\u{44a}', '\u{42a}'),
+  ('\u{44a}', '\u{1c86}'),
+  ('\u{44b}', '\u{42b}'),
+  ('\u{44c}', '\u{42c}'),
+  ('\u{44d}', '\u{42d}'),
+  ('\u{44e}', '\u{42e}'),
+  ('\u{44f}', '\u{42f}'),
+  ('\u{450}', '\u{400}'),
+  ('\u{451}', '\u{401}'),
+  ('\u{452}', '\u{402}'),
+  ('\u{453}', '\u{403}'),
+  ('\u{454}', '\u{404}'),
+  ('\u{455}', '\u{405}'),
+  ('\u{456}', '\u{406}'),
+  ('\u{457}', '\u{407}'),
+  ('\u{458}', '\u{408}'),
+  ('\u{459}', '\u{409}'),
+  ('\u{45a}', '\u{40a}'),
+  ('\u{45b}', '\u{40b}'),
+  ('\u{45c}', '\u{40c}'),
+  ('\u{45d}', '\u{40d}'),
+  ('\u{45e}', '\u{40e}'),
+  ('\u{45f}', '\u{40f}'),
+  ('\u{461}', '\u{460}'),
+  ('\u{463}', '\u{462}'),
+  ('\u{463}', '\u{1c87}'),
+  ('\u{465}', '\u{464}'),
+  ('\u{467}', '\u{466}'),
+  ('\u{469}', '\u{468}'),
+  ('\u{46b}', '\u{46a}'),
+  ('\u{46d}', '\u{46c}'),
+  ('\u{46f}', '\u{46e}'),
+  ('\u{471}', '\u{470}'),
+  ('\u{473}', '\u{472}'),
+  ('\u{475}', '\u{474}'),
+  ('\u{477}', '\u{476}'),
+  ('\u{479}', '\u{478}'),
+  ('\u{47b}', '\u{47a}'),
+  ('\u{47d}', '\u{47c}'),
+  ('\u{47f}', '\u{47e}'),
+  ('\u{481}', '\u{480}'),
+  ('\u{48b}', '\u{48a}'),
+  ('\u{48d}', '\u{48c}'),
+  ('\u{48f}', '\u{48e}'),
+  ('\u{491}', '\u{490}'),
+  ('\u{493}', '\u{492}'),
+  ('\u{495}', '\u{494}'),
+  ('\u{497}', '\u{496}'),
+  ('\u{499}', '\u{498}'),
+  ('\u{49b}', '\u{49a}'),
+  ('\u{49d}', '\u{49c}'),
+  ('\u{49f}', '\u{49e}'),
+  ('\u{4a1}', '\u{4a0}'),
+  ('\u{4a3}', '\u{4a2}'),
+  ('\u{4a5}', '\u{4a4}'),
+  ('\u{4a7}', '\u{4a6}'),
+  ('\u{4a9}', '\u{4a8}'),
+  ('\u{4ab}', '\u{4aa}'),
+  ('\u{4ad}', '\u{4ac}'),
+  ('\u{4af}', '\u{4ae}'),
+  ('\u{4b1}', '\u{4b0}'),
+  ('\u{4b3}', '\u{4b2}'),
+  ('\u{4b5}', '\u{4b4}'),
+  ('\u{4b7}', '\u{4b6}'),
+  ('\u{4b9}', '\u{4b8}'),
+  ('\u{4bb}', '\u{4ba}'),
+  ('\u{4bd}', '\u{4bc}'),
+  ('\u{4bf}', '\u{4be}'),
+  ('\u{4c2}', '\u{4c1}'),
+  ('\u{4c4}', '\u{4c3}'),
+  ('\u{4c6}', '\u{4c5}'),
+  ('\u{4c8}', '\u{4c7}'),
+  ('\u{4ca}', '\u{4c9}'),
+  ('\u{4cc}', '\u{4cb}'),
+  ('\u{4ce}', '\u{4cd}'),
+  ('\u{4cf}', '\u{4c0}'),
+  ('\u{4d1}', '\u{4d0}'),
+  ('\u{4d3}', '\u{4d2}'),
+  ('\u{4d5}', '\u{4d4}'),
+  ('\u{4d7}', '\u{4d6}'),
+  ('\u{4d9}', '\u{4d8}'),
+  ('\u{4db}', '\u{4da}'),
+  ('\u{4dd}', '\u{4dc}'),
+  ('\u{4df}', '\u{4de}'),
+  ('\u{4e1}', '\u{4e0}'),
+  ('\u{4e3}', '\u{4e2}'),
+  ('\u{4e5}', '\u{4e4}'),
+  ('\u{4e7}', '\u{4e6}'),
+  ('\u{4e9}', '\u{4e8}'),
+  ('\u{4eb}', '\u{4ea}'),
+  ('\u{4ed}', '\u{4ec}'),
+  ('\u{4ef}', '\u{4ee}'),
+  ('\u{4f1}', '\u{4f0}'),
+  ('\u{4f3}', '\u{4f2}'),
+  ('\u{4f5}', '\u{4f4}'),
+  ('\u{4f7}', '\u{4f6}'),
+  ('\u{4f9}', '\u{4f8}'),
+  ('\u{4fb}', '\u{4fa}'),
+  ('\u{4fd}', '\u{4fc}'),
+  ('\u{4ff}', '\u{4fe}'),
+  ('\u{501}', '\u{500}'),
+  ('\u{503}', '\u{502}'),
+  ('\u{505}', '\u{504}'),
+  ('\u{507}', '\u{506}'),
+  ('\u{509}', '\u{508}'),
+  ('\u{50b}', '\u{50a}'),
+  ('\u{50d}', '\u{50c}'),
+  ('\u{50f}', '\u{50e}'),
+  ('\u{511}', '\u{510}'),
+  ('\u{513}', '\u{512}'),
+  ('\u{515}', '\u{514}'),
+  ('\u{517}', '\u{516}'),
+  ('\u{519}', '\u{518}'),
+  ('\u{51b}', '\u{51a}'),
+  ('\u{51d}', '\u{51c}'),
+  ('\u{51f}', '\u{51e}'),
+  ('\u{521}', '\u{520}'),
+  ('\u{523}', '\u{522}'),
+  ('\u{525}', '\u{524}'),
+  ('\u{527}', '\u{526}'),
+  ('\u{529}', '\u{528}'),
+  ('\u{52b}', '\u{52a}'),
+  ('\u{52d}', '\u{52c}'),
+  ('\u{52f}', '\u{52e}'),
+  ('\u{561}', '\u{531}'),
+  ('\u{562}', '\u{532}'),
+  ('\u{563}', '\u{533}'),
+  ('\u{564}', '\u{534}'),
+  ('\u{565}', '\u{535}'),
+  ('\u{566}', '\u{536}'),
+  ('\u{567}', '\u{537}'),
+  ('\u{568}', '\u{538}'),
+  ('\u{569}', '\u{539}'),
+  ('\u{56a}', '\u{53a}'),
+  ('\u{56b}', '\u{53b}'),
+  ('\u{56c}', '\u{53c}'),
+  ('\u{56d}', '\u{53d}'),
+  ('\u{56e}', '\u{53e}'),
+  ('\u{56f}', '\u{53f}'),
+  ('\u{570}', '\u{540}'),
+  ('\u{571}', '\u{541}'),
+  ('\u{572}', '\u{542}'),
+  ('\u{573}', '\u{543}'),
+  ('\u{574}', '\u{544}'),
+  ('\u{575}', '\u{545}'),
+  ('\u{576}', '\u{546}'),
+  ('\u{577}', '\u{547}'),
+  ('\u{578}', '\u{548}'),
+  ('\u{579}', '\u{549}'),
+  ('\u{57a}', '\u{54a}'),
+  ('\u{57b}', '\u{54b}'),
+  ('\u{57c}', '\u{54c}'),
+  ('\u{57d}', '\u{54d}'),
+  ('\u{57e}', '\u{54e}'),
+  ('\u{57f}', '\u{54f}'),
+  ('\u{580}', '\u{550}'),
+  ('\u{581}', '\u{551}'),
+  ('\u{582}', '\u{552}'),
+  ('\u{583}', '\u{553}'),
+  ('\u{584}', '\u{554}'),
+  ('\u{585}', '\u{555}'),
+  ('\u{586}', '\u{556}'),
+  ('\u{10d0}', '\u{1c90}'),
+  ('\u{10d1}', '\u{1c91}'),
+  ('\u{10d2}', '\u{1c92}'),
+  ('\u{10d3}', '\u{1c93}'),
+  ('\u{10d4}', '\u{1c94}'),
+  ('\u{10d5}', '\u{1c95}'),
+  ('\u{10d6}', '\u{1c96}'),
+  ('\u{10d7}', '\u{1c97}'),
+  ('\u{10d8}', '\u{1c98}'),
+  ('\u{10d9}', '\u{1c99}'),
+  ('\u{10da}', '\u{1c9a}'),
+  ('\u{10db}', '\u{1c9b}'),
+  ('\u{10dc}', '\u{1c9c}'),
+  ('\u{10dd}', '\u{1c9d}'),
+  ('\u{10de}', '\u{1c9e}'),
+  ('\u{10df}', '\u{1c9f}'),
+  ('\u{10e0}', '\u{1ca0}'),
+  ('\u{10e1}', '\u{1ca1}'),
+  ('\u{10e2}', '\u{1ca2}'),
+  ('\u{10e3}', '\u{1ca3}'),
+  ('\u{10e4}', '\u{1ca4}'),
+  ('\u{10e5}', '\u{1ca5}'),
+  ('\u{10e6}', '\u{1ca6}'),
+  ('\u{10e7}', '\u{1ca7}'),
+  ('\u{10e8}', '\u{1ca8}'),
+  ('\u{10e9}', '\u{1ca9}'),
+  ('\u{10ea}', '\u{1caa}'),
+  ('\u{10eb}', '\u{1cab}'),
+  ('\u{10ec}', '\u{1cac}'),
+  ('\u{10ed}', '\u{1cad}'),
+  ('\u{10ee}', '\u{1cae}'),
+  ('\u{10ef}', '\u{1caf}'),
+  ('\u{10f0}', '\u{1cb0}'),
+  ('\u{10f1}', '\u{1cb1}'),
+  ('\u{10f2}', '\u{1cb2}'),
+  ('\u{10f3}', '\u{1cb3}'),
+  ('\u{10f4}', '\u{1cb4}'),
+  ('\u{10f5}', '\u{1cb5}'),
+  ('\u{10f6}', '\u{1cb6}'),
+  ('\u{10f7}', '\u{1cb7}'),
+  ('\u{10f8}', '\u{1cb8}'),
+  ('\u{10f9}', '\u{1cb9}'),
+  ('\u{10fa}', '\u{1cba}'),
+  ('\u{10fd}', '\u{1cbd}'),
+  ('\u{10fe}', '\u{1cbe}'),
+  ('\u{10ff}', '\u{1cbf}'),
+  ('\u{13a0}', '\u{ab70}'),
+  ('\u{13a1}', '\u{ab71}'),
+  ('\u{13a2}', '\u{ab72}'),
+  ('\u{13a3}', '\u{ab73}'),
+  ('\u{13a4}', '\u{ab74}'),
+  ('\u{13a5}', '\u{ab75}'),
+  ('\u{13a6}', '\u{ab76}'),
+  ('\u{13a7}', '\u{ab77}'),
+  ('\u{13a8}', '\u{ab78}'),
+  ('\u{13a9}', '\u{ab79}'),
+  ('\u{13aa}', '\u{ab7a}'),
+  ('\u{13ab}', '\u{ab7b}'),
+  ('\u{13ac}', '\u{ab7c}'),
+  ('\u{13ad}', '\u{ab7d}'),
+  ('\u{13ae}', '\u{ab7e}'),
+  ('\u{13af}', '\u{ab7f}'),
+  ('\u{13b0}', '\u{ab80}'),
+  ('\u{13b1}', '\u{ab81}'),
+  ('\u{13b2}', '\u{ab82}'),
+  ('\u{13b3}', '\u{ab83}'),
+  ('\u{13b4}', '\u{ab84}'),
+  ('\u{13b5}', '\u{ab85}'),
+  ('\u{13b6}', '\u{ab86}'),
+  ('\u{13b7}', '\u{ab87}'),
+  ('\u{13b8}', '\u{ab88}'),
+  ('\u{13b9}', '\u{ab89}'),
+  ('\u{13ba}', '\u{ab8a}'),
+  ('\u{13bb}', '\u{ab8b}'),
+  ('\u{13bc}', '\u{ab8c}'),
+  ('\u{13bd}', '\u{ab8d}'),
+  ('\u{13be}', '\u{ab8e}'),
+  ('\u{13bf}', '\u{ab8f}'),
+  ('\u{13c0}', '\u{ab90}'),
+  ('\u{13c1}', '\u{ab91}'),
+  ('\u{13c2}', '\u{ab92}'),
+  ('\u{13c3}', '\u{ab93}'),
+  ('\u{13c4}', '\u{ab94}'),
+  ('\u{13c5}', '\u{ab95}'),
+  ('\u{13c6}', '\u{ab96}'),
+  ('\u{13c7}', '\u{ab97}'),
+  ('\u{13c8}', '\u{ab98}'),
+  ('\u{13c9}', '\u{ab99}'),
+  ('\u{13ca}', '\u{ab9a}'),
+  ('\u{13cb}', '\u{ab9b}'),
+  ('\u{13cc}', '\u{ab9c}'),
+  ('\u{13cd}', '\u{ab9d}'),
+  ('\u{13ce}', '\u{ab9e}'),
+  ('\u{13cf}', '\u{ab9f}'),
+  ('\u{13d0}', '\u{aba0}'),
+  ('\u{13d1}', '\u{aba1}'),
+  ('\u{13d2}', '\u{aba2}'),
+  ('\u{13d3}', '\u{aba3}'),
+  ('\u{13d4}', '\u{aba4}'),
+  ('\u{13d5}', '\u{aba5}'),
+  ('\u{13d6}', '\u{aba6}'),
+  ('\u{13d7}', '\u{aba7}'),
+  ('\u{13d8}', '\u{aba8}'),
+  ('\u{13d9}', '\u{aba9}'),
+  ('\u{13da}', '\u{abaa}'),
+  ('\u{13db}', '\u{abab}'),
+  ('\u{13dc}', '\u{abac}'),
+  ('\u{13dd}', '\u{abad}'),
+  ('\u{13de}', '\u{abae}'),
+  ('\u{13df}', '\u{abaf}'),
+  ('\u{13e0}', '\u{abb0}'),
+  ('\u{13e1}', '\u{abb1}'),
+  ('\u{13e2}', '\u{abb2}'),
+  ('\u{13e3}', '\u{abb3}'),
+  ('\u{13e4}', '\u{abb4}'),
+  ('\u{13e5}', '\u{abb5}'),
+  ('\u{13e6}', '\u{abb6}'),
+  ('\u{13e7}', '\u{abb7}'),
+  ('\u{13e8}', '\u{abb8}'),
+  ('\u{13e9}', '\u{abb9}'),
+  ('\u{13ea}', '\u{abba}'),
+  ('\u{13eb}', '\u{abbb}'),
+  ('\u{13ec}', '\u{abbc}'),
+  ('\u{13ed}', '\u{abbd}'),
+  ('\u{13ee}', '\u{abbe}'),
+  ('\u{13ef}', '\u{abbf}'),
+  ('\u{13f0}', '\u{13f8}'),
+  ('\u{13f1}', '\u{13f9}'),
+  ('\u{13f2}', '\u{13fa}'),
+  ('\u{13f3}', '\u{13fb}'),
+  ('\u{13f4}', '\u{13fc}'),
+  ('\u{13f5}', '\u{13fd}'),
+  ('\u{1c8a}', '\u{1c89}'),
+  ('\u{1d79}', '\u{a77d}'),
+  ('\u{1d7d}', '\u{2c63}'),
+  ('\u{1d8e}', '\u{a7c6}'),
+  ('\u{1e01}', '\u{1e00}'),
+  ('\u{1e03}', '\u{1e02}'),
+  ('\u{1e05}', '\u{1e04}'),
+  ('\u{1e07}', '\u{1e06}'),
+  ('\u{1e09}', '\u{1e08}'),
+  ('\u{1e0b}', '\u{1e0a}'),
+  ('\u{1e0d}', '\u{1e0c}'),
+  ('\u{1e0f}', '\u{1e0e}'),
+  ('\u{1e11}', '\u{1e10}'),
+  ('\u{1e13}', '\u{1e12}'),
+  ('\u{1e15}', '\u{1e14}'),
+  ('\u{1e17}', '\u{1e16}'),
+  ('\u{1e19}', '\u{1e18}'),
+  ('\u{1e1b}', '\u{1e1a}'),
+  ('\u{1e1d}', '\u{1e1c}'),
+  ('\u{1e1f}', '\u{1e1e}'),
+  ('\u{1e21}', '\u{1e20}'),
+  ('\u{1e23}', '\u{1e22}'),
+  ('\u{1e25}', '\u{1e24}'),
+  ('\u{1e27}', '\u{1e26}'),
+  ('\u{1e29}', '\u{1e28}'),
+  ('\u{1e2b}', '\u{1e2a}'),
+  ('\u{1e2d}', '\u{1e2c}'),
+  ('\u{1e2f}', '\u{1e2e}'),
+  ('\u{1e31}', '\u{1e30}'),
+  ('\u{1e33}', '\u{1e32}'),
+  ('\u{1e35}', '\u{1e34}'),
+  ('\u{1e37}', '\u{1e36}'),
+  ('\u{1e39}', '\u{1e38}'),
+  ('\u{1e3b}', '\u{1e3a}'),
+  ('\u{1e3d}', '\u{1e3c}'),
+  ('\u{1e3f}', '\u{1e3e}'),
+  ('\u{1e41}', '\u{1e40}'),
+  ('\u{1e43}', '\u{1e42}'),
+  ('\u{1e45}', '\u{1e44}'),
+  ('\u{1e47}', '\u{1e46}'),
+  ('\u{1e49}', '\u{1e48}'),
+  ('\u{1e4b}', '\u{1e4a}'),
+  ('\u{1e4d}', '\u{1e4c}'),
+  ('\u{1e4f}', '\u{1e4e}'),
+  ('\u{1e51}', '\u{1e50}'),
+  ('\u{1e53}', '\u{1e52}'),
+  ('\u{1e55}', '\u{1e54}'),
+  ('\u{1e57}', '\u{1e56}'),
+  ('\u{1e59}', '\u{1e58}'),
+  ('\u{1e5b}', '\u{1e5a}'),
+  ('\u{1e5d}', '\u{1e5c}'),
+  ('\u{1e5f}', '\u{1e5e}'),
+  ('\u{1e61}', '\u{1e60}'),
+  ('\u{1e61}', '\u{1e9b}'),
+  ('\u{1e63}', '\u{1e62}'),
+  ('\u{1e65}', '\u{1e64}'),
+  ('\u{1e67}', '\u{1e66}'),
+  ('\u{1e69}', '\u{1e68}'),
+  ('\u{1e6b}', '\u{1e6a}'),
+  ('\u{1e6d}', '\u{1e6c}'),
+  ('\u{1e6f}', '\u{1e6e}'),
+  ('\u{1e71}', '\u{1e70}'),
+  ('\u{1e73}', '\u{1e72}'),
+  ('\u{1e75}', '\u{1e74}'),
+  ('\u{1e77}', '\u{1e76}'),
+  ('\u{1e79}', '\u{1e78}'),
+  ('\u{1e7b}', '\u{1e7a}'),
+  ('\u{1e7d}', '\u{1e7c}'),
+  ('\u{1e7f}', '\u{1e7e}'),
+  ('\u{1e81}', '\u{1e80}'),
+  ('\u{1e83}', '\u{1e82}'),
+  ('\u{1e85}', '\u{1e84}'),
+  ('\u{1e87}', '\u{1e86}'),
+  ('\u{1e89}', '\u{1e88}'),
+  ('\u{1e8b}', '\u{1e8a}'),
+  ('\u{1e8d}', '\u{1e8c}'),
+  ('\u{1e8f}', '\u{1e8e}'),
+  ('\u{1e91}', '\u{1e90}'),
+  ('\u{1e93}', '\u{1e92}'),
+  ('\u{1e95}', '\u{1e94}'),
+  ('\u{1ea1}', '\u{1ea0}'),
+  ('\u{1ea3}', '\u{1ea2}'),
+  ('\u{1ea5}', '\u{1ea4}'),
+  ('\u{1ea7}', '\u{1ea6}'),
+  ('\u{1ea9}', '\u{1ea8}'),
+  ('\u{1eab}', '\u{1eaa}'),
+  ('\u{1ead}', '\u{1eac}'),
+  ('\u{1eaf}', '\u{1eae}'),
+  ('\u{1eb1}', '\u{1eb0}'),
+  ('\u{1eb3}', '\u{1eb2}'),
+  ('\u{1eb5}', '\u{1eb4}'),
+  ('\u{1eb7}', '\u{1eb6}'),
+  ('\u{1eb9}', '\u{1eb8}'),
+  ('\u{1ebb}', '\u{1eba}'),
+  ('\u{1ebd}', '\u{1ebc}'),
+  ('\u{1ebf}', '\u{1ebe}'),
+  ('\u{1ec1}', '\u{1ec0}'),
+  ('\u{1ec3}', '\u{1ec2}'),
+  ('\u{1ec5}', '\u{1ec4}'),
+  ('\u{1ec7}', '\u{1ec6}'),
+  ('\u{1ec9}', '\u{1ec8}'),
+  ('\u{1ecb}', '\u{1eca}'),
+  ('\u{1ecd}', '\u{1ecc}'),
+  ('\u{1ecf}', '\u{1ece}'),
+  ('\u{1ed1}', '\u{1ed0}'),
+  ('\u{1ed3}', '\u{1ed2}'),
+  ('\u{1ed5}', '\u{1ed4}'),
+  ('\u{1ed7}', '\u{1ed6}'),
+  ('\u{1ed9}', '\u{1ed8}'),
+  ('\u{1edb}', '\u{1eda}'),
+  ('\u{1edd}', '\u{1edc}'),
+  ('\u{1edf}', '\u{1ede}'),
+  ('\u{1ee1}', '\u{1ee0}'),
+  ('\u{1ee3}', '\u{1ee2}'),
+  ('\u{1ee5}', '\u{1ee4}'),
+  ('\u{1ee7}', '\u{1ee6}'),
+  ('\u{1ee9}', '\u{1ee8}'),
+  ('\u{1eeb}', '\u{1eea}'),
+  ('\u{1eed}', '\u{1eec}'),
+  ('\u{1eef}', '\u{1eee}'),
+  ('\u{1ef1}', '\u{1ef0}'),
+  ('\u{1ef3}', '\u{1ef2}'),
+  ('\u{1ef5}', '\u{1ef4}'),
+  ('\u{1ef7}', '\u{1ef6}'),
+  ('\u{1ef9}', '\u{1ef8}'),
+  ('\u{1efb}', '\u{1efa}'),
+  ('\u{1efd}', '\u{1efc}'),
+  ('\u{1eff}', '\u{1efe}'),
+  ('\u{1f00}', '\u{1f08}'),
+  ('\u{1f01}', '\u{1f09}'),
+  ('\u{1f02}', '\u{1f0a}'),
+  ('\u{1f03}', '\u{1f0b}'),
+  ('\u{1f04}', '\u{1f0c}'),
+  ('\u{1f05}', '\u{1f0d}'),
+  ('\u{1f06}', '\u{1f0e}'),
+  ('\u{1f07}', '\u{1f0f}'),
+  ('\u{1f10}', '\u{1f18}'),
+  ('\u{1f11}', '\u{1f19}'),
+  ('\u{1f12}', '\u{1f1a}'),
+  ('\u{1f13}', '\u{1f1b}'),
+  ('\u{1f14}', '\u{1f1c}'),
+  ('\u{1f15}', '\u{1f1d}'),
+  ('\u{1f20}', '\u{1f28}'),
+  ('\u{1f21}', '\u{1f29}'),
+  ('\u{1f22}', '\u{1f2a}'),
+  ('\u{1f23}', '\u{1f2b}'),
+  ('\u{1f24}', '\u{1f2c}'),
+  ('\u{1f25}', '\u{1f2d}'),
+  ('\u{1f26}', '\u{1f2e}'),
+  ('\u{1f27}', '\u{1f2f}'),
+  ('\u{1f30}', '\u{1f38}'),
+  ('\u{1f31}', '\u{1f39}'),
+  ('\u{1f32}', '\u{1f3a}'),
+  ('\u{1f33}', '\u{1f3b}'),
+  ('\u{1f34}', '\u{1f3c}'),
+  ('\u{1f35}', '\u{1f3d}'),
+  ('\u{1f36}', '\u{1f3e}'),
+  ('\u{1f37}', '\u{1f3f}'),
+  ('\u{1f40}', '\u{1f48}'),
+  ('\u{1f41}', '\u{1f49}'),
+  ('\u{1f42}', '\u{1f4a}'),
+  ('\u{1f43}', '\u{1f4b}'),
+  ('\u{1f44}', '\u{1f4c}'),
+  ('\u{1f45}', '\u{1f4d}'),
+  ('\u{1f51}', '\u{1f59}'),
+  ('\u{1f53}', '\u{1f5b}'),
+  ('\u{1f55}', '\u{1f5d}'),
+  ('\u{1f57}', '\u{1f5f}'),
+  ('\u{1f60}', '\u{1f68}'),
+  ('\u{1f61}', '\u{1f69}'),
+  ('\u{1f62}', '\u{1f6a}'),
+  ('\u{1f63}', '\u{1f6b}'),
+  ('\u{1f64}', '\u{1f6c}'),
+  ('\u{1f65}', '\u{1f6d}'),
+  ('\u{1f66}', '\u{1f6e}'),
+  ('\u{1f67}', '\u{1f6f}'),
+  ('\u{1f70}', '\u{1fba}'),
+  ('\u{1f71}', '\u{1fbb}'),
+  ('\u{1f72}', '\u{1fc8}'),
+  ('\u{1f73}', '\u{1fc9}'),
+  ('\u{1f74}', '\u{1fca}'),
+  ('\u{1f75}', '\u{1fcb}'),
+  ('\u{1f76}', '\u{1fda}'),
+  ('\u{1f77}', '\u{1fdb}'),
+  ('\u{1f78}', '\u{1ff8}'),
+  ('\u{1f79}', '\u{1ff9}'),
+  ('\u{1f7a}', '\u{1fea}'),
+  ('\u{1f7b}', '\u{1feb}'),
+  ('\u{1f7c}', '\u{1ffa}'),
+  ('\u{1f7d}', '\u{1ffb}'),
+  ('\u{1fb0}', '\u{1fb8}'),
+  ('\u{1fb1}', '\u{1fb9}'),
+  ('\u{1fd0}', '\u{1fd8}'),
+  ('\u{1fd1}', '\u{1fd9}'),
+  ('\u{1fe0}', '\u{1fe8}'),
+  ('\u{1fe1}', '\u{1fe9}'),
+  ('\u{1fe5}', '\u{1fec}'),
+  ('\u{214e}', '\u{2132}'),
+  ('\u{2170}', '\u{2160}'),
+  ('\u{2171}', '\u{2161}'),
+  ('\u{2172}', '\u{2162}'),
+  ('\u{2173}', '\u{2163}'),
+  ('\u{2174}', '\u{2164}'),
+  ('\u{2175}', '\u{2165}'),
+  ('\u{2176}', '\u{2166}'),
+  ('\u{2177}', '\u{2167}'),
+  ('\u{2178}', '\u{2168}'),
+  ('\u{2179}', '\u{2169}'),
+  ('\u{217a}', '\u{216a}'),
+  ('\u{217b}', '\u{216b}'),
+  ('\u{217c}', '\u{216c}'),
+  ('\u{217d}', '\u{216d}'),
+  ('\u{217e}', '\u{216e}'),
+  ('\u{217f}', '\u{216f}'),
+  ('\u{2184}', '\u{2183}'),
+  ('\u{24d0}', '\u{24b6}'),
+  ('\u{24d1}', '\u{24b7}'),
+  ('\u{24d2}', '\u{24b8}'),
+  ('\u{24d3}', '\u{24b9}'),
+  ('\u{24d4}', '\u{24ba}'),
+  ('\u{24d5}', '\u{24bb}'),
+  ('\u{24d6}', '\u{24bc}'),
+  ('\u{24d7}', '\u{24bd}'),
+  ('\u{24d8}', '\u{24be}'),
+  ('\u{24d9}', '\u{24bf}'),
+  ('\u{24da}', '\u{24c0}'),
+  ('\u{24db}', '\u{24c1}'),
+  ('\u{24dc}', '\u{24c2}'),
+  ('\u{24dd}', '\u{24c3}'),
+  ('\u{24de}', '\u{24c4}'),
+  ('\u{24df}', '\u{24c5}'),
+  ('\u{24e0}', '\u{24c6}'),
+  ('\u{24e1}', '\u{24c7}'),
+  ('\u{24e2}', '\u{24c8}'),
+  ('\u{24e3}', '\u{24c9}'),
+  ('\u{24e4}', '\u{24ca}'),
+  ('\u{24e5}', '\u{24cb}'),
+  ('\u{24e6}', '\u{24cc}'),
+  ('\u{24e7}', '\u{24cd}'),
+  ('\u{24e8}', '\u{24ce}'),
+  ('\u{24e9}', '\u{24cf}'),
+  ('\u{2c30}', '\u{2c00}'),
+  ('\u{2c31}', '\u{2c01}'),
+  ('\u{2c32}', '\u{2c02}'),
+  ('\u{2c33}', '\u{2c03}'),
+  ('\u{2c34}', '\u{2c04}'),
+  ('\u{2c35}', '\u{2c05}'),
+  ('\u{2c36}', '\u{2c06}'),
+  ('\u{2c37}', '\u{2c07}'),
+  ('\u{2c38}', '\u{2c08}'),
+  ('\u{2c39}', '\u{2c09}'),
+  ('\u{2c3a}', '\u{2c0a}'),
+  ('\u{2c3b}', '\u{2c0b}'),
+  ('\u{2c3c}', '\u{2c0c}'),
+  ('\u{2c3d}', '\u{2c0d}'),
+  ('\u{2c3e}', '\u{2c0e}'),
+  ('\u{2c3f}', '\u{2c0f}'),
+  ('\u{2c40}', '\u{2c10}'),
+  ('\u{2c41}', '\u{2c11}'),
+  ('\u{2c42}', '\u{2c12}'),
+  ('\u{2c43}', '\u{2c13}'),
+  ('\u{2c44}', '\u{2c14}'),
+  ('\u{2c45}', '\u{2c15}'),
+  ('\u{2c46}', '\u{2c16}'),
+  ('\u{2c47}', '\u{2c17}'),
+  ('\u{2c48}', '\u{2c18}'),
+  ('\u{2c49}', '\u{2c19}'),
+  ('\u{2c4a}', '\u{2c1a}'),
+  ('\u{2c4b}', '\u{2c1b}'),
+  ('\u{2c4c}', '\u{2c1c}'),
+  ('\u{2c4d}', '\u{2c1d}'),
+  ('\u{2c4e}', '\u{2c1e}'),
+  ('\u{2c4f}', '\u{2c1f}'),
+  ('\u{2c50}', '\u{2c20}'),
+  ('\u{2c51}', '\u{2c21}'),
+  ('\u{2c52}', '\u{2c22}'),
+  ('\u{2c53}', '\u{2c23}'),
+  ('\u{2c54}', '\u{2c24}'),
+  ('\u{2c55}', '\u{2c25}'),
+  ('\u{2c56}', '\u{2c26}'),
+  ('\u{2c57}', '\u{2c27}'),
+  ('\u{2c58}', '\u{2c28}'),
+  ('\u{2c59}', '\u{2c29}'),
+  ('\u{2c5a}', '\u{2c2a}'),
+  ('\u{2c5b}', '\u{2c2b}'),
+  ('\u{2c5c}', '\u{2c2c}'),
+  ('\u{2c5d}', '\u{2c2d}'),
+  ('\u{2c5e}', '\u{2c2e}'),
+  ('\u{2c5f}', '\u{2c2f}'),
+  ('\u{2c61}', '\u{2c60}'),
+  ('\u{2c65}', '\u{23a}'),
+  ('\u{2c66}', '\u{23e}'),
+  ('\u{2c68}', '\u{2c67}'),
+  ('\u{2c6a}', '\u{2c69}'),
+  ('\u{2c6c}', '\u{2c6b}'),
+  ('\u{2c73}', '\u{2c72}'),
+  ('\u{2c76}', '\u{2c75}'),
+  ('\u{2c81}', '\u{2c80}'),
+  ('\u{2c83}', '\u{2c82}'),
+  ('\u{2c85}', '\u{2c84}'),
+  ('\u{2c87}', '\u{2c86}'),
+  ('\u{2c89}', '\u{2c88}'),
+  ('\u{2c8b}', '\u{2c8a}'),
+  ('\u{2c8d}', '\u{2c8c}'),
+  ('\u{2c8f}', '\u{2c8e}'),
+  ('\u{2c91}', '\u{2c90}'),
+  ('\u{2c93}', '\u{2c92}'),
+  ('\u{2c95}', '\u{2c94}'),
+  ('\u{2c97}', '\u{2c96}'),
+  ('\u{2c99}', '\u{2c98}'),
+  ('\u{2c9b}', '\u{2c9a}'),
+  ('\u{2c9d}', '\u{2c9c}'),
+  ('\u{2c9f}', '\u{2c9e}'),
+  ('\u{2ca1}', '\u{2ca0}'),
+  ('\u{2ca3}', '\u{2ca2}'),
+  ('\u{2ca5}', '\u{2ca4}'),
+  ('\u{2ca7}', '\u{2ca6}'),
+  ('\u{2ca9}', '\u{2ca8}'),
+  ('\u{2cab}', '\u{2caa}'),
+  ('\u{2cad}', '\u{2cac}'),
+  ('\u{2caf}', '\u{2cae}'),
+  ('\u{2cb1}', '\u{2cb0}'),
+  ('\u{2cb3}', '\u{2cb2}'),
+  ('\u{2cb5}', '\u{2cb4}'),
+  ('\u{2cb7}', '\u{2cb6}'),
+  ('\u{2cb9}', '\u{2cb8}'),
+  ('\u{2cbb}', '\u{2cba}'),
+  ('\u{2cbd}', '\u{2cbc}'),
+  ('\u{2cbf}', '\u{2cbe}'),
+  ('\u{2cc1}', '\u{2cc0}'),
+  ('\u{2cc3}', '\u{2cc2}'),
+  ('\u{2cc5}', '\u{2cc4}'),
+  ('\u{2cc7}', '\u{2cc6}'),
+  ('\u{2cc9}', '\u{2cc8}'),
+  ('\u{2ccb}', '\u{2cca}'),
+  ('\u{2ccd}', '\u{2ccc}'),
+  ('\u{2ccf}', '\u{2cce}'),
+  ('\u{2cd1}', '\u{2cd0}'),
+  ('\u{2cd3}', '\u{2cd2}'),
+  ('\u{2cd5}', '\u{2cd4}'),
+  ('\u{2cd7}', '\u{2cd6}'),
+  ('\u{2cd9}', '\u{2cd8}'),
+  ('\u{2cdb}', '\u{2cda}'),
+  ('\u{2cdd}', '\u{2cdc}'),
+  ('\u{2cdf}', '\u{2cde}'),
+  ('\u{2ce1}', '\u{2ce0}'),
+  ('\u{2ce3}', '\u{2ce2}'),
+  ('\u{2cec}', '\u{2ceb}'),
+  ('\u{2cee}', '\u{2ced}'),
+  ('\u{2cf3}', '\u{2cf2}'),
+  ('\u{2d00}', '\u{10a0}'),
+  ('\u{2d01}', '\u{10a1}'),
+  ('\u{2d02}', '\u{10a2}'),
+  ('\u{2d03}', '\u{10a3}'),
+  ('\u{2d04}', '\u{10a4}'),
+  ('\u{2d05}', '\u{10a5}'),
+  ('\u{2d06}', '\u{10a6}'),
+  ('\u{2d07}', '\u{10a7}'),
+  ('\u{2d08}', '\u{10a8}'),
+  ('\u{2d09}', '\u{10a9}'),
+  ('\u{2d0a}', '\u{10aa}'),
+  ('\u{2d0b}', '\u{10ab}'),
+  ('\u{2d0c}', '\u{10ac}'),
+  ('\u{2d0d}', '\u{10ad}'),
+  ('\u{2d0e}', '\u{10ae}'),
+  ('\u{2d0f}', '\u{10af}'),
+  ('\u{2d10}', '\u{10b0}'),
+  ('\u{2d11}', '\u{10b1}'),
+  ('\u{2d12}', '\u{10b2}'),
+  ('\u{2d13}', '\u{10b3}'),
+  ('\u{2d14}', '\u{10b4}'),
+  ('\u{2d15}', '\u{10b5}'),
+  ('\u{2d16}', '\u{10b6}'),
+  ('\u{2d17}', '\u{10b7}'),
+  ('\u{2d18}', '\u{10b8}'),
+  ('\u{2d19}', '\u{10b9}'),
+  ('\u{2d1a}', '\u{10ba}'),
+  ('\u{2d1b}', '\u{10bb}'),
+  ('\u{2d1c}', '\u{10bc}'),
+  ('\u{2d1d}', '\u{10bd}'),
+  ('\u{2d1e}', '\u{10be}'),
+  ('\u{2d1f}', '\u{10bf}'),
+  ('\u{2d20}', '\u{10c0}'),
+  ('\u{2d21}', '\u{10c1}'),
+  ('\u{2d22}', '\u{10c2}'),
+  ('\u{2d23}', '\u{10c3}'),
+  ('\u{2d24}', '\u{10c4}'),
+  ('\u{2d25}', '\u{10c5}'),
+  ('\u{2d27}', '\u{10c7}'),
+  ('\u{2d2d}', '\u{10cd}'),
+  ('\u{a641}', '\u{a640}'),
+  ('\u{a643}', '\u{a642}'),
+  ('\u{a645}', '\u{a644}'),
+  ('\u{a647}', '\u{a646}'),
+  ('\u{a649}', '\u{a648}'),
+  ('\u{a64b}', '\u{1c88}'),
+  ('\u{a64b}', '\u{a64a}'),
+  ('\u{a64d}', '\u{a64c}'),
+  ('\u{a64f}', '\u{a64e}'),
+  ('\u{a651}', '\u{a650}'),
+  ('\u{a653}', '\u{a652}'),
+  ('\u{a655}', '\u{a654}'),
+  ('\u{a657}', '\u{a656}'),
+  ('\u{a659}', '\u{a658}'),
+  ('\u{a65b}', '\u{a65a}'),
+  ('\u{a65d}', '\u{a65c}'),
+  ('\u{a65f}', '\u{a65e}'),
+  ('\u{a661}', '\u{a660}'),
+  ('\u{a663}', '\u{a662}'),
+  ('\u{a665}', '\u{a664}'),
+  ('\u{a667}', '\u{a666}'),
+  ('\u{a669}', '\u{a668}'),
+  ('\u{a66b}', '\u{a66a}'),
+  ('\u{a66d}', '\u{a66c}'),
+  ('\u{a681}', '\u{a680}'),
+  ('\u{a683}', '\u{a682}'),
+  ('\u{a685}', '\u{a684}'),
+  ('\u{a687}', '\u{a686}'),
+  ('\u{a689}', '\u{a688}'),
+  ('\u{a68b}', '\u{a68a}'),
+  ('\u{a68d}', '\u{a68c}'),
+  ('\u{a68f}', '\u{a68e}'),
+  ('\u{a691}', '\u{a690}'),
+  ('\u{a693}', '\u{a692}'),
+  ('\u{a695}', '\u{a694}'),
+  ('\u{a697}', '\u{a696}'),
+  ('\u{a699}', '\u{a698}'),
+  ('\u{a69b}', '\u{a69a}'),
+  ('\u{a723}', '\u{a722}'),
+  ('\u{a725}', '\u{a724}'),
+  ('\u{a727}', '\u{a726}'),
+  ('\u{a729}', '\u{a728}'),
+  ('\u{a72b}', '\u{a72a}'),
+  ('\u{a72d}', '\u{a72c}'),
+  ('\u{a72f}', '\u{a72e}'),
+  ('\u{a733}', '\u{a732}'),
+  ('\u{a735}', '\u{a734}'),
+  ('\u{a737}', '\u{a736}'),
+  ('\u{a739}', '\u{a738}'),
+  ('\u{a73b}', '\u{a73a}'),
+  ('\u{a73d}', '\u{a73c}'),
+  ('\u{a73f}', '\u{a73e}'),
+  ('\u{a741}', '\u{a740}'),
+  ('\u{a743}', '\u{a742}'),
+  ('\u{a745}', '\u{a744}'),
+  ('\u{a747}', '\u{a746}'),
+  ('\u{a749}', '\u{a748}'),
+  ('\u{a74b}', '\u{a74a}'),
+  ('\u{a74d}', '\u{a74c}'),
+  ('\u{a74f}', '\u{a74e}'),
+  ('\u{a751}', '\u{a750}'),
+  ('\u{a753}', '\u{a752}'),
+  ('\u{a755}', '\u{a754}'),
+  ('\u{a757}', '\u{a756}'),
+  ('\u{a759}', '\u{a758}'),
+  ('\u{a75b}', '\u{a75a}'),
+  ('\u{a75d}', '\u{a75c}'),
+  ('\u{a75f}', '\u{a75e}'),
+  ('\u{a761}', '\u{a760}'),
+  ('\u{a763}', '\u{a762}'),
+  ('\u{a765}', '\u{a764}'),
+  ('\u{a767}', '\u{a766}'),
+  ('\u{a769}', '\u{a768}'),
+  ('\u{a76b}', '\u{a76a}'),
+  ('\u{a76d}', '\u{a76c}'),
+  ('\u{a76f}', '\u{a76e}'),
+  ('\u{a77a}', '\u{a779}'),
+  ('\u{a77c}', '\u{a77b}'),
+  ('\u{a77f}', '\u{a77e}'),
+  ('\u{a781}', '\u{a780}'),
+  ('\u{a783}', '\u{a782}'),
+  ('\u{a785}', '\u{a784}'),
+  ('\u{a787}', '\u{a786}'),
+  ('\u{a78c}', '\u{a78b}'),
+  ('\u{a791}', '\u{a790}'),
+  ('\u{a793}', '\u{a792}'),
+  ('\u{a794}', '\u{a7c4}'),
+  ('\u{a797}', '\u{a796}'),
+  ('\u{a799}', '\u{a798}'),
+  ('\u{a79b}', '\u{a79a}'),
+  ('\u{a79d}', '\u{a79c}'),
+  ('\u{a79f}', '\u{a79e}'),
+  ('\u{a7a1}', '\u{a7a0}'),
+  ('\u{a7a3}', '\u{a7a2}'),
+  ('\u{a7a5}', '\u{a7a4}'),
+  ('\u{a7a7}', '\u{a7a6}'),
+  ('\u{a7a9}', '\u{a7a8}'),
+  ('\u{a7b5}', '\u{a7b4}'),
+  ('\u{a7b7}', '\u{a7b6}'),
+  ('\u{a7b9}', '\u{a7b8}'),
+  ('\u{a7bb}', '\u{a7ba}'),
+  ('\u{a7bd}', '\u{a7bc}'),
+  ('\u{a7bf}', '\u{a7be}'),
+  ('\u{a7c1}', '\u{a7c0}'),
+  ('\u{a7c3}', '\u{a7c2}'),
+  ('\u{a7c8}', '\u{a7c7}'),
+  ('\u{a7ca}', '\u{a7c9}'),
+  ('\u{a7cd}', '\u{a7cc}'),
+  ('\u{a7d1}', '\u{a7d0}'),
+  ('\u{a7d7}', '\u{a7d6}'),
+  ('\u{a7d9}', '\u{a7d8}'),
+  ('\u{a7db}', '\u{a7da}'),
+  ('\u{a7f6}', '\u{a7f5}'),
+  ('\u{ab53}', '\u{a7b3}'),
+  ('\u{ff41}', '\u{ff21}'),
+  ('\u{ff42}', '\u{ff22}'),
+  ('\u{ff43}', '\u{ff23}'),
+  ('\u{ff44}', '\u{ff24}'),
+  ('\u{ff45}', '\u{ff25}'),
+  ('\u{ff46}', '\u{ff26}'),
+  ('\u{ff47}', '\u{ff27}'),
+  ('\u{ff48}', '\u{ff28}'),
+  ('\u{ff49}', '\u{ff29}'),
+  ('\u{ff4a}', '\u{ff2a}'),
+  ('\u{ff4b}', '\u{ff2b}'),
+  ('\u{ff4c}', '\u{ff2c}'),
+  ('\u{ff4d}', '\u{ff2d}'),
+  ('\u{ff4e}', '\u{ff2e}'),
+  ('\u{ff4f}', '\u{ff2f}'),
+  ('\u{ff50}', '\u{ff30}'),
+  ('\u{ff51}', '\u{ff31}'),
+  ('\u{ff52}', '\u{ff32}'),
+  ('\u{ff53}', '\u{ff33}'),
+  ('\u{ff54}', '\u{ff34}'),
+  ('\u{ff55}', '\u{ff35}'),
+  ('\u{ff56}', '\u{ff36}'),
+  ('\u{ff57}', '\u{ff37}'),
+  ('\u{ff58}', '\u{ff38}'),
+  ('\u{ff59}', '\u{ff39}'),
+  ('\u{ff5a}', '\u{ff3a}'),
+  ('\u{10428}', '\u{10400}'),
+  ('\u{10429}', '\u{10401}'),
+  ('\u{1042a}', '\u{10402}'),
+  ('\u{1042b}', '\u{10403}'),
+  ('\u{1042c}', '\u{10404}'),
+  ('\u{1042d}', '\u{10405}'),
+  ('\u{1042e}', '\u{10406}'),
+  ('\u{1042f}', '\u{10407}'),
+  ('\u{10430}', '\u{10408}'),
+  ('\u{10431}', '\u{10409}'),
+  ('\u{10432}', '\u{1040a}'),
+  ('\u{10433}', '\u{1040b}'),
+  ('\u{10434}', '\u{1040c}'),
+  ('\u{10435}', '\u{1040d}'),
+  ('\u{10436}', '\u{1040e}'),
+  ('\u{10437}', '\u{1040f}'),
+  ('\u{10438}', '\u{10410}'),
+  ('\u{10439}', '\u{10411}'),
+  ('\u{1043a}', '\u{10412}'),
+  ('\u{1043b}', '\u{10413}'),
+  ('\u{1043c}', '\u{10414}'),
+  ('\u{1043d}', '\u{10415}'),
+  ('\u{1043e}', '\u{10416}'),
+  ('\u{1043f}', '\u{10417}'),
+  ('\u{10440}', '\u{10418}'),
+  ('\u{10441}', '\u{10419}'),
+  ('\u{10442}', '\u{1041a}'),
+  ('\u{10443}', '\u{1041b}'),
+  ('\u{10444}', '\u{1041c}'),
+  ('\u{10445}', '\u{1041d}'),
+  ('\u{10446}', '\u{1041e}'),
+  ('\u{10447}', '\u{1041f}'),
+  ('\u{10448}', '\u{10420}'),
+  ('\u{10449}', '\u{10421}'),
+  ('\u{1044a}', '\u{10422}'),
+  ('\u{1044b}', '\u{10423}'),
+  ('\u{1044c}', '\u{10424}'),
+  ('\u{1044d}', '\u{10425}'),
+  ('\u{1044e}', '\u{10426}'),
+  ('\u{1044f}', '\u{10427}'),
+  ('\u{104d8}', '\u{104b0}'),
+  ('\u{104d9}', '\u{104b1}'),
+  ('\u{104da}', '\u{104b2}'),
+  ('\u{104db}', '\u{104b3}'),
+  ('\u{104dc}', '\u{104b4}'),
+  ('\u{104dd}', '\u{104b5}'),
+  ('\u{104de}', '\u{104b6}'),
+  ('\u{104df}', '\u{104b7}'),
+  ('\u{104e0}', '\u{104b8}'),
+  ('\u{104e1}', '\u{104b9}'),
+  ('\u{104e2}', '\u{104ba}'),
+  ('\u{104e3}', '\u{104bb}'),
+  ('\u{104e4}', '\u{104bc}'),
+  ('\u{104e5}', '\u{104bd}'),
+  ('\u{104e6}', '\u{104be}'),
+  ('\u{104e7}', '\u{104bf}'),
+  ('\u{104e8}', '\u{104c0}'),
+  ('\u{104e9}', '\u{104c1}'),
+  ('\u{104ea}', '\u{104c2}'),
+  ('\u{104eb}', '\u{104c3}'),
+  ('\u{104ec}', '\u{104c4}'),
+  ('\u{104ed}', '\u{104c5}'),
+  ('\u{104ee}', '\u{104c6}'),
+  ('\u{104ef}', '\u{104c7}'),
+  ('\u{104f0}', '\u{104c8}'),
+  ('\u{104f1}', '\u{104c9}'),
+  ('\u{104f2}', '\u{104ca}'),
+  ('\u{104f3}', '\u{104cb}'),
+  ('\u{104f4}', '\u{104cc}'),
+  ('\u{104f5}', '\u{104cd}'),
+  ('\u{104f6}', '\u{104ce}'),
+  ('\u{104f7}', '\u{104cf}'),
+  ('\u{104f8}', '\u{104d0}'),
+  ('\u{104f9}', '\u{104d1}'),
+  ('\u{104fa}', '\u{104d2}'),
+  ('\u{104fb}', '\u{104d3}'),
+  ('\u{10597}', '\u{10570}'),
+  ('\u{10598}', '\u{10571}'),
+  ('\u{10599}', '\u{10572}'),
+  ('\u{1059a}', '\u{10573}'),
+  ('\u{1059b}', '\u{10574}'),
+  ('\u{1059c}', '\u{10575}'),
+  ('\u{1059d}', '\u{10576}'),
+  ('\u{1059e}', '\u{10577}'),
+  ('\u{1059f}', '\u{10578}'),
+  ('\u{105a0}', '\u{10579}'),
+  ('\u{105a1}', '\u{1057a}'),
+  ('\u{105a3}', '\u{1057c}'),
+  ('\u{105a4}', '\u{1057d}'),
+  ('\u{105a5}', '\u{1057e}'),
+  ('\u{105a6}', '\u{1057f}'),
+  ('\u{105a7}', '\u{10580}'),
+  ('\u{105a8}', '\u{10581}'),
+  ('\u{105a9}', '\u{10582}'),
+  ('\u{105aa}', '\u{10583}'),
+  ('\u{105ab}', '\u{10584}'),
+  ('\u{105ac}', '\u{10585}'),
+  ('\u{105ad}', '\u{10586}'),
+  ('\u{105ae}', '\u{10587}'),
+  ('\u{105af}', '\u{10588}'),
+  ('\u{105b0}', '\u{10589}'),
+  ('\u{105b1}', '\u{1058a}'),
+  ('\u{105b3}', '\u{1058c}'),
+  ('\u{105b4}', '\u{1058d}'),
+  ('\u{105b5}', '\u{1058e}'),
+  ('\u{105b6}', '\u{1058f}'),
+  ('\u{105b7}', '\u{10590}'),
+  ('\u{105b8}', '\u{10591}'),
+  ('\u{105b9}', '\u{10592}'),
+  ('\u{105bb}', '\u{10594}'),
+  ('\u{105bc}', '\u{10595}'),
+  ('\u{10cc0}', '\u{10c80}'),
+  ('\u{10cc1}', '\u{10c81}'),
+  ('\u{10cc2}', '\u{10c82}'),
+  ('\u{10cc3}', '\u{10c83}'),
+  ('\u{10cc4}', '\u{10c84}'),
+  ('\u{10cc5}', '\u{10c85}'),
+  ('\u{10cc6}', '\u{10c86}'),
+  ('\u{10cc7}', '\u{10c87}'),
+  ('\u{10cc8}', '\u{10c88}'),
+  ('\u{10cc9}', '\u{10c89}'),
+  ('\u{10cca}', '\u{10c8a}'),
+  ('\u{10ccb}', '\u{10c8b}'),
+  ('\u{10ccc}', '\u{10c8c}'),
+  ('\u{10ccd}', '\u{10c8d}'),
+  ('\u{10cce}', '\u{10c8e}'),
+  ('\u{10ccf}', '\u{10c8f}'),
+  ('\u{10cd0}', '\u{10c90}'),
+  ('\u{10cd1}', '\u{10c91}'),
+  ('\u{10cd2}', '\u{10c92}'),
+  ('\u{10cd3}', '\u{10c93}'),
+  ('\u{10cd4}', '\u{10c94}'),
+  ('\u{10cd5}', '\u{10c95}'),
+  ('\u{10cd6}', '\u{10c96}'),
+  ('\u{10cd7}', '\u{10c97}'),
+  ('\u{10cd8}', '\u{10c98}'),
+  ('\u{10cd9}', '\u{10c99}'),
+  ('\u{10cda}', '\u{10c9a}'),
+  ('\u{10cdb}', '\u{10c9b}'),
+  ('\u{10cdc}', '\u{10c9c}'),
+  ('\u{10cdd}', '\u{10c9d}'),
+  ('\u{10cde}', '\u{10c9e}'),
+  ('\u{10cdf}', '\u{10c9f}'),
+  ('\u{10ce0}', '\u{10ca0}'),
+  ('\u{10ce1}', '\u{10ca1}'),
+  ('\u{10ce2}', '\u{10ca2}'),
+  ('\u{10ce3}', '\u{10ca3}'),
+  ('\u{10ce4}', '\u{10ca4}'),
+  ('\u{10ce5}', '\u{10ca5}'),
+  ('\u{10ce6}', '\u{10ca6}'),
+  ('\u{10ce7}', '\u{10ca7}'),
+  ('\u{10ce8}', '\u{10ca8}'),
+  ('\u{10ce9}', '\u{10ca9}'),
+  ('\u{10cea}', '\u{10caa}'),
+  ('\u{10ceb}', '\u{10cab}'),
+  ('\u{10cec}', '\u{10cac}'),
+  ('\u{10ced}', '\u{10cad}'),
+  ('\u{10cee}', '\u{10cae}'),
+  ('\u{10cef}', '\u{10caf}'),
+  ('\u{10cf0}', '\u{10cb0}'),
+  ('\u{10cf1}', '\u{10cb1}'),
+  ('\u{10cf2}', '\u{10cb2}'),
+  ('\u{10d70}', '\u{10d50}'),
+  ('\u{10d71}', '\u{10d51}'),
+  ('\u{10d72}', '\u{10d52}'),
+  ('\u{10d73}', '\u{10d53}'),
+  ('\u{10d74}', '\u{10d54}'),
+  ('\u{10d75}', '\u{10d55}'),
+  ('\u{10d76}', '\u{10d56}'),
+  ('\u{10d77}', '\u{10d57}'),
+  ('\u{10d78}', '\u{10d58}'),
+  ('\u{10d79}', '\u{10d59}'),
+  ('\u{10d7a}', '\u{10d5a}'),
+  ('\u{10d7b}', '\u{10d5b}'),
+  ('\u{10d7c}', '\u{10d5c}'),
+  ('\u{10d7d}', '\u{10d5d}'),
+  ('\u{10d7e}', '\u{10d5e}'),
+  ('\u{10d7f}', '\u{10d5f}'),
+  ('\u{10d80}', '\u{10d60}'),
+  ('\u{10d81}', '\u{10d61}'),
+  ('\u{10d82}', '\u{10d62}'),
+  ('\u{10d83}', '\u{10d63}'),
+  ('\u{10d84}', '\u{10d64}'),
+  ('\u{10d85}', '\u{10d65}'),
+  ('\u{118c0}', '\u{118a0}'),
+  ('\u{118c1}', '\u{118a1}'),
+  ('\u{118c2}', '\u{118a2}'),
+  ('\u{118c3}', '\u{118a3}'),
+  ('\u{118c4}', '\u{118a4}'),
+  ('\u{118c5}', '\u{118a5}'),
+  ('\u{118c6}', '\u{118a6}'),
+  ('\u{118c7}', '\u{118a7}'),
+  ('\u{118c8}', '\u{118a8}'),
+  ('\u{118c9}', '\u{118a9}'),
+  ('\u{118ca}', '\u{118aa}'),
+  ('\u{118cb}', '\u{118ab}'),
+  ('\u{118cc}', '\u{118ac}'),
+  ('\u{118cd}', '\u{118ad}'),
+  ('\u{118ce}', '\u{118ae}'),
+  ('\u{118cf}', '\u{118af}'),
+  ('\u{118d0}', '\u{118b0}'),
+  ('\u{118d1}', '\u{118b1}'),
+  ('\u{118d2}', '\u{118b2}'),
+  ('\u{118d3}', '\u{118b3}'),
+  ('\u{118d4}', '\u{118b4}'),
+  ('\u{118d5}', '\u{118b5}'),
+  ('\u{118d6}', '\u{118b6}'),
+  ('\u{118d7}', '\u{118b7}'),
+  ('\u{118d8}', '\u{118b8}'),
+  ('\u{118d9}', '\u{118b9}'),
+  ('\u{118da}', '\u{118ba}'),
+  ('\u{118db}', '\u{118bb}'),
+  ('\u{118dc}', '\u{118bc}'),
+  ('\u{118dd}', '\u{118bd}'),
+  ('\u{118de}', '\u{118be}'),
+  ('\u{118df}', '\u{118bf}'),
+  ('\u{16e60}', '\u{16e40}'),
+  ('\u{16e61}', '\u{16e41}'),
+  ('\u{16e62}', '\u{16e42}'),
+  ('\u{16e63}', '\u{16e43}'),
+  ('\u{16e64}', '\u{16e44}'),
+  ('\u{16e65}', '\u{16e45}'),
+  ('\u{16e66}', '\u{16e46}'),
+  ('\u{16e67}', '\u{16e47}'),
+  ('\u{16e68}', '\u{16e48}'),
+  ('\u{16e69}', '\u{16e49}'),
+  ('\u{16e6a}', '\u{16e4a}'),
+  ('\u{16e6b}', '\u{16e4b}'),
+  ('\u{16e6c}', '\u{16e4c}'),
+  ('\u{16e6d}', '\u{16e4d}'),
+  ('\u{16e6e}', '\u{16e4e}'),
+  ('\u{16e6f}', '\u{16e4f}'),
+  ('\u{16e70}', '\u{16e50}'),
+  ('\u{16e71}', '\u{16e51}'),
+  ('\u{16e72}', '\u{16e52}'),
+  ('\u{16e73}', '\u{16e53}'),
+  ('\u{16e74}', '\u{16e54}'),
+  ('\u{16e75}', '\u{16e55}'),
+  ('\u{16e76}', '\u{16e56}'),
+  ('\u{16e77}', '\u{16e57}'),
+  ('\u{16e78}', '\u{16e58}'),
+  ('\u{16e79}', '\u{16e59}'),
+  ('\u{16e7a}', '\u{16e5a}'),
+  ('\u{16e7b}', '\u{16e5b}'),
+  ('\u{16e7c}', '\u{16e5c}'),
+  ('\u{16e7d}', '\u{16e5d}'),
+  ('\u{16e7e}', '\u{16e5e}'),
+  ('\u{16e7f}', '\u{16e5f}'),
+  ('\u{1e922}', '\u{1e900}'),
+  ('\u{1e923}', '\u{1e901}'),
+  ('\u{1e924}', '\u{1e902}'),
+  ('\u{1e925}', '\u{1e903}'),
+  ('\u{1e926}', '\u{1e904}'),
+  ('\u{1e927}', '\u{1e905}'),
+  ('\u{1e928}', '\u{1e906}'),
+  ('\u{1e929}', '\u{1e907}'),
+  ('\u{1e92a}', '\u{1e908}'),
+  ('\u{1e92b}', '\u{1e909}'),
+  ('\u{1e92c}', '\u{1e90a}'),
+  ('\u{1e92d}', '\u{1e90b}'),
+  ('\u{1e92e}', '\u{1e90c}'),
+  ('\u{1e92f}', '\u{1e90d}'),
+  ('\u{1e930}', '\u{1e90e}'),
+  ('\u{1e931}', '\u{1e90f}'),
+  ('\u{1e932}', '\u{1e910}'),
+  ('\u{1e933}', '\u{1e911}'),
+  ('\u{1e934}', '\u{1e912}'),
+  ('\u{1e935}', '\u{1e913}'),
+  ('\u{1e936}', '\u{1e914}'),
+  ('\u{1e937}', '\u{1e915}'),
+  ('\u{1e938}', '\u{1e916}'),
+  ('\u{1e939}', '\u{1e917}'),
+  ('\u{1e93a}', '\u{1e918}'),
+  ('\u{1e93b}', '\u{1e919}'),
+  ('\u{1e93c}', '\u{1e91a}'),
+  ('\u{1e93d}', '\u{1e91b}'),
+  ('\u{1e93e}', '\u{1e91c}'),
+  ('\u{1e93f}', '\u{1e91d}'),
+  ('\u{1e940}', '\u{1e91e}'),
+  ('\u{1e941}', '\u{1e91f}'),
+  ('\u{1e942}', '\u{1e920}'),
+  ('\u{1e943}', '\u{1e921}'),
+];
+pub const NFD_BEFORE_FOLDING: &[char] = &[
+  '\u{345}',
+  '\u{1f80}',
+  '\u{1f81}',
+  '\u{1f82}',
+  '\u{1f83}',
+  '\u{1f84}',
+  '\u{1f85}',
+  '\u{1f86}',
+  '\u{1f87}',
+  '\u{1f88}',
+  '\u{1f89}',
+  '\u{1f8a}',
+  '\u{1f8b}',
+  '\u{1f8c}',
+  '\u{1f8d}',
+  '\u{1f8e}',
+  '\u{1f8f}',
+  '\u{1f90}',
+  '\u{1f91}',
+  '\u{1f92}',
+  '\u{1f93}',
+  '\u{1f94}',
+  '\u{1f95}',
+  '\u{1f96}',
+  '\u{1f97}',
+  '\u{1f98}',
+  '\u{1f99}',
+  '\u{1f9a}',
+  '\u{1f9b}',
+  '\u{1f9c}',
+  '\u{1f9d}',
+  '\u{1f9e}',
+  '\u{1f9f}',
+  '\u{1fa0}',
+  '\u{1fa1}',
+  '\u{1fa2}',
+  '\u{1fa3}',
+  '\u{1fa4}',
+  '\u{1fa5}',
+  '\u{1fa6}',
+  '\u{1fa7}',
+  '\u{1fa8}',
+  '\u{1fa9}',
+  '\u{1faa}',
+  '\u{1fab}',
+  '\u{1fac}',
+  '\u{1fad}',
+  '\u{1fae}',
+  '\u{1faf}',
+  '\u{1fb2}',
+  '\u{1fb3}',
+  '\u{1fb4}',
+  '\u{1fb7}',
+  '\u{1fbc}',
+  '\u{1fc2}',
+  '\u{1fc3}',
+  '\u{1fc4}',
+  '\u{1fc7}',
+  '\u{1fcc}',
+  '\u{1ff2}',
+  '\u{1ff3}',
+  '\u{1ff4}',
+  '\u{1ff7}',
+  '\u{1ffc}',
+];
+#[cfg(feature = "unicode-10")]
+pub const CASE_FOLDING_TABLE_10_0_0: &[(char, [char; 3])] = &[
+  ('A', ['a', '\0', '\0']),
+  ('B', ['b', '\0', '\0']),
+  ('C', ['c', '\0', '\0']),
+  ('D', ['d', '\0', '\0']),
+  ('E', ['e', '\0', '\0']),
+  ('F', ['f', '\0', '\0']),
+  ('G', ['g', '\0', '\0']),
+  ('H', ['h', '\0', '\0']),
+  ('I', ['i', '\0', '\0']),
+  ('J', ['j', '\0', '\0']),
+  ('K', ['k', '\0', '\0']),
+  ('L', ['l', '\0', '\0']),
+  ('M', ['m', '\0', '\0']),
+  ('N', ['n', '\0', '\0']),
+  ('O', ['o', '\0', '\0']),
+  ('P', ['p', '\0', '\0']),
+  ('Q', ['q', '\0', '\0']),
+  ('R', ['r', '\0', '\0']),
+  ('S', ['s', '\0', '\0']),
+  ('T', ['t', '\0', '\0']),
+  ('U', ['u', '\0', '\0']),
+  ('V', ['v', '\0', '\0']),
+  ('W', ['w', '\0', '\0']),
+  ('X', ['x', '\0', '\0']),
+  ('Y', ['y', '\0', '\0']),
+  ('Z', ['z', '\0', '\0']),
+  ('\u{b5}', ['\u{3bc}', '\0', '\0']),
+  ('\u{c0}', ['\u{e0}', '\0', '\0']),
+  ('\u{c1}', ['\u{e1}', '\0', '\0']),
+  ('\u{c2}', ['\u{e2}', '\0', '\0']),
+  ('\u{c3}', ['\u{e3}', '\0', '\0']),
+  ('\u{c4}', ['\u{e4}', '\0', '\0']),
+  ('\u{c5}', ['\u{e5}', '\0', '\0']),
+  ('\u{c6}', ['\u{e6}', '\0', '\0']),
+  ('\u{c7}', ['\u{e7}', '\0', '\0']),
+  ('\u{c8}', ['\u{e8}', '\0', '\0']),
+  ('\u{c9}', ['\u{e9}', '\0', '\0']),
+  ('\u{ca}', ['\u{ea}', '\0', '\0']),
+  ('\u{cb}', ['\u{eb}', '\0', '\0']),
+  ('\u{cc}', ['\u{ec}', '\0', '\0']),
+  ('\u{cd}', ['\u{ed}', '\0', '\0']),
+  ('\u{ce}', ['\u{ee}', '\0', '\0']),
+  ('\u{cf}', ['\u{ef}', '\0', '\0']),
+  ('\u{d0}', ['\u{f0}', '\0', '\0']),
+  ('\u{d1}', ['\u{f1}', '\0', '\0']),
+  ('\u{d2}', ['\u{f2}', '\0', '\0']),
+  ('\u{d3}', ['\u{f3}', '\0', '\0']),
+  ('\u{d4}', ['\u{f4}', '\0', '\0']),
+  ('\u{d5}', ['\u{f5}', '\0', '\0']),
+  ('\u{d6}', ['\u{f6}', '\0', '\0']),
+  ('\u{d8}', ['\u{f8}', '\0', '\0']),
+  ('\u{d9}', ['\u{f9}', '\0', '\0']),
+  ('\u{da}', ['\u{fa}', '\0', '\0']),
+  ('\u{db}', ['\u{fb}', '\0', '\0']),
+  ('\u{dc}', ['\u{fc}', '\0', '\0']),
+  ('\u{dd}', ['\u{fd}', '\0', '\0']),
+  ('\u{de}', ['\u{fe}', '\0', '\0']),
+  ('\u{df}', ['s', 's', '\0']),
+  ('\u{100}', ['\u{101}', '\0', '\0']),
+  ('\u{102}', ['\u{103}', '\0', '\0']),
+  ('\u{104}', ['\u{105}', '\0', '\0']),
+  ('\u{106}', ['\u{107}', '\0', '\0']),
+  ('\u{108}', ['\u{109}', '\0', '\0']),
+  ('\u{10a}', ['\u{10b}', '\0', '\0']),
+  ('\u{10c}', ['\u{10d}', '\0', '\0']),
+  ('\u{10e}', ['\u{10f}', '\0', '\0']),
+  ('\u{110}', ['\u{111}', '\0', '\0']),
+  ('\u{112}', ['\u{113}', '\0', '\0']),
+  ('\u{114}', ['\u{115}', '\0', '\0']),
+  ('\u{116}', ['\u{117}', '\0', '\0']),
+  ('\u{118}', ['\u{119}', '\0', '\0']),
+  ('\u{11a}', ['\u{11b}', '\0', '\0']),
+  ('\u{11c}', ['\u{11d}', '\0', '\0']),
+  ('\u{11e}', ['\u{11f}', '\0', '\0']),
+  ('\u{120}', ['\u{121}', '\0', '\0']),
+  ('\u{122}', ['\u{123}', '\0', '\0']),
+  ('\u{124}', ['\u{125}', '\0', '\0']),
+  ('\u{126}', ['\u{127}', '\0', '\0']),
+  ('\u{128}', ['\u{129}', '\0', '\0']),
+  ('\u{12a}', ['\u{12b}', '\0', '\0']),
+  ('\u{12c}', ['\u{12d}', '\0', '\0']),
+  ('\u{12e}', ['\u{12f}', '\0', '\0']),
+  ('\u{130}', ['i', '\u{307}', '\0']),
+  ('\u{132}', ['\u{133}', '\0', '\0']),
+  ('\u{134}', ['\u{135}', '\0', '\0']),
+  ('\u{136}', ['\u{137}', '\0', '\0']),
+  ('\u{139}', ['\u{13a}', '\0', '\0']),
+  ('\u{13b}', ['\u{13c}', '\0', '\0']),
+  ('\u{13d}', ['\u{13e}', '\0', '\0']),
+  ('\u{13f}', ['\u{140}', '\0', '\0']),
+  ('\u{141}', ['\u{142}', '\0', '\0']),
+  ('\u{143}', ['\u{144}', '\0', '\0']),
+  ('\u{145}', ['\u{146}', '\0', '\0']),
+  ('\u{147}', ['\u{148}', '\0', '\0']),
+  ('\u{149}', ['\u{2bc}', 'n', '\0']),
+  ('\u{14a}', ['\u{14b}', '\0', '\0']),
+  ('\u{14c}', ['\u{14d}', '\0', '\0']),
+  ('\u{14e}', ['\u{14f}', '\0', '\0']),
+  ('\u{150}', ['\u{151}', '\0', '\0']),
+  ('\u{152}', ['\u{153}', '\0', '\0']),
+  ('\u{154}', ['\u{155}', '\0', '\0']),
+  ('\u{156}', ['\u{157}', '\0', '\0']),
+  ('\u{158}', ['\u{159}', '\0', '\0']),
+  ('\u{15a}', ['\u{15b}', '\0', '\0']),
+  ('\u{15c}', ['\u{15d}', '\0', '\0']),
+  ('\u{15e}', ['\u{15f}', '\0', '\0']),
+  ('\u{160}', ['\u{161}', '\0', '\0']),
+  ('\u{162}', ['\u{163}', '\0', '\0']),
+  ('\u{164}', ['\u{165}', '\0', '\0']),
+  ('\u{166}', ['\u{167}', '\0', '\0']),
+  ('\u{168}', ['\u{169}', '\0', '\0']),
+  ('\u{16a}', ['\u{16b}', '\0', '\0']),
+  ('\u{16c}', ['\u{16d}', '\0', '\0']),
+  ('\u{16e}', ['\u{16f}', '\0', '\0']),
+  ('\u{170}', ['\u{171}', '\0', '\0']),
+  ('\u{172}', ['\u{173}', '\0', '\0']),
+  ('\u{174}', ['\u{175}', '\0', '\0']),
+  ('\u{176}', ['\u{177}', '\0', '\0']),
+  ('\u{178}', ['\u{ff}', '\0', '\0']),
+  ('\u{179}', ['\u{17a}', '\0', '\0']),
+  ('\u{17b}', ['\u{17c}', '\0', '\0']),
+  ('\u{17d}', ['\u{17e}', '\0', '\0']),
+  ('\u{17f}', ['s', '\0', '\0']),
+  ('\u{181}', ['\u{253}', '\0', '\0']),
+  ('\u{182}', ['\u{183}', '\0', '\0']),
+  ('\u{184}', ['\u{185}', '\0', '\0']),
+  ('\u{186}', ['\u{254}', '\0', '\0']),
+  ('\u{187}', ['\u{188}', '\0', '\0']),
+  ('\u{189}', ['\u{256}', '\0', '\0']),
+  ('\u{18a}', ['\u{257}', '\0', '\0']),
+  ('\u{18b}', ['\u{18c}', '\0', '\0']),
+  ('\u{18e}', ['\u{1dd}', '\0', '\0']),
+  ('\u{18f}', ['\u{259}', '\0', '\0']),
+  ('\u{190}', ['\u{25b}', '\0', '\0']),
+  ('\u{191}', ['\u{192}', '\0', '\0']),
+  ('\u{193}', ['\u{260}', '\0', '\0']),
+  ('\u{194}', ['\u{263}', '\0', '\0']),
+  ('\u{196}', ['\u{269}', '\0', '\0']),
+  ('\u{197}', ['\u{268}', '\0', '\0']),
+  ('\u{198}', ['\u{199}', '\0', '\0']),
+  ('\u{19c}', ['\u{26f}', '\0', '\0']),
+  ('\u{19d}', ['\u{272}', '\0', '\0']),
+  ('\u{19f}', ['\u{275}', '\0', '\0']),
+  ('\u{1a0}', ['\u{1a1}', '\0', '\0']),
+  ('\u{1a2}', ['\u{1a3}', '\0', '\0']),
+  ('\u{1a4}', ['\u{1a5}', '\0', '\0']),
+  ('\u{1a6}', ['\u{280}', '\0', '\0']),
+  ('\u{1a7}', ['\u{1a8}', '\0', '\0']),
+  ('\u{1a9}', ['\u{283}', '\0', '\0']),
+  ('\u{1ac}', ['\u{1ad}', '\0', '\0']),
+  ('\u{1ae}', ['\u{288}', '\0', '\0']),
+  ('\u{1af}', ['\u{1b0}', '\0', '\0']),
+  ('\u{1b1}', ['\u{28a}', '\0', '\0']),
+  ('\u{1b2}', ['\u{28b}', '\0', '\0']),
+  ('\u{1b3}', ['\u{1b4}', '\0', '\0']),
+  ('\u{1b5}', ['\u{1b6}', '\0', '\0']),
+  ('\u{1b7}', ['\u{292}', '\0', '\0']),
+  ('\u{1b8}', ['\u{1b9}', '\0', '\0']),
+  ('\u{1bc}', ['\u{1bd}', '\0', '\0']),
+  ('\u{1c4}', ['\u{1c6}', '\0', '\0']),
+  ('\u{1c5}', ['\u{1c6}', '\0', '\0']),
+  ('\u{1c7}', ['\u{1c9}', '\0', '\0']),
+  ('\u{1c8}', ['\u{1c9}', '\0', '\0']),
+  ('\u{1ca}', ['\u{1cc}', '\0', '\0']),
+  ('\u{1cb}', ['\u{1cc}', '\0', '\0']),
+  ('\u{1cd}', ['\u{1ce}', '\0', '\0']),
+  ('\u{1cf}', ['\u{1d0}', '\0', '\0']),
+  ('\u{1d1}', ['\u{1d2}', '\0', '\0']),
+  ('\u{1d3}', ['\u{1d4}', '\0', '\0']),
+  ('\u{1d5}', ['\u{1d6}', '\0', '\0']),
+  ('\u{1d7}', ['\u{1d8}', '\0', '\0']),
+  ('\u{1d9}', ['\u{1da}', '\0', '\0']),
+  ('\u{1db}', ['\u{1dc}', '\0', '\0']),
+  ('\u{1de}', ['\u{1df}', '\0', '\0']),
+  ('\u{1e0}', ['\u{1e1}', '\0', '\0']),
+  ('\u{1e2}', ['\u{1e3}', '\0', '\0']),
+  ('\u{1e4}', ['\u{1e5}', '\0', '\0']),
+  ('\u{1e6}', ['\u{1e7}', '\0', '\0']),
+  ('\u{1e8}', ['\u{1e9}', '\0', '\0']),
+  ('\u{1ea}', ['\u{1eb}', '\0', '\0']),
+  ('\u{1ec}', ['\u{1ed}', '\0', '\0']),
+  ('\u{1ee}', ['\u{1ef}', '\0', '\0']),
+  ('\u{1f0}', ['j', '\u{30c}', '\0']),
+  ('\u{1f1}', ['\u{1f3}', '\0', '\0']),
+  ('\u{1f2}', ['\u{1f3}', '\0', '\0']),
+  ('\u{1f4}', ['\u{1f5}', '\0', '\0']),
+  ('\u{1f6}', ['\u{195}', '\0', '\0']),
+  ('\u{1f7}', ['\u{1bf}', '\0', '\0']),
+  ('\u{1f8}', ['\u{1f9}', '\0', '\0']),
+  ('\u{1fa}', ['\u{1fb}', '\0', '\0']),
+  ('\u{1fc}', ['\u{1fd}', '\0', '\0']),
+  ('\u{1fe}', ['\u{1ff}', '\0', '\0']),
+  ('\u{200}', ['\u{201}', '\0', '\0']),
+  ('\u{202}', ['\u{203}', '\0', '\0']),
+  ('\u{204}', ['\u{205}', '\0', '\0']),
+  ('\u{206}', ['\u{207}', '\0', '\0']),
+  ('\u{208}', ['\u{209}', '\0', '\0']),
+  ('\u{20a}', ['\u{20b}', '\0', '\0']),
+  ('\u{20c}', ['\u{20d}', '\0', '\0']),
+  ('\u{20e}', ['\u{20f}', '\0', '\0']),
+  ('\u{210}', ['\u{211}', '\0', '\0']),
+  ('\u{212}', ['\u{213}', '\0', '\0']),
+  ('\u{214}', ['\u{215}', '\0', '\0']),
+  ('\u{216}', ['\u{217}', '\0', '\0']),
+  ('\u{218}', ['\u{219}', '\0', '\0']),
+  ('\u{21a}', ['\u{21b}', '\0', '\0']),
+  ('\u{21c}', ['\u{21d}', '\0', '\0']),
+  ('\u{21e}', ['\u{21f}', '\0', '\0']),
+  ('\u{220}', ['\u{19e}', '\0', '\0']),
+  ('\u{222}', ['\u{223}', '\0', '\0']),
+  ('\u{224}', ['\u{225}', '\0', '\0']),
+  ('\u{226}', ['\u{227}', '\0', '\0']),
+  ('\u{228}', ['\u{229}', '\0', '\0']),
+  ('\u{22a}', ['\u{22b}', '\0', '\0']),
+  ('\u{22c}', ['\u{22d}', '\0', '\0']),
+  ('\u{22e}', ['\u{22f}', '\0', '\0']),
+  ('\u{230}', ['\u{231}', '\0', '\0']),
+  ('\u{232}', ['\u{233}', '\0', '\0']),
+  ('\u{23a}', ['\u{2c65}', '\0', '\0']),
+  ('\u{23b}', ['\u{23c}', '\0', '\0']),
+  ('\u{23d}', ['\u{19a}', '\0', '\0']),
+  ('\u{23e}', ['\u{2c66}', '\0', '\0']),
+  ('\u{241}', ['\u{242}', '\0', '\0']),
+  ('\u{243}', ['\u{180}', '\0', '\0']),
+  ('\u{244}', ['\u{289}', '\0', '\0']),
+  ('\u{245}', ['\u{28c}', '\0', '\0']),
+  ('\u{246}', ['\u{247}', '\0', '\0']),
+  ('\u{248}', ['\u{249}', '\0', '\0']),
+  ('\u{24a}', ['\u{24b}', '\0', '\0']),
+  ('\u{24c}', ['\u{24d}', '\0', '\0']),
+  ('\u{24e}', ['\u{24f}', '\0', '\0']),
+  ('\u{345}', ['\u{3b9}', '\0', '\0']),
+  ('\u{370}', ['\u{371}', '\0', '\0']),
+  ('\u{372}', ['\u{373}', '\0', '\0']),
+  ('\u{376}', ['\u{377}', '\0', '\0']),
+  ('\u{37f}', ['\u{3f3}', '\0', '\0']),
+  ('\u{386}', ['\u{3ac}', '\0', '\0']),
+  ('\u{388}', ['\u{3ad}', '\0', '\0']),
+  ('\u{389}', ['\u{3ae}', '\0', '\0']),
+  ('\u{38a}', ['\u{3af}', '\0', '\0']),
+  ('\u{38c}', ['\u{3cc}', '\0', '\0']),
+  ('\u{38e}', ['\u{3cd}', '\0', '\0']),
+  ('\u{38f}', ['\u{3ce}', '\0', '\0']),
+  ('\u{390}', ['\u{3b9}', '\u{308}', '\u{301}']),
+  ('\u{391}', ['\u{3b1}', '\0', '\0']),
+  ('\u{392}', ['\u{3b2}', '\0', '\0']),
+  ('\u{393}', ['\u{3b3}', '\0', '\0']),
+  ('\u{394}', ['\u{3b4}', '\0', '\0']),
+  ('\u{395}', ['\u{3b5}', '\0', '\0']),
+  ('\u{396}', ['\u{3b6}', '\0', '\0']),
+  ('\u{397}', ['\u{3b7}', '\0', '\0']),
+  ('\u{398}', ['\u{3b8}', '\0', '\0']),
+  ('\u{399}', ['\u{3b9}', '\0', '\0']),
+  ('\u{39a}', ['\u{3ba}', '\0', '\0']),
+  ('\u{39b}', ['\u{3bb}', '\0', '\0']),
+  ('\u{39c}', ['\u{3bc}', '\0', '\0']),
+  ('\u{39d}', ['\u{3bd}', '\0', '\0']),
+  ('\u{39e}', ['\u{3be}', '\0', '\0']),
+  ('\u{39f}', ['\u{3bf}', '\0', '\0']),
+  ('\u{3a0}', ['\u{3c0}', '\0', '\0']),
+  ('\u{3a1}', ['\u{3c1}', '\0', '\0']),
+  ('\u{3a3}', ['\u{3c3}', '\0', '\0']),
+  ('\u{3a4}', ['\u{3c4}', '\0', '\0']),
+  ('\u{3a5}', ['\u{3c5}', '\0', '\0']),
+  ('\u{3a6}', ['\u{3c6}', '\0', '\0']),
+  ('\u{3a7}', ['\u{3c7}', '\0', '\0']),
+  ('\u{3a8}', ['\u{3c8}', '\0', '\0']),
+  ('\u{3a9}', ['\u{3c9}', '\0', '\0']),
+  ('\u{3aa}', ['\u{3ca}', '\0', '\0']),
+  ('\u{3ab}', ['\u{3cb}', '\0', '\0']),
+  ('\u{3b0}', ['\u{3c5}', '\u{308}', '\u{301}']),
+  ('\u{3c2}', ['\u{3c3}', '\0', '\0']),
+  ('\u{3cf}', ['\u{3d7}', '\0', '\0']),
+  ('\u{3d0}', ['\u{3b2}', '\0', '\0']),
+  ('\u{3d1}', ['\u{3b8}', '\0', '\0']),
+  ('\u{3d5}', ['\u{3c6}', '\0', '\0']),
+  ('\u{3d6}', ['\u{3c0}', '\0', '\0']),
+  ('\u{3d8}', ['\u{3d9}', '\0', '\0']),
+  ('\u{3da}', ['\u{3db}', '\0', '\0']),
+  ('\u{3dc}', ['\u{3dd}', '\0', '\0']),
+  ('\u{3de}', ['\u{3df}', '\0', '\0']),
+  ('\u{3e0}', ['\u{3e1}', '\0', '\0']),
+  ('\u{3e2}', ['\u{3e3}', '\0', '\0']),
+  ('\u{3e4}', ['\u{3e5}', '\0', '\0']),
+  ('\u{3e6}', ['\u{3e7}', '\0', '\0']),
+  ('\u{3e8}', ['\u{3e9}', '\0', '\0']),
+  ('\u{3ea}', ['\u{3eb}', '\0', '\0']),
+  ('\u{3ec}', ['\u{3ed}', '\0', '\0']),
+  ('\u{3ee}', ['\u{3ef}', '\0', '\0']),
+  ('\u{3f0}', ['\u{3ba}', '\0', '\0']),
+  ('\u{3f1}', ['\u{3c1}', '\0', '\0']),
+  ('\u{3f4}', ['\u{3b8}', '\0', '\0']),
+  ('\u{3f5}', ['\u{3b5}', '\0', '\0']),
+  ('\u{3f7}', ['\u{3f8}', '\0', '\0']),
+  ('\u{3f9}', ['\u{3f2}', '\0', '\0']),
+  ('\u{3fa}', ['\u{3fb}', '\0', '\0']),
+  ('\u{3fd}', ['\u{37b}', '\0', '\0']),
+  ('\u{3fe}', ['\u{37c}', '\0', '\0']),
+  ('\u{3ff}', ['\u{37d}', '\0', '\0']),
+  ('\u{400}', ['\u{450}', '\0', '\0']),
+  ('\u{401}', ['\u{451}', '\0', '\0']),
+  ('\u{402}', ['\u{452}', '\0', '\0']),
+  ('\u{403}', ['\u{453}', '\0', '\0']),
+  ('\u{404}', ['\u{454}', '\0', '\0']),
+  ('\u{405}', ['\u{455}', '\0', '\0']),
+  ('\u{406}', ['\u{456}', '\0', '\0']),
+  ('\u{407}', ['\u{457}', '\0', '\0']),
+  ('\u{408}', ['\u{458}', '\0', '\0']),
+  ('\u{409}', ['\u{459}', '\0', '\0']),
+  ('\u{40a}', ['\u{45a}', '\0', '\0']),
+  ('\u{40b}', ['\u{45b}', '\0', '\0']),
+  ('\u{40c}', ['\u{45c}', '\0', '\0']),
+  ('\u{40d}', ['\u{45d}', '\0', '\0']),
+  ('\u{40e}', ['\u{45e}', '\0', '\0']),
+  ('\u{40f}', ['\u{45f}', '\0', '\0']),
+  ('\u{410}', ['\u{430}', '\0', '\0']),
+  ('\u{411}', ['\u{431}', '\0', '\0']),
+  ('\u{412}', ['\u{432}', '\0', '\0']),
+  ('\u{413}', ['\u{433}', '\0', '\0']),
+  ('\u{414}', ['\u{434}', '\0', '\0']),
+  ('\u{415}', ['\u{435}', '\0', '\0']),
+  ('\u{416}', ['\u{436}', '\0', '\0']),
+  ('\u{417}', ['\u{437}', '\0', '\0']),
+  ('\u{418}', ['\u{438}', '\0', '\0']),
+  ('\u{419}', ['\u{439}', '\0', '\0']),
+  ('\u{41a}', ['\u{43a}', '\0', '\0']),
+  ('\u{41b}', ['\u{43b}', '\0', '\0']),
+  ('\u{41c}', ['\u{43c}', '\0', '\0']),
+  ('\u{41d}', ['\u{43d}', '\0', '\0']),
+  ('\u{41e}', ['\u{43e}', '\0', '\0']),
+  ('\u{41f}', ['\u{43f}', '\0', '\0']),
+  ('\u{420}', ['\u{440}', '\0', '\0']),
+  ('\u{421}', ['\u{441}', '\0', '\0']),
+  ('\u{422}', ['\u{442}', '\0', '\0']),
+  ('\u{423}', ['\u{443}', '\0', '\0']),
+  ('\u{424}', ['\u{444}', '\0', '\0']),
+  ('\u{425}', ['\u{445}', '\0', '\0']),
+  ('\u{426}', ['\u{446}', '\0', '\0']),
+  ('\u{427}', ['\u{447}', '\0', '\0']),
+  ('\u{428}', ['\u{448}', '\0', '\0']),
+  ('\u{429}', ['\u{449}', '\0', '\0']),
+  ('\u{42a}', ['\u{44a}', '\0', '\0']),
+  ('\u{42b}', ['\u{44b}', '\0', '\0']),
+  ('\u{42c}', ['\u{44c}', '\0', '\0']),
+  ('\u{42d}', ['\u{44d}', '\0', '\0']),
+  ('\u{42e}', ['\u{44e}', '\0', '\0']),
+  ('\u{42f}', ['\u{44f}', '\0', '\0']),
+  ('\u{460}', ['\u{461}', '\0', '\0']),
+  ('\u{462}', ['\u{463}', '\0', '\0']),
+  ('\u{464}', ['\u{465}', '\0', '\0']),
+  ('\u{466}', ['\u{467}', '\0', '\0']),
+  ('\u{468}', ['\u{469}', '\0', '\0']),
+  ('\u{46a}', ['\u{46b}', '\0', '\0']),
+  ('\u{46c}', ['\u{46d}', '\0', '\0']),
+  ('\u{46e}', ['\u{46f}', '\0', '\0']),
+  ('\u{470}', ['\u{471}', '\0', '\0']),
+  ('\u{472}', ['\u{473}', '\0', '\0']),
+  ('\u{474}', ['\u{475}', '\0', '\0']),
+  ('\u{476}', ['\u{477}', '\0', '\0']),
+  ('\u{478}', ['\u{479}', '\0', '\0']),
+  ('\u{47a}', ['\u{47b}', '\0', '\0']),
+  ('\u{47c}', ['\u{47d}', '\0', '\0']),
+  ('\u{47e}', ['\u{47f}', '\0', '\0']),
+  ('\u{480}', ['\u{481}', '\0', '\0']),
+  ('\u{48a}', ['\u{48b}', '\0', '\0']),
+  ('\u{48c}', ['\u{48d}', '\0', '\0']),
+  ('\u{48e}', ['\u{48f}', '\0', '\0']),
+  ('\u{490}', ['\u{491}', '\0', '\0']),
+  ('\u{492}', ['\u{493}', '\0', '\0']),
+  ('\u{494}', ['\u{495}', '\0', '\0']),
+  ('\u{496}', ['\u{497}', '\0', '\0']),
+  ('\u{498}', ['\u{499}', '\0', '\0']),
+  ('\u{49a}', ['\u{49b}', '\0', '\0']),
+  ('\u{49c}', ['\u{49d}', '\0', '\0']),
+  ('\u{49e}', ['\u{49f}', '\0', '\0']),
+  ('\u{4a0}', ['\u{4a1}', '\0', '\0']),
+  ('\u{4a2}', ['\u{4a3}', '\0', '\0']),
+  ('\u{4a4}', ['\u{4a5}', '\0', '\0']),
+  ('\u{4a6}', ['\u{4a7}', '\0', '\0']),
+  ('\u{4a8}', ['\u{4a9}', '\0', '\0']),
+  ('\u{4aa}', ['\u{4ab}', '\0', '\0']),
+  ('\u{4ac}', ['\u{4ad}', '\0', '\0']),
+  ('\u{4ae}', ['\u{4af}', '\0', '\0']),
+  ('\u{4b0}', ['\u{4b1}', '\0', '\0']),
+  ('\u{4b2}', ['\u{4b3}', '\0', '\0']),
+  ('\u{4b4}', ['\u{4b5}', '\0', '\0']),
+  ('\u{4b6}', ['\u{4b7}', '\0', '\0']),
+  ('\u{4b8}', ['\u{4b9}', '\0', '\0']),
+  ('\u{4ba}', ['\u{4bb}', '\0', '\0']),
+  ('\u{4bc}', ['\u{4bd}', '\0', '\0']),
+  ('\u{4be}', ['\u{4bf}', '\0', '\0']),
+  ('\u{4c0}', ['\u{4cf}', '\0', '\0']),
+  ('\u{4c1}', ['\u{4c2}', '\0', '\0']),
+  ('\u{4c3}', ['\u{4c4}', '\0', '\0']),
+  ('\u{4c5}', ['\u{4c6}', '\0', '\0']),
+  ('\u{4c7}', ['\u{4c8}', '\0', '\0']),
+  ('\u{4c9}', ['\u{4ca}', '\0', '\0']),
+  ('\u{4cb}', ['\u{4cc}', '\0', '\0']),
+  ('\u{4cd}', ['\u{4ce}', '\0', '\0']),
+  ('\u{4d0}', ['\u{4d1}', '\0', '\0']),
+  ('\u{4d2}', ['\u{4d3}', '\0', '\0']),
+  ('\u{4d4}', ['\u{4d5}', '\0', '\0']),
+  ('\u{4d6}', ['\u{4d7}', '\0', '\0']),
+  ('\u{4d8}', ['\u{4d9}', '\0', '\0']),
+  ('\u{4da}', ['\u{4db}', '\0', '\0']),
+  ('\u{4dc}', ['\u{4dd}', '\0', '\0']),
+  ('\u{4de}', ['\u{4df}', '\0', '\0']),
+  ('\u{4e0}', ['\u{4e1}', '\0', '\0']),
+  ('\u{4e2}', ['\u{4e3}', '\0', '\0']),
+  ('\u{4e4}', ['\u{4e5}', '\0', '\0']),
+  ('\u{4e6}', ['\u{4e7}', '\0', '\0']),
+  ('\u{4e8}', ['\u{4e9}', '\0', '\0']),
+  ('\u{4ea}', ['\u{4eb}', '\0', '\0']),
+  ('\u{4ec}', ['\u{4ed}', '\0', '\0']),
+  ('\u{4ee}', ['\u{4ef}', '\0', '\0']),
+  ('\u{4f0}', ['\u{4f1}', '\0', '\0']),
+  ('\u{4f2}', ['\u{4f3}', '\0', '\0']),
+  ('\u{4f4}', ['\u{4f5}', '\0', '\0']),
+  ('\u{4f6}', ['\u{4f7}', '\0', '\0']),
+  ('\u{4f8}', ['\u{4f9}', '\0', '\0']),
+  ('\u{4fa}', ['\u{4fb}', '\0', '\0']),
+  ('\u{4fc}', ['\u{4fd}', '\0', '\0']),
+  ('\u{4fe}', ['\u{4ff}', '\0', '\0']),
+  ('\u{500}', ['\u{501}', '\0', '\0']),
+  ('\u{502}', ['\u{503}', '\0', '\0']),
+  ('\u{504}', ['\u{505}', '\0', '\0']),
+  ('\u{506}', ['\u{507}', '\0', '\0']),
+  ('\u{508}', ['\u{509}', '\0', '\0']),
+  ('\u{50a}', ['\u{50b}', '\0', '\0']),
+  ('\u{50c}', ['\u{50d}', '\0', '\0']),
+  ('\u{50e}', ['\u{50f}', '\0', '\0']),
+  ('\u{510}', ['\u{511}', '\0', '\0']),
+  ('\u{512}', ['\u{513}', '\0', '\0']),
+  ('\u{514}', ['\u{515}', '\0', '\0']),
+  ('\u{516}', ['\u{517}', '\0', '\0']),
+  ('\u{518}', ['\u{519}', '\0', '\0']),
+  ('\u{51a}', ['\u{51b}', '\0', '\0']),
+  ('\u{51c}', ['\u{51d}', '\0', '\0']),
+  ('\u{51e}', ['\u{51f}', '\0', '\0']),
+  ('\u{520}', ['\u{521}', '\0', '\0']),
+  ('\u{522}', ['\u{523}', '\0', '\0']),
+  ('\u{524}', ['\u{525}', '\0', '\0']),
+  ('\u{526}', ['\u{527}', '\0', '\0']),
+  ('\u{528}', ['\u{529}', '\0', '\0']),
+  ('\u{52a}', ['\u{52b}', '\0', '\0']),
+  ('\u{52c}', ['\u{52d}', '\0', '\0']),
+  ('\u{52e}', ['\u{52f}', '\0', '\0']),
+  ('\u{531}', ['\u{561}', '\0', '\0']),
+  ('\u{532}', ['\u{562}', '\0', '\0']),
+  ('\u{533}', ['\u{563}', '\0', '\0']),
+  ('\u{534}', ['\u{564}', '\0', '\0']),
+  ('\u{535}', ['\u{565}', '\0', '\0']),
+  ('\u{536}', ['\u{566}', '\0', '\0']),
+  ('\u{537}', ['\u{567}', '\0', '\0']),
+  ('\u{538}', ['\u{568}', '\0', '\0']),
+  ('\u{539}', ['\u{569}', '\0', '\0']),
+  ('\u{53a}', ['\u{56a}', '\0', '\0']),
+  ('\u{53b}', ['\u{56b}', '\0', '\0']),
+  ('\u{53c}', ['\u{56c}', '\0', '\0']),
+  ('\u{53d}', ['\u{56d}', '\0', '\0']),
+  ('\u{53e}', ['\u{56e}', '\0', '\0']),
+  ('\u{53f}', ['\u{56f}', '\0', '\0']),
+  ('\u{540}', ['\u{570}', '\0', '\0']),
+  ('\u{541}', ['\u{571}', '\0', '\0']),
+  ('\u{542}', ['\u{572}', '\0', '\0']),
+  ('\u{543}', ['\u{573}', '\0', '\0']),
+  ('\u{544}', ['\u{574}', '\0', '\0']),
+  ('\u{545}', ['\u{575}', '\0', '\0']),
+  ('\u{546}', ['\u{576}', '\0', '\0']),
+  ('\u{547}', ['\u{577}', '\0', '\0']),
+  ('\u{548}', ['\u{578}', '\0', '\0']),
+  ('\u{549}', ['\u{579}', '\0', '\0']),
+  ('\u{54a}', ['\u{57a}', '\0', '\0']),
+  ('\u{54b}', ['\u{57b}', '\0', '\0']),
+  ('\u{54c}', ['\u{57c}', '\0', '\0']),
+  ('\u{54d}', ['\u{57d}', '\0', '\0']),
+  ('\u{54e}', ['\u{57e}', '\0', '\0']),
+  ('\u{54f}', ['\u{57f}', '\0', '\0']),
+  ('\u{550}', ['\u{580}', '\0', '\0']),
+  ('\u{551}', ['\u{581}', '\0', '\0']),
+  ('\u{552}', ['\u{582}', '\0', '\0']),
+  ('\u{553}', ['\u{583}', '\0', '\0']),
+  ('\u{554}', ['\u{584}', '\0', '\0']),
+  ('\u{555}', ['\u{585}', '\0', '\0']),
+  ('\u{556}', ['\u{586}', '\0', '\0']),
+  ('\u{587}', ['\u{565}', '\u{582}', '\0']),
+  ('\u{10a0}', ['\u{2d00}', '\0', '\0']),
+  ('\u{10a1}', ['\u{2d01}', '\0', '\0']),
+  ('\u{10a2}', ['\u{2d02}', '\0', '\0']),
+  ('\u{10a3}', ['\u{2d03}', '\0', '\0']),
+  ('\u{10a4}', ['\u{2d04}', '\0', '\0']),
+  ('\u{10a5}', ['\u{2d05}', '\0', '\0']),
+  ('\u{10a6}', ['\u{2d06}', '\0', '\0']),
+  ('\u{10a7}', ['\u{2d07}', '\0', '\0']),
+  ('\u{10a8}', ['\u{2d08}', '\0', '\0']),
+  ('\u{10a9}', ['\u{2d09}', '\0', '\0']),
+  ('\u{10aa}', ['\u{2d0a}', '\0', '\0']),
+  ('\u{10ab}', ['\u{2d0b}', '\0', '\0']),
+  ('\u{10ac}', ['\u{2d0c}', '\0', '\0']),
+  ('\u{10ad}', ['\u{2d0d}', '\0', '\0']),
+  ('\u{10ae}', ['\u{2d0e}', '\0', '\0']),
+  ('\u{10af}', ['\u{2d0f}', '\0', '\0']),
+  ('\u{10b0}', ['\u{2d10}', '\0', '\0']),
+  ('\u{10b1}', ['\u{2d11}', '\0', '\0']),
+  ('\u{10b2}', ['\u{2d12}', '\0', '\0']),
+  ('\u{10b3}', ['\u{2d13}', '\0', '\0']),
+  ('\u{10b4}', ['\u{2d14}', '\0', '\0']),
+  ('\u{10b5}', ['\u{2d15}', '\0', '\0']),
+  ('\u{10b6}', ['\u{2d16}', '\0', '\0']),
+  ('\u{10b7}', ['\u{2d17}', '\0', '\0']),
+  ('\u{10b8}', ['\u{2d18}', '\0', '\0']),
+  ('\u{10b9}', ['\u{2d19}', '\0', '\0']),
+  ('\u{10ba}', ['\u{2d1a}', '\0', '\0']),
+  ('\u{10bb}', ['\u{2d1b}', '\0', '\0']),
+  ('\u{10bc}', ['\u{2d1c}', '\0', '\0']),
+  ('\u{10bd}', ['\u{2d1d}', '\0', '\0']),
+  ('\u{10be}', ['\u{2d1e}', '\0', '\0']),
+  ('\u{10bf}', ['\u{2d1f}', '\0', '\0']),
+  ('\u{10c0}', ['\u{2d20}', '\0', '\0']),
+  ('\u{10c1}', ['\u{2d21}', '\0', '\0']),
+  ('\u{10c2}', ['\u{2d22}', '\0', '\0']),
+  ('\u{10c3}', ['\u{2d23}', '\0', '\0']),
+  ('\u{10c4}', ['\u{2d24}', '\0', '\0']),
+  ('\u{10c5}', ['\u{2d25}', '\0', '\0']),
+  ('\u{10c7}', ['\u{2d27}', '\0', '\0']),
+  ('\u{10cd}', ['\u{2d2d}', '\0', '\0']),
+  ('\u{13f8}', ['\u{13f0}', '\0', '\0']),
+  ('\u{13f9}', ['\u{13f1}', '\0', '\0']),
+  ('\u{13fa}', ['\u{13f2}', '\0', '\0']),
+  ('\u{13fb}', ['\u{13f3}', '\0', '\0']),
+  ('\u{13fc}', ['\u{13f4}', '\0', '\0']),
+  ('\u{13fd}', ['\u{13f5}', '\0', '\0']),
+  ('\u{1c80}', ['\u{432}', '\0', '\0']),
+  ('\u{1c81}', ['\u{434}', '\0', '\0']),
+  ('\u{1c82}', ['\u{43e}', '\0', '\0']),
+  ('\u{1c83}', ['\u{441}', '\0', '\0']),
+  ('\u{1c84}', ['\u{442}', '\0', '\0']),
+  ('\u{1c85}', ['\u{442}', '\0', '\0']),
+  ('\u{1c86}', ['\u{44a}', '\0', '\0']),
+  ('\u{1c87}', ['\u{463}', '\0', '\0']),
+  ('\u{1c88}', ['\u{a64b}', '\0', '\0']),
+  ('\u{1e00}', ['\u{1e01}', '\0', '\0']),
+  ('\u{1e02}', ['\u{1e03}', '\0', '\0']),
+  ('\u{1e04}', ['\u{1e05}', '\0', '\0']),
+  ('\u{1e06}', ['\u{1e07}', '\0', '\0']),
+  ('\u{1e08}', ['\u{1e09}', '\0', '\0']),
+  ('\u{1e0a}', ['\u{1e0b}', '\0', '\0']),
+  ('\u{1e0c}', ['\u{1e0d}', '\0', '\0']),
+  ('\u{1e0e}', ['\u{1e0f}', '\0', '\0']),
+  ('\u{1e10}', ['\u{1e11}', '\0', '\0']),
+  ('\u{1e12}', ['\u{1e13}', '\0', '\0']),
+  ('\u{1e14}', ['\u{1e15}', '\0', '\0']),
+  ('\u{1e16}', ['\u{1e17}', '\0', '\0']),
+  ('\u{1e18}', ['\u{1e19}', '\0', '\0']),
+  ('\u{1e1a}', ['\u{1e1b}', '\0', '\0']),
+  ('\u{1e1c}', ['\u{1e1d}', '\0', '\0']),
+  ('\u{1e1e}', ['\u{1e1f}', '\0', '\0']),
+  ('\u{1e20}', ['\u{1e21}', '\0', '\0']),
+  ('\u{1e22}', ['\u{1e23}', '\0', '\0']),
+  ('\u{1e24}', ['\u{1e25}', '\0', '\0']),
+  ('\u{1e26}', ['\u{1e27}', '\0', '\0']),
+  ('\u{1e28}', ['\u{1e29}', '\0', '\0']),
+  ('\u{1e2a}', ['\u{1e2b}', '\0', '\0']),
+  ('\u{1e2c}', ['\u{1e2d}', '\0', '\0']),
+  ('\u{1e2e}', ['\u{1e2f}', '\0', '\0']),
+  ('\u{1e30}', ['\u{1e31}', '\0', '\0']),
+  ('\u{1e32}', ['\u{1e33}', '\0', '\0']),
+  ('\u{1e34}', ['\u{1e35}', '\0', '\0']),
+  ('\u{1e36}', ['\u{1e37}', '\0', '\0']),
+  ('\u{1e38}', ['\u{1e39}', '\0', '\0']),
+  ('\u{1e3a}', ['\u{1e3b}', '\0', '\0']),
+  ('\u{1e3c}', ['\u{1e3d}', '\0', '\0']),
+  ('\u{1e3e}', ['\u{1e3f}', '\0', '\0']),
+  ('\u{1e40}', ['\u{1e41}', '\0', '\0']),
+  ('\u{1e42}', ['\u{1e43}', '\0', '\0']),
+  ('\u{1e44}', ['\u{1e45}', '\0', '\0']),
+  ('\u{1e46}', ['\u{1e47}', '\0', '\0']),
+  ('\u{1e48}', ['\u{1e49}', '\0', '\0']),
+  ('\u{1e4a}', ['\u{1e4b}', '\0', '\0']),
+  ('\u{1e4c}', ['\u{1e4d}', '\0', '\0']),
+  ('\u{1e4e}', ['\u{1e4f}', '\0', '\0']),
+  ('\u{1e50}', ['\u{1e51}', '\0', '\0']),
+  ('\u{1e52}', ['\u{1e53}', '\0', '\0']),
+  ('\u{1e54}', ['\u{1e55}', '\0', '\0']),
+  ('\u{1e56}', ['\u{1e57}', '\0', '\0']),
+  ('\u{1e58}', ['\u{1e59}', '\0', '\0']),
+  ('\u{1e5a}', ['\u{1e5b}', '\0', '\0']),
+  ('\u{1e5c}', ['\u{1e5d}', '\0', '\0']),
+  ('\u{1e5e}', ['\u{1e5f}', '\0', '\0']),
+  ('\u{1e60}', ['\u{1e61}', '\0', '\0']),
+  ('\u{1e62}', ['\u{1e63}', '\0', '\0']),
+  ('\u{1e64}', ['\u{1e65}', '\0', '\0']),
+  ('\u{1e66}', ['\u{1e67}', '\0', '\0']),
+  ('\u{1e68}', ['\u{1e69}', '\0', '\0']),
+  ('\u{1e6a}', ['\u{1e6b}', '\0', '\0']),
+  ('\u{1e6c}', ['\u{1e6d}', '\0', '\0']),
+  ('\u{1e6e}', ['\u{1e6f}', '\0', '\0']),
+  ('\u{1e70}', ['\u{1e71}', '\0', '\0']),
+  ('\u{1e72}', ['\u{1e73}', '\0', '\0']),
+  ('\u{1e74}', ['\u{1e75}', '\0', '\0']),
+  ('\u{1e76}', ['\u{1e77}', '\0', '\0']),
+  ('\u{1e78}', ['\u{1e79}', '\0', '\0']),
+  ('\u{1e7a}', ['\u{1e7b}', '\0', '\0']),
+  ('\u{1e7c}', ['\u{1e7d}', '\0', '\0']),
+  ('\u{1e7e}', ['\u{1e7f}', '\0', '\0']),
+  ('\u{1e80}', ['\u{1e81}', '\0', '\0']),
+  ('\u{1e82}', ['\u{1e83}', '\0', '\0']),
+  ('\u{1e84}', ['\u{1e85}', '\0', '\0']),
+  ('\u{1e86}', ['\u{1e87}', '\0', '\0']),
+  ('\u{1e88}', ['\u{1e89}', '\0', '\0']),
+  ('\u{1e8a}', ['\u{1e8b}', '\0', '\0']),
+  ('\u{1e8c}', ['\u{1e8d}', '\0', '\0']),
+  ('\u{1e8e}', ['\u{1e8f}', '\0', '\0']),
+  ('\u{1e90}', ['\u{1e91}', '\0', '\0']),
+  ('\u{1e92}', ['\u{1e93}', '\0', '\0']),
+  ('\u{1e94}', ['\u{1e95}', '\0', '\0']),
+  ('\u{1e96}', ['h', '\u{331}', '\0']),
+  ('\u{1e97}', ['t', '\u{308}', '\0']),
+  ('\u{1e98}', ['w', '\u{30a}', '\0']),
+  ('\u{1e99}', ['y', '\u{30a}', '\0']),
+  ('\u{1e9a}', ['a', '\u{2be}', '\0']),
+  ('\u{1e9b}', ['\u{1e61}', '\0', '\0']),
+  ('\u{1e9e}', ['s', 's', '\0']),
+  ('\u{1ea0}', ['\u{1ea1}', '\0', '\0']),
+  ('\u{1ea2}', ['\u{1ea3}', '\0', '\0']),
+  ('\u{1ea4}', ['\u{1ea5}', '\0', '\0']),
+  ('\u{1ea6}', ['\u{1ea7}', '\0', '\0']),
+  ('\u{1ea8}', ['\u{1ea9}', '\0', '\0']),
+  ('\u{1eaa}', ['\u{1eab}', '\0', '\0']),
+  ('\u{1eac}', ['\u{1ead}', '\0', '\0']),
+  ('\u{1eae}', ['\u{1eaf}', '\0', '\0']),
+  ('\u{1eb0}', ['\u{1eb1}', '\0', '\0']),
+  ('\u{1eb2}', ['\u{1eb3}', '\0', '\0']),
+  ('\u{1eb4}', ['\u{1eb5}', '\0', '\0']),
+  ('\u{1eb6}', ['\u{1eb7}', '\0', '\0']),
+  ('\u{1eb8}', ['\u{1eb9}', '\0', '\0']),
+  ('\u{1eba}', ['\u{1ebb}', '\0', '\0']),
+  ('\u{1ebc}', ['\u{1ebd}', '\0', '\0']),
+  ('\u{1ebe}', ['\u{1ebf}', '\0', '\0']),
+  ('\u{1ec0}', ['\u{1ec1}', '\0', '\0']),
+  ('\u{1ec2}', ['\u{1ec3}', '\0', '\0']),
+  ('\u{1ec4}', ['\u{1ec5}', '\0', '\0']),
+  ('\u{1ec6}', ['\u{1ec7}', '\0', '\0']),
+  ('\u{1ec8}', ['\u{1ec9}', '\0', '\0']),
+  ('\u{1eca}', ['\u{1ecb}', '\0', '\0']),
+  ('\u{1ecc}', ['\u{1ecd}', '\0', '\0']),
+  ('\u{1ece}', ['\u{1ecf}', '\0', '\0']),
+  ('\u{1ed0}', ['\u{1ed1}', '\0', '\0']),
+  ('\u{1ed2}', ['\u{1ed3}', '\0', '\0']),
+  ('\u{1ed4}', ['\u{1ed5}', '\0', '\0']),
+  ('\u{1ed6}', ['\u{1ed7}', '\0', '\0']),
+  ('\u{1ed8}', ['\u{1ed9}', '\0', '\0']),
+  ('\u{1eda}', ['\u{1edb}', '\0', '\0']),
+  ('\u{1edc}', ['\u{1edd}', '\0', '\0']),
+  ('\u{1ede}', ['\u{1edf}', '\0', '\0']),
+  ('\u{1ee0}', ['\u{1ee1}', '\0', '\0']),
+  ('\u{1ee2}', ['\u{1ee3}', '\0', '\0']),
+  ('\u{1ee4}', ['\u{1ee5}', '\0', '\0']),
+  ('\u{1ee6}', ['\u{1ee7}', '\0', '\0']),
+  ('\u{1ee8}', ['\u{1ee9}', '\0', '\0']),
+  ('\u{1eea}', ['\u{1eeb}', '\0', '\0']),
+  ('\u{1eec}', ['\u{1eed}', '\0', '\0']),
+  ('\u{1eee}', ['\u{1eef}', '\0', '\0']),
+  ('\u{1ef0}', ['\u{1ef1}', '\0', '\0']),
+  ('\u{1ef2}', ['\u{1ef3}', '\0', '\0']),
+  ('\u{1ef4}', ['\u{1ef5}', '\0', '\0']),
+  ('\u{1ef6}', ['\u{1ef7}', '\0', '\0']),
+  ('\u{1ef8}', ['\u{1ef9}', '\0', '\0']),
+  ('\u{1efa}', ['\u{1efb}', '\0', '\0']),
+  ('\u{1efc}', ['\u{1efd}', '\0', '\0']),
+  ('\u{1efe}', ['\u{1eff}', '\0', '\0']),
+  ('\u{1f08}', ['\u{1f00}', '\0', '\0']),
+  ('\u{1f09}', ['\u{1f01}', '\0', '\0']),
+  ('\u{1f0a}', ['\u{1f02}', '\0', '\0']),
+  ('\u{1f0b}', ['\u{1f03}', '\0', '\0']),
+  ('\u{1f0c}', ['\u{1f04}', '\0', '\0']),
+  ('\u{1f0d}', ['\u{1f05}', '\0', '\0']),
+  ('\u{1f0e}', ['\u{1f06}', '\0', '\0']),
+  ('\u{1f0f}', ['\u{1f07}', '\0', '\0']),
+  ('\u{1f18}', ['\u{1f10}', '\0', '\0']),
+  ('\u{1f19}', ['\u{1f11}', '\0', '\0']),
+  ('\u{1f1a}', ['\u{1f12}', '\0', '\0']),
+  ('\u{1f1b}', ['\u{1f13}', '\0', '\0']),
+  ('\u{1f1c}', ['\u{1f14}', '\0', '\0']),
+  ('\u{1f1d}', ['\u{1f15}', '\0', '\0']),
+  ('\u{1f28}', ['\u{1f20}', '\0', '\0']),
+  ('\u{1f29}', ['\u{1f21}', '\0', '\0']),
+  ('\u{1f2a}', ['\u{1f22}', '\0', '\0']),
+  ('\u{1f2b}', ['\u{1f23}', '\0', '\0']),
+  ('\u{1f2c}', ['\u{1f24}', '\0', '\0']),
+  ('\u{1f2d}', ['\u{1f25}', '\0', '\0']),
+  ('\u{1f2e}', ['\u{1f26}', '\0', '\0']),
+  ('\u{1f2f}', ['\u{1f27}', '\0', '\0']),
+  ('\u{1f38}', ['\u{1f30}', '\0', '\0']),
+  ('\u{1f39}', ['\u{1f31}', '\0', '\0']),
+  ('\u{1f3a}', ['\u{1f32}', '\0', '\0']),
+  ('\u{1f3b}', ['\u{1f33}', '\0', '\0']),
+  ('\u{1f3c}', ['\u{1f34}', '\0', '\0']),
+  ('\u{1f3d}', ['\u{1f35}', '\0', '\0']),
+  ('\u{1f3e}', ['\u{1f36}', '\0', '\0']),
+  ('\u{1f3f}', ['\u{1f37}', '\0', '\0']),
+  ('\u{1f48}', ['\u{1f40}', '\0', '\0']),
+  ('\u{1f49}', ['\u{1f41}', '\0', '\0']),
+  ('\u{1f4a}', ['\u{1f42}', '\0', '\0']),
+  ('\u{1f4b}', ['\u{1f43}', '\0', '\0']),
+  ('\u{1f4c}', ['\u{1f44}', '\0', '\0']),
+  ('\u{1f4d}', ['\u{1f45}', '\0', '\0']),
+  ('\u{1f50}', ['\u{3c5}', '\u{313}', '\0']),
+  ('\u{1f52}', ['\u{3c5}', '\u{313}', '\u{300}']),
+  ('\u{1f54}', ['\u{3c5}', '\u{313}', '\u{301}']),
+  ('\u{1f56}', ['\u{3c5}', '\u{313}', '\u{342}']),
+  ('\u{1f59}', ['\u{1f51}', '\0', '\0']),
+  ('\u{1f5b}', ['\u{1f53}', '\0', '\0']),
+  ('\u{1f5d}', ['\u{1f55}', '\0', '\0']),
+  ('\u{1f5f}', ['\u{1f57}', '\0', '\0']),
+  ('\u{1f68}', ['\u{1f60}', '\0', '\0']),
+  ('\u{1f69}', ['\u{1f61}', '\0', '\0']),
+  ('\u{1f6a}', ['\u{1f62}', '\0', '\0']),
+  ('\u{1f6b}', ['\u{1f63}', '\0', '\0']),
+  ('\u{1f6c}', ['\u{1f64}', '\0', '\0']),
+  ('\u{1f6d}', ['\u{1f65}', '\0', '\0']),
+  ('\u{1f6e}', ['\u{1f66}', '\0', '\0']),
+  ('\u{1f6f}', ['\u{1f67}', '\0', '\0']),
+  ('\u{1f80}', ['\u{1f00}', '\u{3b9}', '\0']),
+  ('\u{1f81}', ['\u{1f01}', '\u{3b9}', '\0']),
+  ('\u{1f82}', ['\u{1f02}', '\u{3b9}', '\0']),
+  ('\u{1f83}', ['\u{1f03}', '\u{3b9}', '\0']),
+  ('\u{1f84}', ['\u{1f04}', '\u{3b9}', '\0']),
+  ('\u{1f85}', ['\u{1f05}', '\u{3b9}', '\0']),
+  ('\u{1f86}', ['\u{1f06}', '\u{3b9}', '\0']),
+  ('\u{1f87}', ['\u{1f07}', '\u{3b9}', '\0']),
+  ('\u{1f88}', ['\u{1f00}', '\u{3b9}', '\0']),
+  ('\u{1f89}', ['\u{1f01}', '\u{3b9}', '\0']),
+  ('\u{1f8a}', ['\u{1f02}', '\u{3b9}', '\0']),
+  ('\u{1f8b}', ['\u{1f03}', '\u{3b9}', '\0']),
+  ('\u{1f8c}', ['\u{1f04}', '\u{3b9}', '\0']),
+  ('\u{1f8d}', ['\u{1f05}', '\u{3b9}', '\0']),
+  ('\u{1f8e}', ['\u{1f06}', '\u{3b9}', '\0']),
+  ('\u{1f8f}', ['\u{1f07}', '\u{3b9}', '\0']),
+  ('\u{1f90}', ['\u{1f20}', '\u{3b9}', '\0']),
+  ('\u{1f91}', ['\u{1f21}', '\u{3b9}', '\0']),
+  ('\u{1f92}', ['\u{1f22}', '\u{3b9}', '\0']),
+  ('\u{1f93}', ['\u{1f23}', '\u{3b9}', '\0']),
+  ('\u{1f94}', ['\u{1f24}', '\u{3b9}', '\0']),
+  ('\u{1f95}', ['\u{1f25}', '\u{3b9}', '\0']),
+  ('\u{1f96}', ['\u{1f26}', '\u{3b9}', '\0']),
+  ('\u{1f97}', ['\u{1f27}', '\u{3b9}', '\0']),
+  ('\u{1f98}', ['\u{1f20}', '\u{3b9}', '\0']),
+  ('\u{1f99}', ['\u{1f21}', '\u{3b9}', '\0']),
+  ('\u{1f9a}', ['\u{1f22}', '\u{3b9}', '\0']),
+  ('\u{1f9b}', ['\u{1f23}', '\u{3b9}', '\0']),
+  ('\u{1f9c}', ['\u{1f24}', '\u{3b9}', '\0']),
+  ('\u{1f9d}', ['\u{1f25}', '\u{3b9}', '\0']),
+  ('\u{1f9e}', ['\u{1f26}', '\u{3b9}', '\0']),
+  ('\u{1f9f}', ['\u{1f27}', '\u{3b9}', '\0']),
+  ('\u{1fa0}', ['\u{1f60}', '\u{3b9}', '\0']),
+  ('\u{1fa1}', ['\u{1f61}', '\u{3b9}', '\0']),
+  ('\u{1fa2}', ['\u{1f62}', '\u{3b9}', '\0']),
+  ('\u{1fa3}', ['\u{1f63}', '\u{3b9}', '\0']),
+  ('\u{1fa4}', ['\u{1f64}', '\u{3b9}', '\0']),
+  ('\u{1fa5}', ['\u{1f65}', '\u{3b9}', '\0']),
+  ('\u{1fa6}', ['\u{1f66}', '\u{3b9}', '\0']),
+  ('\u{1fa7}', ['\u{1f67}', '\u{3b9}', '\0']),
+  ('\u{1fa8}', ['\u{1f60}', '\u{3b9}', '\0']),
+  ('\u{1fa9}', ['\u{1f61}', '\u{3b9}', '\0']),
+  ('\u{1faa}', ['\u{1f62}', '\u{3b9}', '\0']),
+  ('\u{1fab}', ['\u{1f63}', '\u{3b9}', '\0']),
+  ('\u{1fac}', ['\u{1f64}', '\u{3b9}', '\0']),
+  ('\u{1fad}', ['\u{1f65}', '\u{3b9}', '\0']),
+  ('\u{1fae}', ['\u{1f66}', '\u{3b9}', '\0']),
+  ('\u{1faf}', ['\u{1f67}', '\u{3b9}', '\0']),
+  ('\u{1fb2}', ['\u{1f70}', '\u{3b9}', '\0']),
+  ('\u{1fb3}', ['\u{3b1}', '\u{3b9}', '\0']),
+  ('\u{1fb4}', ['\u{3ac}', '\u{3b9}', '\0']),
+  ('\u{1fb6}', ['\u{3b1}', '\u{342}', '\0']),
+  ('\u{1fb7}', ['\u{3b1}', '\u{342}', '\u{3b9}']),
+  ('\u{1fb8}', ['\u{1fb0}', '\0', '\0']),
+  ('\u{1fb9}', ['\u{1fb1}', '\0', '\0']),
+  ('\u{1fba}', ['\u{1f70}', '\0', '\0']),
+  ('\u{1fbb}', ['\u{1f71}', '\0', '\0']),
+  ('\u{1fbc}', ['\u{3b1}', '\u{3b9}', '\0']),
+  ('\u{1fbe}', ['\u{3b9}', '\0', '\0']),
+  ('\u{1fc2}', ['\u{1f74}', '\u{3b9}', '\0']),
+  ('\u{1fc3}', ['\u{3b7}', '\u{3b9}', '\0']),
+  ('\u{1fc4}', ['\u{3ae}', '\u{3b9}', '\0']),
+  ('\u{1fc6}', ['\u{3b7}', '\u{342}', '\0']),
+  ('\u{1fc7}', ['\u{3b7}', '\u{342}', '\u{3b9}']),
+  ('\u{1fc8}', ['\u{1f72}', '\0', '\0']),
+  ('\u{1fc9}', ['\u{1f73}', '\0', '\0']),
+  ('\u{1fca}', ['\u{1f74}', '\0', '\0']),
+  ('\u{1fcb}', ['\u{1f75}', '\0', '\0']),
+  ('\u{1fcc}', ['\u{3b7}', '\u{3b9}', '\0']),
+  ('\u{1fd2}', ['\u{3b9}', '\u{308}', '\u{300}']),
+  ('\u{1fd3}', ['\u{3b9}', '\u{308}', '\u{301}']),
+  ('\u{1fd6}', ['\u{3b9}', '\u{342}', '\0']),
+  ('\u{1fd7}', ['\u{3b9}', '\u{308}', '\u{342}']),
+  ('\u{1fd8}', ['\u{1fd0}', '\0', '\0']),
+  ('\u{1fd9}', ['\u{1fd1}', '\0', '\0']),
+  ('\u{1fda}', ['\u{1f76}', '\0', '\0']),
+  ('\u{1fdb}', ['\u{1f77}', '\0', '\0']),
+  ('\u{1fe2}', ['\u{3c5}', '\u{308}', '\u{300}']),
+  ('\u{1fe3}', ['\u{3c5}', '\u{308}', '\u{301}']),
+  ('\u{1fe4}', ['\u{3c1}', '\u{313}', '\0']),
+  ('\u{1fe6}', ['\u{3c5}', '\u{342}', '\0']),
+  ('\u{1fe7}', ['\u{3c5}', '\u{308}', '\u{342}']),
+  ('\u{1fe8}', ['\u{1fe0}', '\0', '\0']),
+  ('\u{1fe9}', ['\u{1fe1}', '\0', '\0']),
+  ('\u{1fea}', ['\u{1f7a}', '\0', '\0']),
+  ('\u{1feb}', ['\u{1f7b}', '\0', '\0']),
+  ('\u{1fec}', ['\u{1fe5}', '\0', '\0']),
+  ('\u{1ff2}', ['\u{1f7c}', '\u{3b9}', '\0']),
+  ('\u{1ff3}', ['\u{3c9}', '\u{3b9}', '\0']),
+  ('\u{1ff4}', ['\u{3ce}', '\u{3b9}', '\0']),
+  ('\u{1ff6}', ['\u{3c9}', '\u{342}', '\0']),
+  ('\u{1ff7}', ['\u{3c9}', '\u{342}', '\u{3b9}']),
+  ('\u{1ff8}', ['\u{1f78}', '\0', '\0']),
+  ('\u{1ff9}', ['\u{1f79}', '\0', '\0']),
+  ('\u{1ffa}', ['\u{1f7c}', '\0', '\0']),
+  ('\u{1ffb}', ['\u{1f7d}', '\0', '\0']),
+  ('\u{1ffc}', ['\u{3c9}', '\u{3b9}', '\0']),
+  ('\u{2126}', ['\u{3c9}', '\0', '\0']),
+  ('\u{212a}', ['k', '\0', '\0']),
+  ('\u{212b}', ['\u{e5}', '\0', '\0']),
+  ('\u{2132}', ['\u{214e}', '\0', '\0']),
+  ('\u{2160}', ['\u{2170}', '\0', '\0']),
+  ('\u{2161}', ['\u{2171}', '\0', '\0']),
+  ('\u{2162}', ['\u{2172}', '\0', '\0']),
+  ('\u{2163}', ['\u{2173}', '\0', '\0']),
+  ('\u{2164}', ['\u{2174}', '\0', '\0']),
+  ('\u{2165}', ['\u{2175}', '\0', '\0']),
+  ('\u{2166}', ['\u{2176}', '\0', '\0']),
+  ('\u{2167}', ['\u{2177}', '\0', '\0']),
+  ('\u{2168}', ['\u{2178}', '\0', '\0']),
+  ('\u{2169}', ['\u{2179}', '\0', '\0']),
+  ('\u{216a}', ['\u{217a}', '\0', '\0']),
+  ('\u{216b}', ['\u{217b}', '\0', '\0']),
+  ('\u{216c}', ['\u{217c}', '\0', '\0']),
+  ('\u{216d}', ['\u{217d}', '\0', '\0']),
+  ('\u{216e}', ['\u{217e}', '\0', '\0']),
+  ('\u{216f}', ['\u{217f}', '\0', '\0']),
+  ('\u{2183}', ['\u{2184}', '\0', '\0']),
+  ('\u{24b6}', ['\u{24d0}', '\0', '\0']),
+  ('\u{24b7}', ['\u{24d1}', '\0', '\0']),
+  ('\u{24b8}', ['\u{24d2}', '\0', '\0']),
+  ('\u{24b9}', ['\u{24d3}', '\0', '\0']),
+  ('\u{24ba}', ['\u{24d4}', '\0', '\0']),
+  ('\u{24bb}', ['\u{24d5}', '\0', '\0']),
+  ('\u{24bc}', ['\u{24d6}', '\0', '\0']),
+  ('\u{24bd}', ['\u{24d7}', '\0', '\0']),
+  ('\u{24be}', ['\u{24d8}', '\0', '\0']),
+  ('\u{24bf}', ['\u{24d9}', '\0', '\0']),
+  ('\u{24c0}', ['\u{24da}', '\0', '\0']),
+  ('\u{24c1}', ['\u{24db}', '\0', '\0']),
+  ('\u{24c2}', ['\u{24dc}', '\0', '\0']),
+  ('\u{24c3}', ['\u{24dd}', '\0', '\0']),
+  ('\u{24c4}', ['\u{24de}', '\0', '\0']),
+  ('\u{24c5}', ['\u{24df}', '\0', '\0']),
+  ('\u{24c6}', ['\u{24e0}', '\0', '\0']),
+  ('\u{24c7}', ['\u{24e1}', '\0', '\0']),
+  ('\u{24c8}', ['\u{24e2}', '\0', '\0']),
+  ('\u{24c9}', ['\u{24e3}', '\0', '\0']),
+  ('\u{24ca}', ['\u{24e4}', '\0', '\0']),
+  ('\u{24cb}', ['\u{24e5}', '\0', '\0']),
+  ('\u{24cc}', ['\u{24e6}', '\0', '\0']),
+  ('\u{24cd}', ['\u{24e7}', '\0', '\0']),
+  ('\u{24ce}', ['\u{24e8}', '\0', '\0']),
+  ('\u{24cf}', ['\u{24e9}', '\0', '\0']),
+  ('\u{2c00}', ['\u{2c30}', '\0', '\0']),
+  ('\u{2c01}', ['\u{2c31}', '\0', '\0']),
+  ('\u{2c02}', ['\u{2c32}', '\0', '\0']),
+  ('\u{2c03}', ['\u{2c33}', '\0', '\0']),
+  ('\u{2c04}', ['\u{2c34}', '\0', '\0']),
+  ('\u{2c05}', ['\u{2c35}', '\0', '\0']),
+  ('\u{2c06}', ['\u{2c36}', '\0', '\0']),
+  ('\u{2c07}', ['\u{2c37}', '\0', '\0']),
+  ('\u{2c08}', ['\u{2c38}', '\0', '\0']),
+  ('\u{2c09}', ['\u{2c39}', '\0', '\0']),
+  ('\u{2c0a}', ['\u{2c3a}', '\0', '\0']),
+  ('\u{2c0b}', ['\u{2c3b}', '\0', '\0']),
+  ('\u{2c0c}', ['\u{2c3c}', '\0', '\0']),
+  ('\u{2c0d}', ['\u{2c3d}', '\0', '\0']),
+  ('\u{2c0e}', ['\u{2c3e}', '\0', '\0']),
+  ('\u{2c0f}', ['\u{2c3f}', '\0', '\0']),
+  ('\u{2c10}', ['\u{2c40}', '\0', '\0']),
+  ('\u{2c11}', ['\u{2c41}', '\0', '\0']),
+  ('\u{2c12}', ['\u{2c42}', '\0', '\0']),
+  ('\u{2c13}', ['\u{2c43}', '\0', '\0']),
+  ('\u{2c14}', ['\u{2c44}', '\0', '\0']),
+  ('\u{2c15}', ['\u{2c45}', '\0', '\0']),
+  ('\u{2c16}', ['\u{2c46}', '\0', '\0']),
+  ('\u{2c17}', ['\u{2c47}', '\0', '\0']),
+  ('\u{2c18}', ['\u{2c48}', '\0', '\0']),
+  ('\u{2c19}', ['\u{2c49}', '\0', '\0']),
+  ('\u{2c1a}', ['\u{2c4a}', '\0', '\0']),
+  ('\u{2c1b}', ['\u{2c4b}', '\0', '\0']),
+  ('\u{2c1c}', ['\u{2c4c}', '\0', '\0']),
+  ('\u{2c1d}', ['\u{2c4d}', '\0', '\0']),
+  ('\u{2c1e}', ['\u{2c4e}', '\0', '\0']),
+  ('\u{2c1f}', ['\u{2c4f}', '\0', '\0']),
+  ('\u{2c20}', ['\u{2c50}', '\0', '\0']),
+  ('\u{2c21}', ['\u{2c51}', '\0', '\0']),
+  ('\u{2c22}', ['\u{2c52}', '\0', '\0']),
+  ('\u{2c23}', ['\u{2c53}', '\0', '\0']),
+  ('\u{2c24}', ['\u{2c54}', '\0', '\0']),
+  ('\u{2c25}', ['\u{2c55}', '\0', '\0']),
+  ('\u{2c26}', ['\u{2c56}', '\0', '\0']),
+  ('\u{2c27}', ['\u{2c57}', '\0', '\0']),
+  ('\u{2c28}', ['\u{2c58}', '\0', '\0']),
+  ('\u{2c29}', ['\u{2c59}', '\0', '\0']),
+  ('\u{2c2a}', ['\u{2c5a}', '\0', '\0']),
+  ('\u{2c2b}', ['\u{2c5b}', '\0', '\0']),
+  ('\u{2c2c}', ['\u{2c5c}', '\0', '\0']),
+  ('\u{2c2d}', ['\u{2c5d}', '\0', '\0']),
+  ('\u{2c2e}', ['\u{2c5e}', '\0', '\0']),
+  ('\u{2c60}', ['\u{2c61}', '\0', '\0']),
+  ('\u{2c62}', ['\u{26b}', '\0', '\0']),
+  ('\u{2c63}', ['\u{1d7d}', '\0', '\0']),
+  ('\u{2c64}', ['\u{27d}', '\0', '\0']),
+  ('\u{2c67}', ['\u{2c68}', '\0', '\0']),
+  ('\u{2c69}', ['\u{2c6a}', '\0', '\0']),
+  ('\u{2c6b}', ['\u{2c6c}', '\0', '\0']),
+  ('\u{2c6d}', ['\u{251}', '\0', '\0']),
+  ('\u{2c6e}', ['\u{271}', '\0', '\0']),
+  ('\u{2c6f}', ['\u{250}', '\0', '\0']),
+  ('\u{2c70}', ['\u{252}', '\0', '\0']),
+  ('\u{2c72}', ['\u{2c73}', '\0', '\0']),
+  ('\u{2c75}', ['\u{2c76}', '\0', '\0']),
+  ('\u{2c7e}', ['\u{23f}', '\0', '\0']),
+  ('\u{2c7f}', ['\u{240}', '\0', '\0']),
+  ('\u{2c80}', ['\u{2c81}', '\0', '\0']),
+  ('\u{2c82}', ['\u{2c83}', '\0', '\0']),
+  ('\u{2c84}', ['\u{2c85}', '\0', '\0']),
+  ('\u{2c86}', ['\u{2c87}', '\0', '\0']),
+  ('\u{2c88}', ['\u{2c89}', '\0', '\0']),
+  ('\u{2c8a}', ['\u{2c8b}', '\0', '\0']),
+  ('\u{2c8c}', ['\u{2c8d}', '\0', '\0']),
+  ('\u{2c8e}', ['\u{2c8f}', '\0', '\0']),
+  ('\u{2c90}', ['\u{2c91}', '\0', '\0']),
+  ('\u{2c92}', ['\u{2c93}', '\0', '\0']),
+  ('\u{2c94}', ['\u{2c95}', '\0', '\0']),
+  ('\u{2c96}', ['\u{2c97}', '\0', '\0']),
+  ('\u{2c98}', ['\u{2c99}', '\0', '\0']),
+  ('\u{2c9a}', ['\u{2c9b}', '\0', '\0']),
+  ('\u{2c9c}', ['\u{2c9d}', '\0', '\0']),
+  ('\u{2c9e}', ['\u{2c9f}', '\0', '\0']),
+  ('\u{2ca0}', ['\u{2ca1}', '\0', '\0']),
+  ('\u{2ca2}', ['\u{2ca3}', '\0', '\0']),
+  ('\u{2ca4}', ['\u{2ca5}', '\0', '\0']),
+  ('\u{2ca6}', ['\u{2ca7}', '\0', '\0']),
+  ('\u{2ca8}', ['\u{2ca9}', '\0', '\0']),
+  ('\u{2caa}', ['\u{2cab}', '\0', '\0']),
+  ('\u{2cac}', ['\u{2cad}', '\0', '\0']),
+  ('\u{2cae}', ['\u{2caf}', '\0', '\0']),
+  ('\u{2cb0}', ['\u{2cb1}', '\0', '\0']),
+  ('\u{2cb2}', ['\u{2cb3}', '\0', '\0']),
+  ('\u{2cb4}', ['\u{2cb5}', '\0', '\0']),
+  ('\u{2cb6}', ['\u{2cb7}', '\0', '\0']),
+  ('\u{2cb8}', ['\u{2cb9}', '\0', '\0']),
+  ('\u{2cba}', ['\u{2cbb}', '\0', '\0']),
+  ('\u{2cbc}', ['\u{2cbd}', '\0', '\0']),
+  ('\u{2cbe}', ['\u{2cbf}', '\0', '\0']),
+  ('\u{2cc0}', ['\u{2cc1}', '\0', '\0']),
+  ('\u{2cc2}', ['\u{2cc3}', '\0', '\0']),
+  ('\u{2cc4}', ['\u{2cc5}', '\0', '\0']),
+  ('\u{2cc6}', ['\u{2cc7}', '\0', '\0']),
+  ('\u{2cc8}', ['\u{2cc9}', '\0', '\0']),
+  ('\u{2cca}', ['\u{2ccb}', '\0', '\0']),
+  ('\u{2ccc}', ['\u{2ccd}', '\0', '\0']),
+  ('\u{2cce}', ['\u{2ccf}', '\0', '\0']),
+  ('\u{2cd0}', ['\u{2cd1}', '\0', '\0']),
+  ('\u{2cd2}', ['\u{2cd3}', '\0', '\0']),
+  ('\u{2cd4}', ['\u{2cd5}', '\0', '\0']),
+  ('\u{2cd6}', ['\u{2cd7}', '\0', '\0']),
+  ('\u{2cd8}', ['\u{2cd9}', '\0', '\0']),
+  ('\u{2cda}', ['\u{2cdb}', '\0', '\0']),
+  ('\u{2cdc}', ['\u{2cdd}', '\0', '\0']),
+  ('\u{2cde}', ['\u{2cdf}', '\0', '\0']),
+  ('\u{2ce0}', ['\u{2ce1}', '\0', '\0']),
+  ('\u{2ce2}', ['\u{2ce3}', '\0', '\0']),
+  ('\u{2ceb}', ['\u{2cec}', '\0', '\0']),
+  ('\u{2ced}', ['\u{2cee}', '\0', '\0']),
+  ('\u{2cf2}', ['\u{2cf3}', '\0', '\0']),
+  ('\u{a640}', ['\u{a641}', '\0', '\0']),
+  ('\u{a642}', ['\u{a643}', '\0', '\0']),
+  ('\u{a644}', ['\u{a645}', '\0', '\0']),
+  ('\u{a646}', ['\u{a647}', '\0', '\0']),
+  ('\u{a648}', ['\u{a649}', '\0', '\0']),
+  ('\u{a64a}', ['\u{a64b}', '\0', '\0']),
+  ('\u{a64c}', ['\u{a64d}', '\0', '\0']),
+  ('\u{a64e}', ['\u{a64f}', '\0', '\0']),
+  ('\u{a650}', ['\u{a651}', '\0', '\0']),
+  ('\u{a652}', ['\u{a653}', '\0', '\0']),
+  ('\u{a654}', ['\u{a655}', '\0', '\0']),
+  ('\u{a656}', ['\u{a657}', '\0', '\0']),
+  ('\u{a658}', ['\u{a659}', '\0', '\0']),
+  ('\u{a65a}', ['\u{a65b}', '\0', '\0']),
+  ('\u{a65c}', ['\u{a65d}', '\0', '\0']),
+  ('\u{a65e}', ['\u{a65f}', '\0', '\0']),
+  ('\u{a660}', ['\u{a661}', '\0', '\0']),
+  ('\u{a662}', ['\u{a663}', '\0', '\0']),
+  ('\u{a664}', ['\u{a665}', '\0', '\0']),
+  ('\u{a666}', ['\u{a667}', '\0', '\0']),
+  ('\u{a668}', ['\u{a669}', '\0', '\0']),
+  ('\u{a66a}', ['\u{a66b}', '\0', '\0']),
+  ('\u{a66c}', ['\u{a66d}', '\0', '\0']),
+  ('\u{a680}', ['\u{a681}', '\0', '\0']),
+  ('\u{a682}', ['\u{a683}', '\0', '\0']),
+  ('\u{a684}', ['\u{a685}', '\0', '\0']),
+  ('\u{a686}', ['\u{a687}', '\0', '\0']),
+  ('\u{a688}', ['\u{a689}', '\0', '\0']),
+  ('\u{a68a}', ['\u{a68b}', '\0', '\0']),
+  ('\u{a68c}', ['\u{a68d}', '\0', '\0']),
+  ('\u{a68e}', ['\u{a68f}', '\0', '\0']),
+  ('\u{a690}', ['\u{a691}', '\0', '\0']),
+  ('\u{a692}', ['\u{a693}', '\0', '\0']),
+  ('\u{a694}', ['\u{a695}', '\0', '\0']),
+  ('\u{a696}', ['\u{a697}', '\0', '\0']),
+  ('\u{a698}', ['\u{a699}', '\0', '\0']),
+  ('\u{a69a}', ['\u{a69b}', '\0', '\0']),
+  ('\u{a722}', ['\u{a723}', '\0', '\0']),
+  ('\u{a724}', ['\u{a725}', '\0', '\0']),
+  ('\u{a726}', ['\u{a727}', '\0', '\0']),
+  ('\u{a728}', ['\u{a729}', '\0', '\0']),
+  ('\u{a72a}', ['\u{a72b}', '\0', '\0']),
+  ('\u{a72c}', ['\u{a72d}', '\0', '\0']),
+  ('\u{a72e}', ['\u{a72f}', '\0', '\0']),
+  ('\u{a732}', ['\u{a733}', '\0', '\0']),
+  ('\u{a734}', ['\u{a735}', '\0', '\0']),
+  ('\u{a736}', ['\u{a737}', '\0', '\0']),
+  ('\u{a738}', ['\u{a739}', '\0', '\0']),
+  ('\u{a73a}', ['\u{a73b}', '\0', '\0']),
+  ('\u{a73c}', ['\u{a73d}', '\0', '\0']),
+  ('\u{a73e}', ['\u{a73f}', '\0', '\0']),
+  ('\u{a740}', ['\u{a741}', '\0', '\0']),
+  ('\u{a742}', ['\u{a743}', '\0', '\0']),
+  ('\u{a744}', ['\u{a745}', '\0', '\0']),
+  ('\u{a746}', ['\u{a747}', '\0', '\0']),
+  ('\u{a748}', ['\u{a749}', '\0', '\0']),
+  ('\u{a74a}', ['\u{a74b}', '\0', '\0']),
+  ('\u{a74c}', ['\u{a74d}', '\0', '\0']),
+  ('\u{a74e}', ['\u{a74f}', '\0', '\0']),
+  ('\u{a750}', ['\u{a751}', '\0', '\0']),
+  ('\u{a752}', ['\u{a753}', '\0', '\0']),
+  ('\u{a754}', ['\u{a755}', '\0', '\0']),
+  ('\u{a756}', ['\u{a757}', '\0', '\0']),
+  ('\u{a758}', ['\u{a759}', '\0', '\0']),
+  ('\u{a75a}', ['\u{a75b}', '\0', '\0']),
+  ('\u{a75c}', ['\u{a75d}', '\0', '\0']),
+  ('\u{a75e}', ['\u{a75f}', '\0', '\0']),
+  ('\u{a760}', ['\u{a761}', '\0', '\0']),
+  ('\u{a762}', ['\u{a763}', '\0', '\0']),
+  ('\u{a764}', ['\u{a765}', '\0', '\0']),
+  ('\u{a766}', ['\u{a767}', '\0', '\0']),
+  ('\u{a768}', ['\u{a769}', '\0', '\0']),
+  ('\u{a76a}', ['\u{a76b}', '\0', '\0']),
+  ('\u{a76c}', ['\u{a76d}', '\0', '\0']),
+  ('\u{a76e}', ['\u{a76f}', '\0', '\0']),
+  ('\u{a779}', ['\u{a77a}', '\0', '\0']),
+  ('\u{a77b}', ['\u{a77c}', '\0', '\0']),
+  ('\u{a77d}', ['\u{1d79}', '\0', '\0']),
+  ('\u{a77e}', ['\u{a77f}', '\0', '\0']),
+  ('\u{a780}', ['\u{a781}', '\0', '\0']),
+  ('\u{a782}', ['\u{a783}', '\0', '\0']),
+  ('\u{a784}', ['\u{a785}', '\0', '\0']),
+  ('\u{a786}', ['\u{a787}', '\0', '\0']),
+  ('\u{a78b}', ['\u{a78c}', '\0', '\0']),
+  ('\u{a78d}', ['\u{265}', '\0', '\0']),
+  ('\u{a790}', ['\u{a791}', '\0', '\0']),
+  ('\u{a792}', ['\u{a793}', '\0', '\0']),
+  ('\u{a796}', ['\u{a797}', '\0', '\0']),
+  ('\u{a798}', ['\u{a799}', '\0', '\0']),
+  ('\u{a79a}', ['\u{a79b}', '\0', '\0']),
+  ('\u{a79c}', ['\u{a79d}', '\0', '\0']),
+  ('\u{a79e}', ['\u{a79f}', '\0', '\0']),
+  ('\u{a7a0}', ['\u{a7a1}', '\0', '\0']),
+  ('\u{a7a2}', ['\u{a7a3}', '\0', '\0']),
+  ('\u{a7a4}', ['\u{a7a5}', '\0', '\0']),
+  ('\u{a7a6}', ['\u{a7a7}', '\0', '\0']),
+  ('\u{a7a8}', ['\u{a7a9}', '\0', '\0']),
+  ('\u{a7aa}', ['\u{266}', '\0', '\0']),
+  ('\u{a7ab}', ['\u{25c}', '\0', '\0']),
+  ('\u{a7ac}', ['\u{261}', '\0', '\0']),
+  ('\u{a7ad}', ['\u{26c}', '\0', '\0']),
+  ('\u{a7ae}', ['\u{26a}', '\0', '\0']),
+  ('\u{a7b0}', ['\u{29e}', '\0', '\0']),
+  ('\u{a7b1}', ['\u{287}', '\0', '\0']),
+  ('\u{a7b2}', ['\u{29d}', '\0', '\0']),
+  ('\u{a7b3}', ['\u{ab53}', '\0', '\0']),
+  ('\u{a7b4}', ['\u{a7b5}', '\0', '\0']),
+  ('\u{a7b6}', ['\u{a7b7}', '\0', '\0']),
+  ('\u{ab70}', ['\u{13a0}', '\0', '\0']),
+  ('\u{ab71}', ['\u{13a1}', '\0', '\0']),
+  ('\u{ab72}', ['\u{13a2}', '\0', '\0']),
+  ('\u{ab73}', ['\u{13a3}', '\0', '\0']),
+  ('\u{ab74}', ['\u{13a4}', '\0', '\0']),
+  ('\u{ab75}', ['\u{13a5}', '\0', '\0']),
+  ('\u{ab76}', ['\u{13a6}', '\0', '\0']),
+  ('\u{ab77}', ['\u{13a7}', '\0', '\0']),
+  ('\u{ab78}', ['\u{13a8}', '\0', '\0']),
+  ('\u{ab79}', ['\u{13a9}', '\0', '\0']),
+  ('\u{ab7a}', ['\u{13aa}', '\0', '\0']),
+  ('\u{ab7b}', ['\u{13ab}', '\0', '\0']),
+  ('\u{ab7c}', ['\u{13ac}', '\0', '\0']),
+  ('\u{ab7d}', ['\u{13ad}', '\0', '\0']),
+  ('\u{ab7e}', ['\u{13ae}', '\0', '\0']),
+  ('\u{ab7f}', ['\u{13af}', '\0', '\0']),
+  ('\u{ab80}', ['\u{13b0}', '\0', '\0']),
+  ('\u{ab81}', ['\u{13b1}', '\0', '\0']),
+  ('\u{ab82}', ['\u{13b2}', '\0', '\0']),
+  ('\u{ab83}', ['\u{13b3}', '\0', '\0']),
+  ('\u{ab84}', ['\u{13b4}', '\0', '\0']),
+  ('\u{ab85}', ['\u{13b5}', '\0', '\0']),
+  ('\u{ab86}', ['\u{13b6}', '\0', '\0']),
+  ('\u{ab87}', ['\u{13b7}', '\0', '\0']),
+  ('\u{ab88}', ['\u{13b8}', '\0', '\0']),
+  ('\u{ab89}', ['\u{13b9}', '\0', '\0']),
+  ('\u{ab8a}', ['\u{13ba}', '\0', '\0']),
+  ('\u{ab8b}', ['\u{13bb}', '\0', '\0']),
+  ('\u{ab8c}', ['\u{13bc}', '\0', '\0']),
+  ('\u{ab8d}', ['\u{13bd}', '\0', '\0']),
+  ('\u{ab8e}', ['\u{13be}', '\0', '\0']),
+  ('\u{ab8f}', ['\u{13bf}', '\0', '\0']),
+  ('\u{ab90}', ['\u{13c0}', '\0', '\0']),
+  ('\u{ab91}', ['\u{13c1}', '\0', '\0']),
+  ('\u{ab92}', ['\u{13c2}', '\0', '\0']),
+  ('\u{ab93}', ['\u{13c3}', '\0', '\0']),
+  ('\u{ab94}', ['\u{13c4}', '\0', '\0']),
+  ('\u{ab95}', ['\u{13c5}', '\0', '\0']),
+  ('\u{ab96}', ['\u{13c6}', '\0', '\0']),
+  ('\u{ab97}', ['\u{13c7}', '\0', '\0']),
+  ('\u{ab98}', ['\u{13c8}', '\0', '\0']),
+  ('\u{ab99}', ['\u{13c9}', '\0', '\0']),
+  ('\u{ab9a}', ['\u{13ca}', '\0', '\0']),
+  ('\u{ab9b}', ['\u{13cb}', '\0', '\0']),
+  ('\u{ab9c}', ['\u{13cc}', '\0', '\0']),
+  ('\u{ab9d}', ['\u{13cd}', '\0', '\0']),
+  ('\u{ab9e}', ['\u{13ce}', '\0', '\0']),
+  ('\u{ab9f}', ['\u{13cf}', '\0', '\0']),
+  ('\u{aba0}', ['\u{13d0}', '\0', '\0']),
+  ('\u{aba1}', ['\u{13d1}', '\0', '\0']),
+  ('\u{aba2}', ['\u{13d2}', '\0', '\0']),
+  ('\u{aba3}', ['\u{13d3}', '\0', '\0']),
+  ('\u{aba4}', ['\u{13d4}', '\0', '\0']),
+  ('\u{aba5}', ['\u{13d5}', '\0', '\0']),
+  ('\u{aba6}', ['\u{13d6}', '\0', '\0']),
+  ('\u{aba7}', ['\u{13d7}', '\0', '\0']),
+  ('\u{aba8}', ['\u{13d8}', '\0', '\0']),
+  ('\u{aba9}', ['\u{13d9}', '\0', '\0']),
+  ('\u{abaa}', ['\u{13da}', '\0', '\0']),
+  ('\u{abab}', ['\u{13db}', '\0', '\0']),
+  ('\u{abac}', ['\u{13dc}', '\0', '\0']),
+  ('\u{abad}', ['\u{13dd}', '\0', '\0']),
+  ('\u{abae}', ['\u{13de}', '\0', '\0']),
+  ('\u{abaf}', ['\u{13df}', '\0', '\0']),
+  ('\u{abb0}', ['\u{13e0}', '\0', '\0']),
+  ('\u{abb1}', ['\u{13e1}', '\0', '\0']),
+  ('\u{abb2}', ['\u{13e2}', '\0', '\0']),
+  ('\u{abb3}', ['\u{13e3}', '\0', '\0']),
+  ('\u{abb4}', ['\u{13e4}', '\0', '\0']),
+  ('\u{abb5}', ['\u{13e5}', '\0', '\0']),
+  ('\u{abb6}', ['\u{13e6}', '\0', '\0']),
+  ('\u{abb7}', ['\u{13e7}', '\0', '\0']),
+  ('\u{abb8}', ['\u{13e8}', '\0', '\0']),
+  ('\u{abb9}', ['\u{13e9}', '\0', '\0']),
+  ('\u{abba}', ['\u{13ea}', '\0', '\0']),
+  ('\u{abbb}', ['\u{13eb}', '\0', '\0']),
+  ('\u{abbc}', ['\u{13ec}', '\0', '\0']),
+  ('\u{abbd}', ['\u{13ed}', '\0', '\0']),
+  ('\u{abbe}', ['\u{13ee}', '\0', '\0']),
+  ('\u{abbf}', ['\u{13ef}', '\0', '\0']),
+  ('\u{fb00}', ['f', 'f', '\0']),
+  ('\u{fb01}', ['f', 'i', '\0']),
+  ('\u{fb02}', ['f', 'l', '\0']),
+  ('\u{fb03}', ['f', 'f', 'i']),
+  ('\u{fb04}', ['f', 'f', 'l']),
+  ('\u{fb05}', ['s', 't', '\0']),
+  ('\u{fb06}', ['s', 't', '\0']),
+  ('\u{fb13}', ['\u{574}', '\u{576}', '\0']),
+  ('\u{fb14}', ['\u{574}', '\u{565}', '\0']),
+  ('\u{fb15}', ['\u{574}', '\u{56b}', '\0']),
+  ('\u{fb16}', ['\u{57e}', '\u{576}', '\0']),
+  ('\u{fb17}', ['\u{574}', '\u{56d}', '\0']),
+  ('\u{ff21}', ['\u{ff41}', '\0', '\0']),
+  ('\u{ff22}', ['\u{ff42}', '\0', '\0']),
+  ('\u{ff23}', ['\u{ff43}', '\0', '\0']),
+  ('\u{ff24}', ['\u{ff44}', '\0', '\0']),
+  ('\u{ff25}', ['\u{ff45}', '\0', '\0']),
+  ('\u{ff26}', ['\u{ff46}', '\0', '\0']),
+  ('\u{ff27}', ['\u{ff47}', '\0', '\0']),
+  ('\u{ff28}', ['\u{ff48}', '\0', '\0']),
+  ('\u{ff29}', ['\u{ff49}', '\0', '\0']),
+  ('\u{ff2a}', ['\u{ff4a}', '\0', '\0']),
+  ('\u{ff2b}', ['\u{ff4b}', '\0', '\0']),
+  ('\u{ff2c}', ['\u{ff4c}', '\0', '\0']),
+  ('\u{ff2d}', ['\u{ff4d}', '\0', '\0']),
+  ('\u{ff2e}', ['\u{ff4e}', '\0', '\0']),
+  ('\u{ff2f}', ['\u{ff4f}', '\0', '\0']),
+  ('\u{ff30}', ['\u{ff50}', '\0', '\0']),
+  ('\u{ff31}', ['\u{ff51}', '\0', '\0']),
+  ('\u{ff32}', ['\u{ff52}', '\0', '\0']),
+  ('\u{ff33}', ['\u{ff53}', '\0', '\0']),
+  ('\u{ff34}', ['\u{ff54}', '\0', '\0']),
+  ('\u{ff35}', ['\u{ff55}', '\0', '\0']),
+  ('\u{ff36}', ['\u{ff56}', '\0', '\0']),
+  ('\u{ff37}', ['\u{ff57}', '\0', '\0']),
+  ('\u{ff38}', ['\u{ff58}', '\0', '\0']),
+  ('\u{ff39}', ['\u{ff59}', '\0', '\0']),
+  ('\u{ff3a}', ['\u{ff5a}', '\0', '\0']),
+  ('\u{10400}', ['\u{10428}', '\0', '\0']),
+  ('\u{10401}', ['\u{10429}', '\0', '\0']),
+  ('\u{10402}', ['\u{1042a}', '\0', '\0']),
+  ('\u{10403}', ['\u{1042b}', '\0', '\0']),
+  ('\u{10404}', ['\u{1042c}', '\0', '\0']),
+  ('\u{10405}', ['\u{1042d}', '\0', '\0']),
+  ('\u{10406}', ['\u{1042e}', '\0', '\0']),
+  ('\u{10407}', ['\u{1042f}', '\0', '\0']),
+  ('\u{10408}', ['\u{10430}', '\0', '\0']),
+  ('\u{10409}', ['\u{10431}', '\0', '\0']),
+  ('\u{1040a}', ['\u{10432}', '\0', '\0']),
+  ('\u{1040b}', ['\u{10433}', '\0', '\0']),
+  ('\u{1040c}', ['\u{10434}', '\0', '\0']),
+  ('\u{1040d}', ['\u{10435}', '\0', '\0']),
+  ('\u{1040e}', ['\u{10436}', '\0', '\0']),
+  ('\u{1040f}', ['\u{10437}', '\0', '\0']),
+  ('\u{10410}', ['\u{10438}', '\0', '\0']),
+  ('\u{10411}', ['\u{10439}', '\0', '\0']),
+  ('\u{10412}', ['\u{1043a}', '\0', '\0']),
+  ('\u{10413}', ['\u{1043b}', '\0', '\0']),
+  ('\u{10414}', ['\u{1043c}', '\0', '\0']),
+  ('\u{10415}', ['\u{1043d}', '\0', '\0']),
+  ('\u{10416}', ['\u{1043e}', '\0', '\0']),
+  ('\u{10417}', ['\u{1043f}', '\0', '\0']),
+  ('\u{10418}', ['\u{10440}', '\0', '\0']),
+  ('\u{10419}', ['\u{10441}', '\0', '\0']),
+  ('\u{1041a}', ['\u{10442}', '\0', '\0']),
+  ('\u{1041b}', ['\u{10443}', '\0', '\0']),
+  ('\u{1041c}', ['\u{10444}', '\0', '\0']),
+  ('\u{1041d}', ['\u{10445}', '\0', '\0']),
+  ('\u{1041e}', ['\u{10446}', '\0', '\0']),
+  ('\u{1041f}', ['\u{10447}', '\0', '\0']),
+  ('\u{10420}', ['\u{10448}', '\0', '\0']),
+  ('\u{10421}', ['\u{10449}', '\0', '\0']),
+  ('\u{10422}', ['\u{1044a}', '\0', '\0']),
+  ('\u{10423}', ['\u{1044b}', '\0', '\0']),
+  ('\u{10424}', ['\u{1044c}', '\0', '\0']),
+  ('\u{10425}', ['\u{1044d}', '\0', '\0']),
+  ('\u{10426}', ['\u{1044e}', '\0', '\0']),
+  ('\u{10427}', ['\u{1044f}', '\0', '\0']),
+  ('\u{104b0}', ['\u{104d8}', '\0', '\0']),
+  ('\u{104b1}', ['\u{104d9}', '\0', '\0']),
+  ('\u{104b2}', ['\u{104da}', '\0', '\0']),
+  ('\u{104b3}', ['\u{104db}', '\0', '\0']),
+  ('\u{104b4}', ['\u{104dc}', '\0', '\0']),
+  ('\u{104b5}', ['\u{104dd}', '\0', '\0']),
+  ('\u{104b6}', ['\u{104de}', '\0', '\0']),
+  ('\u{104b7}', ['\u{104df}', '\0', '\0']),
+  ('\u{104b8}', ['\u{104e0}', '\0', '\0']),
+  ('\u{104b9}', ['\u{104e1}', '\0', '\0']),
+  ('\u{104ba}', ['\u{104e2}', '\0', '\0']),
+  ('\u{104bb}', ['\u{104e3}', '\0', '\0']),
+  ('\u{104bc}', ['\u{104e4}', '\0', '\0']),
+  ('\u{104bd}', ['\u{104e5}', '\0', '\0']),
+  ('\u{104be}', ['\u{104e6}', '\0', '\0']),
+  ('\u{104bf}', ['\u{104e7}', '\0', '\0']),
+  ('\u{104c0}', ['\u{104e8}', '\0', '\0']),
+  ('\u{104c1}', ['\u{104e9}', '\0', '\0']),
+  ('\u{104c2}', ['\u{104ea}', '\0', '\0']),
+  ('\u{104c3}', ['\u{104eb}', '\0', '\0']),
+  ('\u{104c4}', ['\u{104ec}', '\0', '\0']),
+  ('\u{104c5}', ['\u{104ed}', '\0', '\0']),
+  ('\u{104c6}', ['\u{104ee}', '\0', '\0']),
+  ('\u{104c7}', ['\u{104ef}', '\0', '\0']),
+  ('\u{104c8}', ['\u{104f0}', '\0', '\0']),
+  ('\u{104c9}', ['\u{104f1}', '\0', '\0']),
+  ('\u{104ca}', ['\u{104f2}', '\0', '\0']),
+  ('\u{104cb}', ['\u{104f3}', '\0', '\0']),
+  ('\u{104cc}', ['\u{104f4}', '\0', '\0']),
+  ('\u{104cd}', ['\u{104f5}', '\0', '\0']),
+  ('\u{104ce}', ['\u{104f6}', '\0', '\0']),
+  ('\u{104cf}', ['\u{104f7}', '\0', '\0']),
+  ('\u{104d0}', ['\u{104f8}', '\0', '\0']),
+  ('\u{104d1}', ['\u{104f9}', '\0', '\0']),
+  ('\u{104d2}', ['\u{104fa}', '\0', '\0']),
+  ('\u{104d3}', ['\u{104fb}', '\0', '\0']),
+  ('\u{10c80}', ['\u{10cc0}', '\0', '\0']),
+  ('\u{10c81}', ['\u{10cc1}', '\0', '\0']),
+  ('\u{10c82}', ['\u{10cc2}', '\0', '\0']),
+  ('\u{10c83}', ['\u{10cc3}', '\0', '\0']),
+  ('\u{10c84}', ['\u{10cc4}', '\0', '\0']),
+  ('\u{10c85}', ['\u{10cc5}', '\0', '\0']),
+  ('\u{10c86}', ['\u{10cc6}', '\0', '\0']),
+  ('\u{10c87}', ['\u{10cc7}', '\0', '\0']),
+  ('\u{10c88}', ['\u{10cc8}', '\0', '\0']),
+  ('\u{10c89}', ['\u{10cc9}', '\0', '\0']),
+  ('\u{10c8a}', ['\u{10cca}', '\0', '\0']),
+  ('\u{10c8b}', ['\u{10ccb}', '\0', '\0']),
+  ('\u{10c8c}', ['\u{10ccc}', '\0', '\0']),
+  ('\u{10c8d}', ['\u{10ccd}', '\0', '\0']),
+  ('\u{10c8e}', ['\u{10cce}', '\0', '\0']),
+  ('\u{10c8f}', ['\u{10ccf}', '\0', '\0']),
+  ('\u{10c90}', ['\u{10cd0}', '\0', '\0']),
+  ('\u{10c91}', ['\u{10cd1}', '\0', '\0']),
+  ('\u{10c92}', ['\u{10cd2}', '\0', '\0']),
+  ('\u{10c93}', ['\u{10cd3}', '\0', '\0']),
+  ('\u{10c94}', ['\u{10cd4}', '\0', '\0']),
+  ('\u{10c95}', ['\u{10cd5}', '\0', '\0']),
+  ('\u{10c96}', ['\u{10cd6}', '\0', '\0']),
+  ('\u{10c97}', ['\u{10cd7}', '\0', '\0']),
+  ('\u{10c98}', ['\u{10cd8}', '\0', '\0']),
+  ('\u{10c99}', ['\u{10cd9}', '\0', '\0']),
+  ('\u{10c9a}', ['\u{10cda}', '\0', '\0']),
+  ('\u{10c9b}', ['\u{10cdb}', '\0', '\0']),
+  ('\u{10c9c}', ['\u{10cdc}', '\0', '\0']),
+  ('\u{10c9d}', ['\u{10cdd}', '\0', '\0']),
+  ('\u{10c9e}', ['\u{10cde}', '\0', '\0']),
+  ('\u{10c9f}', ['\u{10cdf}', '\0', '\0']),
+  ('\u{10ca0}', ['\u{10ce0}', '\0', '\0']),
+  ('\u{10ca1}', ['\u{10ce1}', '\0', '\0']),
+  ('\u{10ca2}', ['\u{10ce2}', '\0', '\0']),
+  ('\u{10ca3}', ['\u{10ce3}', '\0', '\0']),
+  ('\u{10ca4}', ['\u{10ce4}', '\0', '\0']),
+  ('\u{10ca5}', ['\u{10ce5}', '\0', '\0']),
+  ('\u{10ca6}', ['\u{10ce6}', '\0', '\0']),
+  ('\u{10ca7}', ['\u{10ce7}', '\0', '\0']),
+  ('\u{10ca8}', ['\u{10ce8}', '\0', '\0']),
+  ('\u{10ca9}', ['\u{10ce9}', '\0', '\0']),
+  ('\u{10caa}', ['\u{10cea}', '\0', '\0']),
+  ('\u{10cab}', ['\u{10ceb}', '\0', '\0']),
+  ('\u{10cac}', ['\u{10cec}', '\0', '\0']),
+  ('\u{10cad}', ['\u{10ced}', '\0', '\0']),
+  ('\u{10cae}', ['\u{10cee}', '\0', '\0']),
+  ('\u{10caf}', ['\u{10cef}', '\0', '\0']),
+  ('\u{10cb0}', ['\u{10cf0}', '\0', '\0']),
+  ('\u{10cb1}', ['\u{10cf1}', '\0', '\0']),
+  ('\u{10cb2}', ['\u{10cf2}', '\0', '\0']),
+  ('\u{118a0}', ['\u{118c0}', '\0', '\0']),
+  ('\u{118a1}', ['\u{118c1}', '\0', '\0']),
+  ('\u{118a2}', ['\u{118c2}', '\0', '\0']),
+  ('\u{118a3}', ['\u{118c3}', '\0', '\0']),
+  ('\u{118a4}', ['\u{118c4}', '\0', '\0']),
+  ('\u{118a5}', ['\u{118c5}', '\0', '\0']),
+  ('\u{118a6}', ['\u{118c6}', '\0', '\0']),
+  ('\u{118a7}', ['\u{118c7}', '\0', '\0']),
+  ('\u{118a8}', ['\u{118c8}', '\0', '\0']),
+  ('\u{118a9}', ['\u{118c9}', '\0', '\0']),
+  ('\u{118aa}', ['\u{118ca}', '\0', '\0']),
+  ('\u{118ab}', ['\u{118cb}', '\0', '\0']),
+  ('\u{118ac}', ['\u{118cc}', '\0', '\0']),
+  ('\u{118ad}', ['\u{118cd}', '\0', '\0']),
+  ('\u{118ae}', ['\u{118ce}', '\0', '\0']),
+  ('\u{118af}', ['\u{118cf}', '\0', '\0']),
+  ('\u{118b0}', ['\u{118d0}', '\0', '\0']),
+  ('\u{118b1}', ['\u{118d1}', '\0', '\0']),
+  ('\u{118b2}', ['\u{118d2}', '\0', '\0']),
+  ('\u{118b3}', ['\u{118d3}', '\0', '\0']),
+  ('\u{118b4}', ['\u{118d4}', '\0', '\0']),
+  ('\u{118b5}', ['\u{118d5}', '\0', '\0']),
+  ('\u{118b6}', ['\u{118d6}', '\0', '\0']),
+  ('\u{118b7}', ['\u{118d7}', '\0', '\0']),
+  ('\u{118b8}', ['\u{118d8}', '\0', '\0']),
+  ('\u{118b9}', ['\u{118d9}', '\0', '\0']),
+  ('\u{118ba}', ['\u{118da}', '\0', '\0']),
+  ('\u{118bb}', ['\u{118db}', '\0', '\0']),
+  ('\u{118bc}', ['\u{118dc}', '\0', '\0']),
+  ('\u{118bd}', ['\u{118dd}', '\0', '\0']),
+  ('\u{118be}', ['\u{118de}', '\0', '\0']),
+  ('\u{118bf}', ['\u{118df}', '\0', '\0']),
+  ('\u{1e900}', ['\u{1e922}', '\0', '\0']),
+  ('\u{1e901}', ['\u{1e923}', '\0', '\0']),
+  ('\u{1e902}', ['\u{1e924}', '\0', '\0']),
+  ('\u{1e903}', ['\u{1e925}', '\0', '\0']),
+  ('\u{1e904}', ['\u{1e926}', '\0', '\0']),
+  ('\u{1e905}', ['\u{1e927}', '\0', '\0']),
+  ('\u{1e906}', ['\u{1e928}', '\0', '\0']),
+  ('\u{1e907}', ['\u{1e929}', '\0', '\0']),
+  ('\u{1e908}', ['\u{1e92a}', '\0', '\0']),
+  ('\u{1e909}', ['\u{1e92b}', '\0', '\0']),
+  ('\u{1e90a}', ['\u{1e92c}', '\0', '\0']),
+  ('\u{1e90b}', ['\u{1e92d}', '\0', '\0']),
+  ('\u{1e90c}', ['\u{1e92e}', '\0', '\0']),
+  ('\u{1e90d}', ['\u{1e92f}', '\0', '\0']),
+  ('\u{1e90e}', ['\u{1e930}', '\0', '\0']),
+  ('\u{1e90f}', ['\u{1e931}', '\0', '\0']),
+  ('\u{1e910}', ['\u{1e932}', '\0', '\0']),
+  ('\u{1e911}', ['\u{1e933}', '\0', '\0']),
+  ('\u{1e912}', ['\u{1e934}', '\0', '\0']),
+  ('\u{1e913}', ['\u{1e935}', '\0', '\0']),
+  ('\u{1e914}', ['\u{1e936}', '\0', '\0']),
+  ('\u{1e915}', ['\u{1e937}', '\0', '\0']),
+  ('\u{1e916}', ['\u{1e938}', '\0', '\0']),
+  ('\u{1e917}', ['\u{1e939}', '\0', '\0']),
+  ('\u{1e918}', ['\u{1e93a}', '\0', '\0']),
+  ('\u{1e919}', ['\u{1e93b}', '\0', '\0']),
+  ('\u{1e91a}', ['\u{1e93c}', '\0', '\0']),
+  ('\u{1e91b}', ['\u{1e93d}', '\0', '\0']),
+  ('\u{1e91c}', ['\u{1e93e}', '\0', '\0']),
+  ('\u{1e91d}', ['\u{1e93f}', '\0', '\0']),
+  ('\u{1e91e}', ['\u{1e940}', '\0', '\0']),
+  ('\u{1e91f}', ['\u{1e941}', '\0', '\0']),
+  ('\u{1e920}', ['\u{1e942}', '\0', '\0']),
+  ('\u{1e921}', ['\u{1e943}', '\0', '\0']),
+];
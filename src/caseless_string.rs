@@ -0,0 +1,450 @@
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::iter::FromIterator;
+use core::ops::Deref;
+
+use super::{caseless_cmp, caseless_hash, default_caseless_match_str};
+
+/// A string that compares and hashes by its default case folding,
+/// while keeping the original text.
+///
+/// It dereferences to `CaselessStr` and from there to the original `str`.
+/// Methods reached that way are *not* caseless:
+/// `CaselessString::from("Foo").contains("foo")` is false.
+#[derive(Clone, Default)]
+pub struct CaselessString(String);
+
+/// The borrowed counterpart of `CaselessString`, wrapping a `str` slice.
+#[repr(transparent)]
+pub struct CaselessStr(str);
+
+impl CaselessStr {
+    pub fn new(s: &str) -> &CaselessStr {
+        // Safety: CaselessStr is a repr(transparent) wrapper around str.
+        unsafe { &*(s as *const str as *const CaselessStr) }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for CaselessString {
+    fn from(s: String) -> CaselessString {
+        CaselessString(s)
+    }
+}
+
+impl<'a> From<&'a str> for CaselessString {
+    fn from(s: &'a str) -> CaselessString {
+        CaselessString(s.to_owned())
+    }
+}
+
+/// Returns the original string.
+impl From<CaselessString> for String {
+    fn from(s: CaselessString) -> String {
+        s.0
+    }
+}
+
+impl FromIterator<char> for CaselessString {
+    fn from_iter<I: IntoIterator<Item = char>>(iter: I) -> CaselessString {
+        CaselessString(String::from_iter(iter))
+    }
+}
+
+impl Deref for CaselessString {
+    type Target = CaselessStr;
+
+    fn deref(&self) -> &CaselessStr {
+        CaselessStr::new(&self.0)
+    }
+}
+
+/// Lets maps keyed by `CaselessString` be queried with a `&CaselessStr`,
+/// without allocating. `Borrow<str>` would be wrong, since a `str` hashes
+/// and compares case-sensitively.
+impl Borrow<CaselessStr> for CaselessString {
+    fn borrow(&self) -> &CaselessStr {
+        self
+    }
+}
+
+/// Gives the original string, for `str` methods. These are not caseless.
+impl Deref for CaselessStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl ToOwned for CaselessStr {
+    type Owned = CaselessString;
+
+    fn to_owned(&self) -> CaselessString {
+        CaselessString(self.0.to_owned())
+    }
+}
+
+impl PartialEq for CaselessStr {
+    fn eq(&self, other: &CaselessStr) -> bool {
+        default_caseless_match_str(&self.0, &other.0)
+    }
+}
+
+impl Eq for CaselessStr {}
+
+impl Hash for CaselessStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        caseless_hash(&self.0, state)
+    }
+}
+
+// CaselessString delegates to CaselessStr so that the two agree,
+// as required by Borrow.
+impl PartialEq for CaselessString {
+    fn eq(&self, other: &CaselessString) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for CaselessString {}
+
+// Caseless comparisons with plain strings, in both directions.
+macro_rules! impl_eq_str {
+    ($($ty: ty),*) => {
+        $(
+            impl<'a> PartialEq<$ty> for CaselessString {
+                fn eq(&self, other: &$ty) -> bool {
+                    default_caseless_match_str(&self.0, other)
+                }
+            }
+
+            impl<'a> PartialEq<CaselessString> for $ty {
+                fn eq(&self, other: &CaselessString) -> bool {
+                    default_caseless_match_str(self, &other.0)
+                }
+            }
+        )*
+    };
+}
+
+impl_eq_str!(str, &'a str, String);
+
+impl Hash for CaselessString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+// Formatting shows the original string, not its case folding.
+macro_rules! impl_fmt {
+    ($($ty: ident),*) => {
+        $(
+            impl fmt::Debug for $ty {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    f.debug_tuple(stringify!($ty)).field(&&self.0).finish()
+                }
+            }
+
+            impl fmt::Display for $ty {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    f.write_str(&self.0)
+                }
+            }
+        )*
+    };
+}
+
+impl_fmt!(CaselessString, CaselessStr, CaselessOrd);
+
+/// A string that compares and orders by its default case folding,
+/// while keeping the original text, for use as a `BTreeMap` key.
+#[derive(Clone, Default)]
+pub struct CaselessOrd(String);
+
+impl CaselessOrd {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for CaselessOrd {
+    fn from(s: String) -> CaselessOrd {
+        CaselessOrd(s)
+    }
+}
+
+impl<'a> From<&'a str> for CaselessOrd {
+    fn from(s: &'a str) -> CaselessOrd {
+        CaselessOrd(s.to_owned())
+    }
+}
+
+impl PartialEq for CaselessOrd {
+    fn eq(&self, other: &CaselessOrd) -> bool {
+        default_caseless_match_str(&self.0, &other.0)
+    }
+}
+
+impl Eq for CaselessOrd {}
+
+impl PartialOrd for CaselessOrd {
+    fn partial_cmp(&self, other: &CaselessOrd) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CaselessOrd {
+    fn cmp(&self, other: &CaselessOrd) -> Ordering {
+        caseless_cmp(&self.0, &other.0)
+    }
+}
+
+/// Like `CaselessString`, backed by a `SmolStr` so that clones are cheap.
+///
+/// It compares and hashes like `CaselessStr`, so maps keyed by
+/// `CaselessSmolStr` can be queried with a `&CaselessStr`.
+#[cfg(feature = "smol_str")]
+#[derive(Clone, Default)]
+pub struct CaselessSmolStr(smol_str::SmolStr);
+
+#[cfg(feature = "smol_str")]
+impl From<smol_str::SmolStr> for CaselessSmolStr {
+    fn from(s: smol_str::SmolStr) -> CaselessSmolStr {
+        CaselessSmolStr(s)
+    }
+}
+
+#[cfg(feature = "smol_str")]
+impl<'a> From<&'a str> for CaselessSmolStr {
+    fn from(s: &'a str) -> CaselessSmolStr {
+        CaselessSmolStr(smol_str::SmolStr::new(s))
+    }
+}
+
+/// Returns the original string.
+#[cfg(feature = "smol_str")]
+impl From<CaselessSmolStr> for smol_str::SmolStr {
+    fn from(s: CaselessSmolStr) -> smol_str::SmolStr {
+        s.0
+    }
+}
+
+#[cfg(feature = "smol_str")]
+impl Deref for CaselessSmolStr {
+    type Target = CaselessStr;
+
+    fn deref(&self) -> &CaselessStr {
+        CaselessStr::new(&self.0)
+    }
+}
+
+#[cfg(feature = "smol_str")]
+impl Borrow<CaselessStr> for CaselessSmolStr {
+    fn borrow(&self) -> &CaselessStr {
+        self
+    }
+}
+
+#[cfg(feature = "smol_str")]
+impl PartialEq for CaselessSmolStr {
+    fn eq(&self, other: &CaselessSmolStr) -> bool {
+        **self == **other
+    }
+}
+
+#[cfg(feature = "smol_str")]
+impl Eq for CaselessSmolStr {}
+
+#[cfg(feature = "smol_str")]
+impl Hash for CaselessSmolStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+#[cfg(feature = "smol_str")]
+impl_fmt!(CaselessSmolStr);
+
+/// Returns the original string.
+#[cfg(feature = "compact_str")]
+impl<'a> From<&'a CaselessStr> for compact_str::CompactString {
+    fn from(s: &'a CaselessStr) -> compact_str::CompactString {
+        compact_str::CompactString::from(&s.0)
+    }
+}
+
+/// Returns the original string.
+#[cfg(feature = "compact_str")]
+impl From<CaselessString> for compact_str::CompactString {
+    fn from(s: CaselessString) -> compact_str::CompactString {
+        compact_str::CompactString::from(s.0)
+    }
+}
+
+/// Serializes the original string, not its case folding.
+#[cfg(feature = "serde")]
+impl serde::Serialize for CaselessString {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for CaselessString {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(CaselessString)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeSet, HashMap};
+
+    // Comparing wrapped strings is the point, not an extra allocation.
+    #[allow(clippy::cmp_owned)]
+    #[test]
+    fn test_caseless_string() {
+        assert!(CaselessString::from("STRASSE") == CaselessString::from("straße"));
+        assert!(CaselessString::from("STRASSE") != CaselessString::from("strase"));
+        let mut map = HashMap::new();
+        map.insert(CaselessString::from("STRASSE"), 1);
+        assert_eq!(map.get(&CaselessString::from("strasse")), Some(&1));
+        assert_eq!(map.get(&CaselessString::from("Straße")), Some(&1));
+        assert_eq!(map.get(&CaselessString::from("Strasser")), None);
+    }
+
+    // Comparing wrapped strings is the point, not an extra allocation.
+    #[allow(clippy::cmp_owned)]
+    #[test]
+    fn test_eq_str() {
+        let s = CaselessString::from("ABC");
+        assert!(s == "abc");
+        assert!(s == *"aBc");
+        assert!(s == String::from("abc"));
+        assert!("abc" == s);
+        assert!(*"abc" == s);
+        assert!(String::from("abc") == s);
+        assert!(s != "abd");
+        assert!(CaselessString::from("Straße") == "STRASSE");
+    }
+
+    #[test]
+    fn test_fmt() {
+        let s = CaselessString::from("Straße");
+        assert_eq!(s.to_string(), "Straße");
+        assert_eq!(format!("{:?}", s), "CaselessString(\"Straße\")");
+        assert_eq!(
+            format!("{:?}", CaselessStr::new("ᏣᎳᎩ")),
+            "CaselessStr(\"ᏣᎳᎩ\")"
+        );
+        assert_eq!(CaselessOrd::from("ǅ").to_string(), "ǅ");
+        assert_eq!(CaselessString::from("ABC"), "abc");
+    }
+
+    #[test]
+    fn test_conversions() {
+        let k: CaselessString = "Foo".into();
+        assert_eq!(k, "FOO");
+        let s: String = k.into();
+        assert_eq!(s, "Foo");
+        let k: CaselessString = String::from("Straße").into();
+        assert_eq!(String::from(k), "Straße");
+        let k = "ABC".chars().rev().collect::<CaselessString>();
+        assert_eq!(k.as_str(), "CBA");
+    }
+
+    #[test]
+    fn test_deref() {
+        let s = CaselessString::from("Straße");
+        assert_eq!(s.len(), 7);
+        assert!(s.contains("aß"));
+        assert!(!s.contains("STR"));
+        assert_eq!(&s[..3], "Str");
+        assert!(s.starts_with('S'));
+    }
+
+    #[test]
+    fn test_borrow() {
+        let mut map = HashMap::new();
+        map.insert(CaselessString::from("KEY"), 1);
+        assert_eq!(map.get(CaselessStr::new("key")), Some(&1));
+        assert_eq!(map.get(CaselessStr::new("Key")), Some(&1));
+        assert_eq!(map.get(CaselessStr::new("keys")), None);
+        assert_eq!(map.remove(CaselessStr::new("kEy")), Some(1));
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_caseless_str() {
+        assert!(CaselessStr::new("Maße") == CaselessStr::new("MASSE"));
+        assert!(CaselessStr::new("Maße") != CaselessStr::new("Mace"));
+        let owned: CaselessString = CaselessStr::new("Maße").to_owned();
+        assert_eq!(owned.as_str(), "Maße");
+        let mut map = HashMap::new();
+        map.insert(owned, 1);
+        assert_eq!(map.get(CaselessStr::new("masse")), Some(&1));
+    }
+
+    #[test]
+    fn test_caseless_ord() {
+        let mut set = BTreeSet::new();
+        for s in &["Foo", "bar", "foo", "Baz", "FOO", "straße", "STRASSE"] {
+            set.insert(CaselessOrd::from(*s));
+        }
+        let keys = set.iter().map(CaselessOrd::as_str).collect::<Vec<_>>();
+        assert_eq!(keys, ["bar", "Baz", "Foo", "straße"]);
+        assert!(set.contains(&CaselessOrd::from("BAR")));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        use serde::de::value::{Error, StrDeserializer};
+        use serde::Deserialize;
+        use serde_test::{assert_ser_tokens, Token};
+
+        assert_ser_tokens(&CaselessString::from("Straße"), &[Token::Str("Straße")]);
+        let de = CaselessString::deserialize(StrDeserializer::<Error>::new("Straße")).unwrap();
+        assert_eq!(de.as_str(), "Straße");
+        assert!(de == "STRASSE");
+    }
+
+    #[cfg(feature = "compact_str")]
+    #[test]
+    fn test_compact_str() {
+        use compact_str::CompactString;
+        use default_case_fold_compact;
+
+        let s = CaselessString::from("Straße");
+        assert_eq!(CompactString::from(CaselessStr::new("Straße")), "Straße");
+        assert_eq!(CompactString::from(s), "Straße");
+        let folded = default_case_fold_compact("Straße");
+        assert_eq!(folded, "strasse");
+        assert!(!folded.is_heap_allocated());
+        assert!(default_caseless_match_str(&folded, "STRASSE"));
+    }
+    #[cfg(feature = "smol_str")]
+    #[test]
+    fn test_smol_str() {
+        use default_case_fold_smol;
+
+        let key = CaselessSmolStr::from("Straße");
+        let mut map = HashMap::new();
+        map.insert(key.clone(), 1);
+        assert_eq!(map.get(CaselessStr::new("STRASSE")), Some(&1));
+        assert!(key == CaselessSmolStr::from("strasse"));
+        assert_eq!(key.as_str(), "Straße");
+        assert_eq!(format!("{:?}", key), r#"CaselessSmolStr("Straße")"#);
+        assert_eq!(smol_str::SmolStr::from(key), "Straße");
+        assert_eq!(default_case_fold_smol("Straße"), "strasse");
+    }
+}
@@ -0,0 +1,337 @@
+//! Collections keyed by the default case folding of strings.
+
+use std::collections::hash_map::{self, HashMap};
+
+use super::default_case_fold_str;
+
+/// A hash map with string keys matched caselessly.
+///
+/// Keys are stored in folded form, so lookups fold the query once
+/// and then use plain string hashing.
+/// The original spelling of each key is kept for iteration.
+///
+/// Inserting a key that caseless-matches an existing key
+/// replaces both the value and the original spelling: the last writer wins.
+#[derive(Clone, Debug)]
+pub struct CaselessHashMap<V> {
+    map: HashMap<String, (String, V)>,
+}
+
+impl<V> CaselessHashMap<V> {
+    pub fn new() -> CaselessHashMap<V> {
+        CaselessHashMap {
+            map: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Insert a value, returning the previous value for a caseless-matching key.
+    pub fn insert(&mut self, key: &str, value: V) -> Option<V> {
+        self.map
+            .insert(default_case_fold_str(key), (key.to_owned(), value))
+            .map(|(_, value)| value)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&V> {
+        self.map
+            .get(&default_case_fold_str(key))
+            .map(|(_, value)| value)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut V> {
+        self.map
+            .get_mut(&default_case_fold_str(key))
+            .map(|(_, value)| value)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.map.contains_key(&default_case_fold_str(key))
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<V> {
+        self.map
+            .remove(&default_case_fold_str(key))
+            .map(|(_, value)| value)
+    }
+
+    /// The entry for a caseless-matching key, for in-place manipulation.
+    ///
+    /// Unlike `insert`, this keeps the original spelling of an existing key.
+    pub fn entry(&mut self, key: &str) -> Entry<'_, V> {
+        Entry {
+            inner: self.map.entry(default_case_fold_str(key)),
+            key: key.to_owned(),
+        }
+    }
+
+    /// Iterate over entries in arbitrary order, with keys in their original spelling.
+    pub fn iter(&self) -> Iter<'_, V> {
+        Iter {
+            inner: self.map.values(),
+        }
+    }
+}
+
+impl<V> Default for CaselessHashMap<V> {
+    fn default() -> CaselessHashMap<V> {
+        CaselessHashMap::new()
+    }
+}
+
+/// An entry in a `CaselessHashMap`, from `CaselessHashMap::entry`.
+pub struct Entry<'a, V> {
+    inner: hash_map::Entry<'a, String, (String, V)>,
+    key: String,
+}
+
+impl<'a, V> Entry<'a, V> {
+    /// The original spelling of the key in the map, or else of the key given to `entry`.
+    pub fn key(&self) -> &str {
+        match self.inner {
+            hash_map::Entry::Occupied(ref entry) => &entry.get().0,
+            hash_map::Entry::Vacant(_) => &self.key,
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        let key = self.key;
+        &mut self.inner.or_insert_with(|| (key, default())).1
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        Entry {
+            inner: self.inner.and_modify(|(_, value)| f(value)),
+            key: self.key,
+        }
+    }
+}
+
+pub struct Iter<'a, V> {
+    inner: hash_map::Values<'a, String, (String, V)>,
+}
+
+impl<'a, V> Iterator for Iter<'a, V> {
+    type Item = (&'a str, &'a V);
+
+    fn next(&mut self) -> Option<(&'a str, &'a V)> {
+        self.inner.next().map(|(key, value)| (&**key, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// A hash set of strings matched caselessly, keeping their original spelling.
+///
+/// Like `CaselessHashMap`, strings are stored by their folded form.
+/// Unlike `CaselessHashMap::insert`, inserting a string that caseless-matches
+/// one already in the set keeps the spelling that was there, as `HashSet` does.
+#[derive(Clone, Debug, Default)]
+pub struct CaselessHashSet {
+    map: HashMap<String, String>,
+}
+
+impl CaselessHashSet {
+    pub fn new() -> CaselessHashSet {
+        CaselessHashSet::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Insert a string, returning false if a caseless-matching one was present.
+    pub fn insert(&mut self, s: &str) -> bool {
+        match self.map.entry(default_case_fold_str(s)) {
+            hash_map::Entry::Occupied(_) => false,
+            hash_map::Entry::Vacant(entry) => {
+                entry.insert(s.to_owned());
+                true
+            }
+        }
+    }
+
+    pub fn contains(&self, s: &str) -> bool {
+        self.map.contains_key(&default_case_fold_str(s))
+    }
+
+    /// The original spelling of the string in the set that caseless-matches `s`.
+    pub fn get(&self, s: &str) -> Option<&str> {
+        self.map.get(&default_case_fold_str(s)).map(|s| &**s)
+    }
+
+    pub fn remove(&mut self, s: &str) -> bool {
+        self.map.remove(&default_case_fold_str(s)).is_some()
+    }
+
+    /// Iterate over strings in arbitrary order, in their original spelling.
+    pub fn iter(&self) -> SetIter<'_> {
+        SetIter {
+            inner: self.map.values(),
+        }
+    }
+}
+
+pub struct SetIter<'a> {
+    inner: hash_map::Values<'a, String, String>,
+}
+
+impl<'a> Iterator for SetIter<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        self.inner.next().map(|s| &**s)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// Maps strings to integer ids, with the same id for caseless-matching strings.
+///
+/// Ids count up from 0 in order of first appearance,
+/// and resolve to the first spelling seen.
+#[derive(Clone, Debug, Default)]
+pub struct CaselessInterner {
+    ids: HashMap<String, usize>,
+    spellings: Vec<String>,
+}
+
+impl CaselessInterner {
+    pub fn new() -> CaselessInterner {
+        CaselessInterner::default()
+    }
+
+    /// The number of distinct ids.
+    pub fn len(&self) -> usize {
+        self.spellings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spellings.is_empty()
+    }
+
+    /// Return the id of `s`, assigning a new one if no string matching it was interned.
+    pub fn intern(&mut self, s: &str) -> usize {
+        let spellings = &mut self.spellings;
+        *self.ids.entry(default_case_fold_str(s)).or_insert_with(|| {
+            spellings.push(s.to_owned());
+            spellings.len() - 1
+        })
+    }
+
+    /// Return the id of `s` if a string matching it was interned.
+    pub fn get(&self, s: &str) -> Option<usize> {
+        self.ids.get(&default_case_fold_str(s)).cloned()
+    }
+
+    /// Return the first spelling interned with `id`.
+    pub fn resolve(&self, id: usize) -> Option<&str> {
+        self.spellings.get(id).map(|s| &**s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map() {
+        let mut map = CaselessHashMap::new();
+        assert_eq!(map.insert("strasse", 1), None);
+        assert_eq!(map.get("STRASSE"), Some(&1));
+        assert_eq!(map.get("Straße"), Some(&1));
+        assert!(map.contains_key("StrassE"));
+        assert!(!map.contains_key("Strase"));
+
+        assert_eq!(map.insert("Straße", 2), Some(1));
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.iter().collect::<Vec<_>>(), [("Straße", &2)]);
+
+        *map.get_mut("STRASSE").unwrap() += 1;
+        assert_eq!(map.remove("strasse"), Some(3));
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_entry() {
+        let mut counts = CaselessHashMap::new();
+        for word in "The cat saw THE dog, the end".split(' ') {
+            *counts.entry(word).or_insert(0) += 1;
+        }
+        assert_eq!(counts.get("the"), Some(&3));
+        assert_eq!(counts.get("CAT"), Some(&1));
+        assert_eq!(counts.entry("tHe").key(), "The");
+        assert_eq!(counts.entry("new").key(), "new");
+
+        counts.entry("Cat").and_modify(|n| *n += 10).or_default();
+        counts.entry("Cow").and_modify(|n| *n += 10).or_default();
+        assert_eq!(counts.get("cat"), Some(&11));
+        assert_eq!(counts.get("cow"), Some(&0));
+        assert_eq!(*counts.entry("STRASSE").or_insert_with(|| 7), 7);
+        assert_eq!(*counts.entry("straße").or_insert_with(|| 8), 7);
+    }
+
+    #[test]
+    fn test_set() {
+        let mut set = CaselessHashSet::new();
+        assert!(set.insert("Foo"));
+        assert!(set.contains("FOO"));
+        assert!(!set.insert("fOO"));
+        assert!(set.insert("Straße"));
+        assert!(set.contains("STRASSE"));
+        assert!(!set.contains("bar"));
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.get("foo"), Some("Foo"));
+
+        let mut spellings = set.iter().collect::<Vec<_>>();
+        spellings.sort();
+        assert_eq!(spellings, ["Foo", "Straße"]);
+
+        assert!(set.remove("strasse"));
+        assert!(!set.remove("strasse"));
+        assert_eq!(set.iter().collect::<Vec<_>>(), ["Foo"]);
+        assert!(set.remove("foo"));
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn test_interner() {
+        let mut interner = CaselessInterner::new();
+        let foo = interner.intern("Foo");
+        assert_eq!(interner.intern("FOO"), foo);
+        let strasse = interner.intern("Straße");
+        assert_ne!(strasse, foo);
+        assert_eq!(interner.intern("STRASSE"), strasse);
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.get("foo"), Some(foo));
+        assert_eq!(interner.get("bar"), None);
+        assert_eq!(interner.resolve(foo), Some("Foo"));
+        assert_eq!(interner.resolve(strasse), Some("Straße"));
+        assert_eq!(interner.resolve(2), None);
+    }
+}
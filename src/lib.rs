@@ -1,25 +1,103 @@
-use unicode_normalization::UnicodeNormalization;
+#![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate core;
 extern crate unicode_normalization;
 
+#[cfg(feature = "bstr")]
+extern crate bstr;
+#[cfg(feature = "compact_str")]
+extern crate compact_str;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_test;
+#[cfg(feature = "smol_str")]
+extern crate smol_str;
+
+use alloc::borrow::Cow;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::{Drain, String};
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::iter::{Cloned, FusedIterator};
+use core::str::{self, CharIndices, Chars, Utf8Error};
+#[cfg(feature = "std")]
+use std::ffi::OsStr;
+use unicode_normalization::{Decompositions, UnicodeNormalization};
+
+#[cfg(feature = "simd")]
+mod ascii;
 mod case_folding_data;
 pub use case_folding_data::UNICODE_VERSION;
 use case_folding_data::*;
 
+mod caseless_string;
+#[cfg(feature = "smol_str")]
+pub use caseless_string::CaselessSmolStr;
+pub use caseless_string::{CaselessOrd, CaselessStr, CaselessString};
+
+#[cfg(feature = "std")]
+pub mod collections;
+
+mod matcher;
+pub use matcher::{CaselessMatcher, CaselessMatcherFindIter};
+
+mod options;
+pub use options::{CaseFoldOptions, Normalization};
+
+mod search;
+pub use search::{
+    caseless_common_prefix, caseless_common_prefix_len, caseless_ends_with, caseless_find,
+    caseless_find_iter, caseless_find_range, caseless_match_prefix_n, caseless_matches,
+    caseless_mismatch, caseless_replace, caseless_split, caseless_starts_with,
+    caseless_strip_prefix, caseless_strip_suffix, CaselessFindIter, CaselessMatches, CaselessSplit,
+};
+
+mod write;
+#[cfg(feature = "std")]
+pub use write::write_case_folded;
+pub use write::CaseFoldWriter;
+
 pub trait Caseless {
     fn default_case_fold(self) -> CaseFold<Self>
     where
         Self: Sized;
     fn default_caseless_match<J: Iterator<Item = char>>(self, other: J) -> bool;
+    fn default_caseless_cmp<J: Iterator<Item = char>>(self, other: J) -> Ordering;
+    fn canonical_case_fold(self) -> CanonicalCaseFold<Self>
+    where
+        Self: Sized;
     fn canonical_caseless_match<J: Iterator<Item = char>>(self, other: J) -> bool;
+    fn compatibility_case_fold(self) -> CompatibilityCaseFold<Self>
+    where
+        Self: Sized;
     fn compatibility_caseless_match<J: Iterator<Item = char>>(self, other: J) -> bool;
+    fn turkic_case_fold(self) -> TurkicCaseFold<Self>
+    where
+        Self: Sized;
+    fn turkic_caseless_match<J: Iterator<Item = char>>(self, other: J) -> bool;
+    fn simple_case_fold(self) -> SimpleCaseFold<Self>
+    where
+        Self: Sized;
+    fn simple_caseless_match<J: Iterator<Item = char>>(self, other: J) -> bool;
+    fn case_fold_with_source(self) -> CaseFoldWithSource<Self>
+    where
+        Self: Sized;
 }
 
 impl<I: Iterator<Item = char>> Caseless for I {
     fn default_case_fold(self) -> CaseFold<I> {
         CaseFold {
             chars: self,
-            queue: ['\0', '\0'],
+            queue: CharCaseFold::empty(),
+            back_queue: CharCaseFold::empty(),
+            last: ('\0', ['\0'; 3]),
         }
     }
 
@@ -27,8 +105,19 @@ impl<I: Iterator<Item = char>> Caseless for I {
         iter_eq(self.default_case_fold(), other.default_case_fold())
     }
 
+    fn default_caseless_cmp<J: Iterator<Item = char>>(self, other: J) -> Ordering {
+        self.default_case_fold().cmp(other.default_case_fold())
+    }
+
+    fn canonical_case_fold(self) -> CanonicalCaseFold<I> {
+        CanonicalCaseFold {
+            chars: self.nfd().default_case_fold().nfd(),
+        }
+    }
+
     fn canonical_caseless_match<J: Iterator<Item = char>>(self, other: J) -> bool {
-        // FIXME: Inner NFD can be optimized:
+        // FIXME: Inner NFD can be optimized (canonical_caseless_match_str does,
+        // but an arbitrary iterator can't be scanned ahead):
         // "Normalization is not required before case folding,
         //  except for the character U+0345 "combining greek ypogegrammeni"
         //  and any characters that have it as part of their canonical decomposition,
@@ -37,119 +126,2190 @@ impl<I: Iterator<Item = char>> Caseless for I {
         //  can catch these special cases, thereby avoiding an extra normalization
         //  step for each comparison."
         // Unicode Standard, section 3.13 Default Case Algorithms
-        iter_eq(
-            self.nfd().default_case_fold().nfd(),
-            other.nfd().default_case_fold().nfd(),
-        )
+        iter_eq(self.canonical_case_fold(), other.canonical_case_fold())
     }
 
-    fn compatibility_caseless_match<J: Iterator<Item = char>>(self, other: J) -> bool {
-        // FIXME: Unclear if the inner NFD can be optimized here like in canonical_caseless_match.
-        iter_eq(
-            self.nfd()
-                .default_case_fold()
-                .nfkd()
-                .default_case_fold()
-                .nfkd(),
-            other
+    fn compatibility_case_fold(self) -> CompatibilityCaseFold<I> {
+        CompatibilityCaseFold {
+            chars: self
                 .nfd()
                 .default_case_fold()
                 .nfkd()
                 .default_case_fold()
                 .nfkd(),
+        }
+    }
+
+    fn compatibility_caseless_match<J: Iterator<Item = char>>(self, other: J) -> bool {
+        // FIXME: Unclear if the inner NFD can be optimized here like in canonical_caseless_match.
+        iter_eq(
+            self.compatibility_case_fold(),
+            other.compatibility_case_fold(),
         )
     }
+
+    fn turkic_case_fold(self) -> TurkicCaseFold<I> {
+        TurkicCaseFold {
+            chars: self,
+            queue: CharCaseFold::empty(),
+        }
+    }
+
+    fn turkic_caseless_match<J: Iterator<Item = char>>(self, other: J) -> bool {
+        iter_eq(self.turkic_case_fold(), other.turkic_case_fold())
+    }
+
+    fn simple_case_fold(self) -> SimpleCaseFold<I> {
+        SimpleCaseFold { chars: self }
+    }
+
+    fn simple_caseless_match<J: Iterator<Item = char>>(self, other: J) -> bool {
+        iter_eq(self.simple_case_fold(), other.simple_case_fold())
+    }
+
+    fn case_fold_with_source(self) -> CaseFoldWithSource<I> {
+        CaseFoldWithSource { chars: self }
+    }
+}
+
+/// The methods of `Caseless` for iterators of `&char`,
+/// such as `slice::Iter<char>`, copying the characters.
+pub trait CaselessRef {
+    fn default_case_fold(self) -> CaseFold<Cloned<Self>>
+    where
+        Self: Sized;
+    fn default_caseless_match<'b, J: Iterator<Item = &'b char>>(self, other: J) -> bool;
+    fn default_caseless_cmp<'b, J: Iterator<Item = &'b char>>(self, other: J) -> Ordering;
+    fn canonical_case_fold(self) -> CanonicalCaseFold<Cloned<Self>>
+    where
+        Self: Sized;
+    fn canonical_caseless_match<'b, J: Iterator<Item = &'b char>>(self, other: J) -> bool;
+    fn compatibility_case_fold(self) -> CompatibilityCaseFold<Cloned<Self>>
+    where
+        Self: Sized;
+    fn compatibility_caseless_match<'b, J: Iterator<Item = &'b char>>(self, other: J) -> bool;
+    fn turkic_case_fold(self) -> TurkicCaseFold<Cloned<Self>>
+    where
+        Self: Sized;
+    fn turkic_caseless_match<'b, J: Iterator<Item = &'b char>>(self, other: J) -> bool;
+    fn simple_case_fold(self) -> SimpleCaseFold<Cloned<Self>>
+    where
+        Self: Sized;
+    fn simple_caseless_match<'b, J: Iterator<Item = &'b char>>(self, other: J) -> bool;
+    fn case_fold_with_source(self) -> CaseFoldWithSource<Cloned<Self>>
+    where
+        Self: Sized;
+}
+
+impl<'a, I: Iterator<Item = &'a char>> CaselessRef for I {
+    fn default_case_fold(self) -> CaseFold<Cloned<I>> {
+        self.cloned().default_case_fold()
+    }
+
+    fn default_caseless_match<'b, J: Iterator<Item = &'b char>>(self, other: J) -> bool {
+        self.cloned().default_caseless_match(other.cloned())
+    }
+
+    fn default_caseless_cmp<'b, J: Iterator<Item = &'b char>>(self, other: J) -> Ordering {
+        self.cloned().default_caseless_cmp(other.cloned())
+    }
+
+    fn canonical_case_fold(self) -> CanonicalCaseFold<Cloned<I>> {
+        self.cloned().canonical_case_fold()
+    }
+
+    fn canonical_caseless_match<'b, J: Iterator<Item = &'b char>>(self, other: J) -> bool {
+        self.cloned().canonical_caseless_match(other.cloned())
+    }
+
+    fn compatibility_case_fold(self) -> CompatibilityCaseFold<Cloned<I>> {
+        self.cloned().compatibility_case_fold()
+    }
+
+    fn compatibility_caseless_match<'b, J: Iterator<Item = &'b char>>(self, other: J) -> bool {
+        self.cloned().compatibility_caseless_match(other.cloned())
+    }
+
+    fn turkic_case_fold(self) -> TurkicCaseFold<Cloned<I>> {
+        self.cloned().turkic_case_fold()
+    }
+
+    fn turkic_caseless_match<'b, J: Iterator<Item = &'b char>>(self, other: J) -> bool {
+        self.cloned().turkic_caseless_match(other.cloned())
+    }
+
+    fn simple_case_fold(self) -> SimpleCaseFold<Cloned<I>> {
+        self.cloned().simple_case_fold()
+    }
+
+    fn simple_caseless_match<'b, J: Iterator<Item = &'b char>>(self, other: J) -> bool {
+        self.cloned().simple_caseless_match(other.cloned())
+    }
+
+    fn case_fold_with_source(self) -> CaseFoldWithSource<Cloned<I>> {
+        self.cloned().case_fold_with_source()
+    }
+}
+
+/// Caseless methods on `str`, covering the `*_str` functions.
+///
+/// `Caseless` is the lower-level API, for any iterator of `char`.
+pub trait CaselessExt {
+    /// Same as `default_case_fold_str`.
+    fn case_fold(&self) -> String;
+    /// Same as `default_caseless_match_str`.
+    fn caseless_eq(&self, other: &str) -> bool;
+    /// Same as `canonical_caseless_match_str`.
+    fn canonical_caseless_eq(&self, other: &str) -> bool;
+    /// Same as `compatibility_caseless_match_str`.
+    fn compatibility_caseless_eq(&self, other: &str) -> bool;
+    /// Same as `eq_ignore_case`.
+    fn eq_ignore_case(&self, other: &str) -> bool;
+}
+
+impl CaselessExt for str {
+    fn case_fold(&self) -> String {
+        default_case_fold_str(self)
+    }
+
+    fn caseless_eq(&self, other: &str) -> bool {
+        default_caseless_match_str(self, other)
+    }
+
+    fn canonical_caseless_eq(&self, other: &str) -> bool {
+        canonical_caseless_match_str(self, other)
+    }
+
+    fn compatibility_caseless_eq(&self, other: &str) -> bool {
+        compatibility_caseless_match_str(self, other)
+    }
+
+    fn eq_ignore_case(&self, other: &str) -> bool {
+        eq_ignore_case(self, other)
+    }
 }
 
 pub fn default_case_fold_str(s: &str) -> String {
+    #[cfg(feature = "simd")]
+    {
+        let mut folded = String::new();
+        ascii::case_fold_into(s, &mut folded);
+        folded
+    }
+    #[cfg(not(feature = "simd"))]
     s.chars().default_case_fold().collect()
 }
 
-pub fn default_caseless_match_str(a: &str, b: &str) -> bool {
-    a.chars().default_caseless_match(b.chars())
+/// Like `default_case_fold_str`, returning a `CompactString`,
+/// which keeps short foldings inline instead of on the heap.
+///
+/// The matching functions take `&str`, so `&CompactString` can be passed
+/// to them directly.
+#[cfg(feature = "compact_str")]
+pub fn default_case_fold_compact(s: &str) -> compact_str::CompactString {
+    s.chars().default_case_fold().collect()
 }
 
-pub fn canonical_caseless_match_str(a: &str, b: &str) -> bool {
-    a.chars().canonical_caseless_match(b.chars())
+/// Like `default_case_fold_str`, returning a `SmolStr`, which is cheap to clone.
+#[cfg(feature = "smol_str")]
+pub fn default_case_fold_smol(s: &str) -> smol_str::SmolStr {
+    s.chars().default_case_fold().collect()
 }
 
-pub fn compatibility_caseless_match_str(a: &str, b: &str) -> bool {
-    a.chars().compatibility_caseless_match(b.chars())
+/// Like `default_case_fold_str`, collecting characters into a `Vec`.
+pub fn default_case_fold_chars(s: &str) -> Vec<char> {
+    // Most characters fold to one character, and most text has more bytes than characters.
+    let mut chars = Vec::with_capacity(s.len());
+    chars.extend(s.chars().default_case_fold());
+    chars
 }
 
-fn iter_eq<L: Iterator, R: Iterator>(mut a: L, mut b: R) -> bool
+/// Like `default_case_fold_str`, but reuses the allocation of `out`,
+/// which is cleared first.
+///
+/// To append to a buffer without clearing it,
+/// pass a `CaseFold` iterator to `String::extend`.
+pub fn default_case_fold_into(s: &str, out: &mut String) {
+    out.clear();
+    #[cfg(feature = "simd")]
+    ascii::case_fold_into(s, out);
+    #[cfg(not(feature = "simd"))]
+    {
+        out.reserve(s.len());
+        out.extend(s.chars().default_case_fold());
+    }
+}
+
+/// Remove strings that caseless-match an earlier string in `v`,
+/// keeping the first spelling of each. `v` does not need to be sorted.
+pub fn dedup_caseless<S: AsRef<str>>(v: &mut Vec<S>) {
+    let mut seen = BTreeSet::new();
+    v.retain(|s| seen.insert(default_case_fold_str(s.as_ref())));
+}
+
+/// Like `dedup_caseless`, with canonical caseless matching.
+pub fn canonical_dedup_caseless<S: AsRef<str>>(v: &mut Vec<S>) {
+    let mut seen = BTreeSet::new();
+    v.retain(|s| seen.insert(canonical_case_fold_str(s.as_ref())));
+}
+
+/// Return the pairs of distinct strings in `items` that caseless-match,
+/// such as `("Timeout", "timeout")`.
+///
+/// Pairs are grouped by case folding, in order of first appearance,
+/// and each pair is in the order its strings appear.
+/// Repeats of the same string are ignored.
+pub fn caseless_collisions<'a, I>(items: I) -> Vec<(&'a str, &'a str)>
 where
-    L::Item: PartialEq<R::Item>,
+    I: IntoIterator<Item = &'a str>,
 {
-    loop {
-        match (a.next(), b.next()) {
-            (None, None) => return true,
-            (None, _) | (_, None) => return false,
-            (Some(x), Some(y)) => {
-                if !x.eq(&y) {
-                    return false;
-                }
+    let mut group_indices = BTreeMap::new();
+    let mut groups: Vec<Vec<&'a str>> = Vec::new();
+    for item in items {
+        let index = *group_indices
+            .entry(default_case_fold_str(item))
+            .or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+        if !groups[index].contains(&item) {
+            groups[index].push(item);
+        }
+    }
+    let mut collisions = Vec::new();
+    for group in groups {
+        for (i, &a) in group.iter().enumerate() {
+            for &b in &group[i + 1..] {
+                collisions.push((a, b));
             }
         }
     }
+    collisions
 }
 
-pub struct CaseFold<I> {
-    chars: I,
-    queue: [char; 2],
+/// Group the indices of `items` that caseless-match, folding them in parallel.
+///
+/// Every index is in exactly one group, in increasing order,
+/// and groups are in order of their first index.
+#[cfg(feature = "rayon")]
+pub fn par_caseless_group<S: AsRef<str> + Sync>(items: &[S]) -> Vec<Vec<usize>> {
+    use rayon::prelude::*;
+
+    let keys = items
+        .par_iter()
+        .map(|s| default_case_fold_str(s.as_ref()))
+        .collect::<Vec<_>>();
+    let mut order = (0..items.len()).collect::<Vec<_>>();
+    // Stable, so that indices stay in increasing order within a group.
+    order.par_sort_by(|&a, &b| keys[a].cmp(&keys[b]));
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for i in order {
+        match groups.last_mut() {
+            Some(group) if keys[group[0]] == keys[i] => group.push(i),
+            _ => groups.push(alloc::vec![i]),
+        }
+    }
+    groups.par_sort_unstable_by_key(|group| group[0]);
+    groups
 }
 
-impl<I> Iterator for CaseFold<I>
+/// Replace `s` with its default case folding, keeping its allocation.
+///
+/// This does not allocate if `s` is already case folded. Otherwise, since
+/// folding can change the length, the part of `s` from the first character
+/// that folding changes is folded into a temporary `String` and copied back.
+pub fn default_case_fold_in_place(s: &mut String) {
+    if let Some((i, _)) = s.char_indices().find(|&(_, c)| char_is_foldable(c)) {
+        let tail = default_case_fold_str(&s[i..]);
+        s.truncate(i);
+        s.push_str(&tail);
+    }
+}
+
+/// Like `default_case_fold_str`, but only allocates if folding changes `s`.
+pub fn default_case_fold_cow(s: &str) -> Cow<'_, str> {
+    let first_foldable = s.char_indices().find(|&(_, c)| char_is_foldable(c));
+    match first_foldable {
+        None => Cow::Borrowed(s),
+        Some((i, _)) => {
+            let mut folded = String::with_capacity(s.len());
+            folded.push_str(&s[..i]);
+            folded.extend(s[i..].chars().default_case_fold());
+            Cow::Owned(folded)
+        }
+    }
+}
+
+/// Fold `chars` as `canonical_case_fold_str` does, lazily:
+/// `Caseless::canonical_case_fold` as a function.
+///
+/// NFD, case folding and NFD again are chained iterator adapters, so this is
+/// a single pass over `chars` with no intermediate string. Each NFD stage
+/// buffers only a run of combining marks, to put them in canonical order.
+pub fn canonical_case_fold<I: Iterator<Item = char>>(chars: I) -> CanonicalCaseFold<I> {
+    chars.canonical_case_fold()
+}
+
+/// Return the string that `canonical_caseless_match_str` compares:
+/// `s` in NFD, case folded, then in NFD again.
+///
+/// Two strings canonical-caseless-match if and only if
+/// their `canonical_case_fold_str` are equal.
+pub fn canonical_case_fold_str(s: &str) -> String {
+    canonical_input(s).default_case_fold().nfd().collect()
+}
+
+/// Return the canonical caseless normal form of `s`, for storing a single
+/// representation of all strings that canonical-caseless-match it.
+///
+/// This is `canonical_case_fold_str`: `NFD(toCasefold(NFD(s)))`.
+/// The Unicode stability policies keep the case folding of assigned
+/// characters stable, so stored forms stay valid across data updates,
+/// except for characters newly assigned in later versions.
+pub fn to_canonical_caseless(s: &str) -> String {
+    canonical_case_fold_str(s)
+}
+
+/// Return the string that `compatibility_caseless_match_str` compares:
+/// `s` in NFD, case folded, in NFKD, case folded again, then in NFKD again.
+///
+/// Two strings compatibility-caseless-match if and only if
+/// their `compatibility_case_fold_str` are equal.
+pub fn compatibility_case_fold_str(s: &str) -> String {
+    s.chars().compatibility_case_fold().collect()
+}
+
+/// Which caseless matching `caseless_match_str` does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MatchMode {
+    /// As in `default_caseless_match_str`.
+    #[default]
+    Default,
+    /// As in `canonical_caseless_match_str`.
+    Canonical,
+    /// As in `compatibility_caseless_match_str`.
+    Compatibility,
+}
+
+/// Return whether `a` and `b` caseless-match with the matching
+/// selected by `mode`, which can come from configuration.
+pub fn caseless_match_str(a: &str, b: &str, mode: MatchMode) -> bool {
+    match mode {
+        MatchMode::Default => a.chars().default_caseless_match(b.chars()),
+        MatchMode::Canonical => iter_eq(
+            canonical_input(a).default_case_fold().nfd(),
+            canonical_input(b).default_case_fold().nfd(),
+        ),
+        MatchMode::Compatibility => a.chars().compatibility_caseless_match(b.chars()),
+    }
+}
+
+pub fn default_caseless_match_str(a: &str, b: &str) -> bool {
+    caseless_match_str(a, b, MatchMode::Default)
+}
+
+/// Same as `default_caseless_match_str`: a Unicode analogue of
+/// `str::eq_ignore_ascii_case`.
+pub fn eq_ignore_case(a: &str, b: &str) -> bool {
+    default_caseless_match_str(a, b)
+}
+
+/// Return whether `s` caseless-matches any of `candidates`.
+///
+/// `s` is folded once, not once per candidate.
+pub fn any_caseless_match<'a, I>(s: &str, candidates: I) -> bool
 where
-    I: Iterator<Item = char>,
+    I: IntoIterator<Item = &'a str>,
 {
-    type Item = char;
+    find_caseless_match(s, candidates).is_some()
+}
 
-    fn next(&mut self) -> Option<char> {
-        let c = self.queue[0];
-        if c != '\0' {
-            self.queue[0] = self.queue[1];
-            self.queue[1] = '\0';
-            return Some(c);
-        }
-        self.chars.next().map(
-            |c| match CASE_FOLDING_TABLE.binary_search_by(|&(x, _)| x.cmp(&c)) {
-                Err(_) => c,
-                Ok(i) => {
-                    let folded = CASE_FOLDING_TABLE[i].1;
-                    self.queue = [folded[1], folded[2]];
-                    folded[0]
-                }
-            },
+/// Return the index and value of the first of `candidates`
+/// that caseless-matches `s`.
+pub fn find_caseless_match<'a, I>(s: &str, candidates: I) -> Option<(usize, &'a str)>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let folded = default_case_fold_chars(s);
+    candidates.into_iter().enumerate().find(|&(_, candidate)| {
+        iter_eq(
+            folded.iter().cloned(),
+            candidate.chars().default_case_fold(),
         )
+    })
+}
+
+/// Like `default_case_fold_str`, for UTF-8 bytes.
+pub fn default_case_fold_bytes(input: &[u8]) -> Result<String, Utf8Error> {
+    str::from_utf8(input).map(default_case_fold_str)
+}
+
+/// Like `default_caseless_match_str`, for UTF-8 bytes.
+///
+/// Returns `false` if either input is not valid UTF-8.
+pub fn default_caseless_match_bytes(a: &[u8], b: &[u8]) -> bool {
+    match (str::from_utf8(a), str::from_utf8(b)) {
+        (Ok(a), Ok(b)) => default_caseless_match_str(a, b),
+        _ => false,
     }
+}
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        let queue_len = if self.queue[0] == '\0' {
-            0
-        } else if self.queue[1] == '\0' {
-            1
-        } else {
-            2
-        };
-        let (low, high) = self.chars.size_hint();
-        (
-            low.saturating_add(queue_len),
-            high.and_then(|h| h.checked_mul(3))
-                .and_then(|h| h.checked_add(queue_len)),
-        )
+/// Like `default_caseless_match_str`, for byte strings that are mostly UTF-8.
+///
+/// Each input is split into runs of valid UTF-8 and invalid bytes, as with
+/// `<[u8]>::utf8_chunks`. Valid runs are compared by their default case
+/// folding, and invalid bytes are compared verbatim: an invalid byte only
+/// matches the same byte at the same place in the folded sequence,
+/// never a character, not even U+FFFD REPLACEMENT CHARACTER.
+/// So `"A\xFF"` matches `"a\xFF"` but not `"a\xFE"` or `"a\u{FFFD}"`.
+#[cfg(feature = "bstr")]
+pub fn caseless_match_bstr(a: &bstr::BStr, b: &bstr::BStr) -> bool {
+    fn fold(s: &bstr::BStr) -> impl Iterator<Item = Result<char, u8>> + '_ {
+        s.utf8_chunks().flat_map(|chunk| {
+            let valid = chunk.valid().chars().default_case_fold().map(Ok);
+            valid.chain(chunk.invalid().iter().map(|&b| Err(b)))
+        })
     }
+    iter_eq(fold(a), fold(b))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::default_case_fold_str;
+/// Like `default_caseless_match_str`, for `OsStr` such as path components.
+///
+/// If either string is not valid Unicode, they match only if they are equal.
+/// (Lossy conversion is avoided because it would make distinct invalid
+/// sequences match each other.)
+#[cfg(feature = "std")]
+pub fn caseless_match_os(a: &OsStr, b: &OsStr) -> bool {
+    match (a.to_str(), b.to_str()) {
+        (Some(a), Some(b)) => default_caseless_match_str(a, b),
+        _ => a == b,
+    }
+}
 
-    #[test]
-    fn test_strs() {
-        assert_eq!(default_case_fold_str("Test Case"), "test case");
-        assert_eq!(default_case_fold_str("Teſt Caſe"), "test case");
-        assert_eq!(default_case_fold_str("spiﬃest"), "spiffiest");
-        assert_eq!(default_case_fold_str("straße"), "strasse");
+pub fn canonical_caseless_match_str(a: &str, b: &str) -> bool {
+    caseless_match_str(a, b, MatchMode::Canonical)
+}
+
+/// Like `canonical_caseless_match_str`, normalizing to NFC after case folding:
+/// `NFC(toCasefold(NFD(X)))` instead of `NFD(toCasefold(NFD(X)))`.
+///
+/// This always gives the same result, since two strings have the same NFC
+/// if and only if they have the same NFD. It compares composed forms,
+/// which are often shorter.
+pub fn canonical_caseless_match_nfc(a: &str, b: &str) -> bool {
+    iter_eq(
+        canonical_input(a).default_case_fold().nfc(),
+        canonical_input(b).default_case_fold().nfc(),
+    )
+}
+
+/// Caseless and diacritic-insensitive matching: `"café"` matches `"CAFE"`.
+///
+/// This is a lossy matcher of this crate, not a Unicode algorithm.
+/// Both strings are converted as for `canonical_caseless_match_str`,
+/// then combining marks (general category M) are removed before comparing.
+/// This also removes marks that are not diacritics, such as Indic vowel signs;
+/// use `loose_caseless_match_str_with` to choose which characters to remove.
+pub fn loose_caseless_match_str(a: &str, b: &str) -> bool {
+    loose_caseless_match_str_with(a, b, unicode_normalization::char::is_combining_mark)
+}
+
+/// Like `loose_caseless_match_str`, removing the characters for which
+/// `is_ignored` returns true.
+pub fn loose_caseless_match_str_with<F>(a: &str, b: &str, is_ignored: F) -> bool
+where
+    F: Fn(char) -> bool,
+{
+    iter_eq(
+        canonical_input(a)
+            .default_case_fold()
+            .nfd()
+            .filter(|&c| !is_ignored(c)),
+        canonical_input(b)
+            .default_case_fold()
+            .nfd()
+            .filter(|&c| !is_ignored(c)),
+    )
+}
+
+pub fn compatibility_caseless_match_str(a: &str, b: &str) -> bool {
+    caseless_match_str(a, b, MatchMode::Compatibility)
+}
+
+/// Compare the default case foldings of `a` and `b` by code point.
+///
+/// Returns `Ordering::Equal` if and only if `default_caseless_match_str(a, b)`.
+pub fn caseless_cmp(a: &str, b: &str) -> Ordering {
+    a.chars().default_caseless_cmp(b.chars())
+}
+
+/// Like `caseless_cmp`, but consistent with `canonical_caseless_match_str`.
+pub fn canonical_caseless_cmp(a: &str, b: &str) -> Ordering {
+    canonical_input(a)
+        .default_case_fold()
+        .nfd()
+        .cmp(canonical_input(b).default_case_fold().nfd())
+}
+
+/// Feed the default case folding of `s` into a hasher.
+///
+/// If `default_caseless_match_str(a, b)`, then `a` and `b` hash the same.
+pub fn caseless_hash<H: Hasher>(s: &str, state: &mut H) {
+    for c in s.chars().default_case_fold() {
+        c.hash(state);
+    }
+    // Like str, end with a byte that can't be part of the rest
+    // so that ("ab", "c") and ("a", "bc") hash differently in a tuple.
+    state.write_u8(0xff);
+}
+
+/// Return a key for sorting with `caseless_cmp` by comparing keys as plain strings.
+///
+/// Two keys are equal if and only if the strings they were made from
+/// match with `default_caseless_match_str`.
+/// Keys are stable for a given `UNICODE_VERSION`.
+pub fn caseless_sort_key(s: &str) -> String {
+    default_case_fold_str(s)
+}
+
+/// Like `caseless_sort_key`, consistent with `canonical_caseless_cmp`
+/// and `canonical_caseless_match_str`.
+pub fn canonical_caseless_sort_key(s: &str) -> String {
+    canonical_case_fold_str(s)
+}
+
+/// Sort strings by `canonical_caseless_cmp`, folding each of them once.
+///
+/// The sort is stable, so strings that canonical-caseless-match
+/// keep their relative order.
+pub fn canonical_caseless_sort<S: AsRef<str>>(v: &mut [S]) {
+    v.sort_by_cached_key(|s| canonical_caseless_sort_key(s.as_ref()))
+}
+
+/// A best-effort key for file names on case-insensitive file systems:
+/// names whose keys are equal would likely collide there.
+///
+/// This is the default case folding, without normalization, which is close
+/// to NTFS. File systems use their own, often older, case tables, so this
+/// can differ from them for some characters. See `fs_caseless_key_nfd`
+/// for file systems that also normalize, like HFS+.
+pub fn fs_caseless_key(name: &str) -> String {
+    default_case_fold_str(name)
+}
+
+/// Like `fs_caseless_key`, also normalizing to NFD as HFS+ does,
+/// so that precomposed and decomposed spellings collide.
+pub fn fs_caseless_key_nfd(name: &str) -> String {
+    canonical_case_fold_str(name)
+}
+
+/// Case folding with the Turkic (`tr`, `az`) mappings for `I` and `İ`:
+/// `I` folds to `ı` and `İ` folds to `i`. All other characters fold
+/// as in `default_case_fold_str`.
+pub fn turkic_default_case_fold_str(s: &str) -> String {
+    s.chars().turkic_case_fold().collect()
+}
+
+/// Same as `turkic_default_case_fold_str`.
+pub fn turkic_case_fold_str(s: &str) -> String {
+    turkic_default_case_fold_str(s)
+}
+
+/// Caseless matching with the Turkic (`tr`, `az`) mappings.
+///
+/// This differs from `default_caseless_match_str` only for strings
+/// containing `I` or `İ`, which match `ı` and `i` respectively,
+/// so `"I"` and `"i"` do not match.
+pub fn turkic_caseless_match_str(a: &str, b: &str) -> bool {
+    a.chars().turkic_caseless_match(b.chars())
+}
+
+/// Simple case folding: each character folds to exactly one character,
+/// so the output has as many characters as the input (`ß` stays `ß`).
+///
+/// Simple case folding only uses the table of simple mappings,
+/// so a binary that does not use full case folding does not include its data.
+pub fn simple_case_fold_str(s: &str) -> String {
+    s.chars().simple_case_fold().collect()
+}
+
+/// Caseless matching with simple case folding: `"ß"` and `"ss"` do not match.
+pub fn simple_caseless_match_str(a: &str, b: &str) -> bool {
+    a.chars().simple_caseless_match(b.chars())
+}
+
+/// Fold a single character, yielding one to three characters.
+pub fn case_fold_char(c: char) -> CharCaseFold {
+    CharCaseFold::new(fold(c))
+}
+
+/// Return the full case folding of `c` and its length, 1 to 3,
+/// padded with `'\0'`: `char_fold('ß')` is `(['s', 's', '\0'], 2)`.
+pub fn char_fold(c: char) -> ([char; 3], usize) {
+    let folded = fold(c);
+    (folded, fold_len(&folded))
+}
+
+/// Return the number of characters `c` folds to: 1, 2, or 3.
+pub fn case_fold_len(c: char) -> usize {
+    char_fold(c).1
+}
+
+/// Return the length in bytes of `default_case_fold_str(s)`, without allocating.
+pub fn case_folded_len(s: &str) -> usize {
+    s.chars().default_case_fold().map(char::len_utf8).sum()
+}
+
+/// Return an upper bound on `case_folded_len(s)`, from the length of `s` alone.
+///
+/// Folding a character at most triples its length in bytes (`'ΐ'`),
+/// so `String::with_capacity(case_fold_capacity_hint(s))` can be passed to
+/// `default_case_fold_into` without reallocating. For the exact length,
+/// which takes a pass over `s`, use `case_folded_len`.
+pub fn case_fold_capacity_hint(s: &str) -> usize {
+    s.len().saturating_mul(MAX_FOLDED_CHARS)
+}
+
+/// Return whether full case folding changes `c`.
+pub fn char_is_foldable(c: char) -> bool {
+    find(c).is_some()
+}
+
+/// Return whether `a` and `b` fold to the same characters.
+///
+/// A character that folds to several, like `'ﬃ'`, only equals itself.
+pub fn chars_caseless_eq(a: char, b: char) -> bool {
+    a == b || fold(a) == fold(b)
+}
+
+/// Like `default_caseless_match_str`, for text kept as `char` slices
+/// such as `Vec<char>` buffers: `caseless_match_chars(&['Ä'], &['ä'])`.
+///
+/// Both are folded lazily, stopping at the first difference.
+pub fn caseless_match_chars(a: &[char], b: &[char]) -> bool {
+    CaselessRef::default_caseless_match(a.iter(), b.iter())
+}
+
+/// Same as `caseless_match_chars`.
+pub fn caseless_eq_chars(a: &[char], b: &[char]) -> bool {
+    caseless_match_chars(a, b)
+}
+
+/// A version of the Unicode case folding data bundled with this crate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum UnicodeVersion {
+    /// The version in `UNICODE_VERSION`, used by all other functions.
+    Latest,
+    /// Unicode 10.0.0, used by versions 0.2.1 and earlier of this crate.
+    #[cfg(feature = "unicode-10")]
+    V10_0_0,
+}
+
+impl UnicodeVersion {
+    fn fold(self, c: char) -> [char; 3] {
+        match self {
+            UnicodeVersion::Latest => fold(c),
+            #[cfg(feature = "unicode-10")]
+            UnicodeVersion::V10_0_0 => {
+                search(CASE_FOLDING_TABLE_10_0_0, c).unwrap_or([c, '\0', '\0'])
+            }
+        }
+    }
+}
+
+/// Like `default_case_fold_str`, with the case folding data of a given Unicode version.
+pub fn default_case_fold_str_with(version: UnicodeVersion, s: &str) -> String {
+    s.chars()
+        .flat_map(|c| CharCaseFold::new(version.fold(c)))
+        .collect()
+}
+
+/// Return the characters whose full case folding is exactly `target`.
+///
+/// This includes `target` itself if it is not changed by folding.
+/// For example `chars_folding_to('k')` yields `k`, `K`, and U+212A KELVIN SIGN.
+pub fn chars_folding_to(target: char) -> impl Iterator<Item = char> {
+    let start = INVERSE_CASE_FOLDING_TABLE.partition_point(|&(to, _)| to < target);
+    let identity = if find(target).is_none() {
+        Some(target)
+    } else {
+        None
+    };
+    identity.into_iter().chain(
+        INVERSE_CASE_FOLDING_TABLE[start..]
+            .iter()
+            .take_while(move |&&(to, _)| to == target)
+            .map(|&(_, from)| from),
+    )
+}
+
+/// The characters that full case folding changes, in code point order,
+/// each with what it folds to.
+pub fn case_folding_pairs() -> impl Iterator<Item = (char, &'static [char])> {
+    CASE_FOLDING_TABLE
+        .iter()
+        .map(|(from, to)| (*from, &to[..fold_len(to)]))
+}
+
+/// Return whether `c` is a lowercase Cherokee letter.
+///
+/// Unlike other scripts, Cherokee was encoded with uppercase letters first,
+/// and for stability case folding maps the lowercase letters added later
+/// (U+13F8 to U+13FD and U+AB70 to U+ABBF) to uppercase.
+pub fn is_cherokee_special(c: char) -> bool {
+    matches!(c, '\u{13F8}'..='\u{13FD}' | '\u{AB70}'..='\u{ABBF}')
+}
+
+/// Return whether `default_case_fold_str(s)` would return `s` unchanged,
+/// without allocating.
+///
+/// Case folded text is not necessarily lowercase:
+/// Cherokee characters fold to their uppercase forms.
+pub fn is_case_folded(s: &str) -> bool {
+    !s.chars().any(char_is_foldable)
+}
+
+/// The characters of `s`, in NFD only if that can change the result
+/// of case folding followed by NFD.
+///
+/// "Normalization is not required before case folding,
+///  except for the character U+0345 "combining greek ypogegrammeni"
+///  and any characters that have it as part of their canonical decomposition"
+/// Unicode Standard, section 3.13 Default Case Algorithms
+fn canonical_input(s: &str) -> CanonicalInput<'_> {
+    if s.chars()
+        .any(|c| NFD_BEFORE_FOLDING.binary_search(&c).is_ok())
+    {
+        CanonicalInput::Nfd(s.chars().nfd())
+    } else {
+        CanonicalInput::Chars(s.chars())
+    }
+}
+
+enum CanonicalInput<'a> {
+    Nfd(Decompositions<Chars<'a>>),
+    Chars(Chars<'a>),
+}
+
+impl<'a> Iterator for CanonicalInput<'a> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        match *self {
+            CanonicalInput::Nfd(ref mut chars) => chars.next(),
+            CanonicalInput::Chars(ref mut chars) => chars.next(),
+        }
+    }
+}
+
+fn iter_eq<L: Iterator, R: Iterator>(mut a: L, mut b: R) -> bool
+where
+    L::Item: PartialEq<R::Item>,
+{
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return true,
+            (None, _) | (_, None) => return false,
+            (Some(x), Some(y)) => {
+                if !x.eq(&y) {
+                    return false;
+                }
+            }
+        }
+    }
+}
+
+/// An iterator over the default case folding of a char iterator.
+///
+/// When the inner iterator is double-ended, so is `CaseFold`:
+/// iterating from the back yields the same characters as iterating
+/// from the front, in exactly the reverse order.
+/// This differs from folding a reversed iterator, which keeps the characters
+/// of each expansion in forward order.
+#[derive(Clone)]
+pub struct CaseFold<I> {
+    chars: I,
+    // The rest of the folding of the last char taken from the front.
+    queue: CharCaseFold,
+    // The rest of the folding of the last char taken from the back.
+    back_queue: CharCaseFold,
+    // The last non-ASCII char looked up and its folding,
+    // so that runs of the same char are looked up once.
+    last: (char, [char; 3]),
+}
+
+impl<I> CaseFold<I> {
+    fn lookup(&mut self, c: char) -> [char; 3] {
+        if self.last.0 != c {
+            self.last = (c, fold(c));
+        }
+        self.last.1
+    }
+}
+
+impl<I> Iterator for CaseFold<I>
+where
+    I: Iterator<Item = char>,
+{
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if let Some(c) = self.queue.next() {
+            return Some(c);
+        }
+        match self.chars.next() {
+            Some(c) => {
+                // ASCII characters never expand.
+                if c.is_ascii() {
+                    return Some(ASCII_CASE_FOLDING_TABLE[c as usize] as char);
+                }
+                self.queue = CharCaseFold::new(self.lookup(c));
+                self.queue.next()
+            }
+            None => self.back_queue.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        size_hint(&self.chars, self.queue.len() + self.back_queue.len())
+    }
+
+    fn nth(&mut self, mut n: usize) -> Option<char> {
+        if n < self.queue.len() {
+            return self.queue.nth(n);
+        }
+        n -= self.queue.len();
+        self.queue = CharCaseFold::empty();
+        while let Some(c) = self.chars.next() {
+            if c.is_ascii() {
+                if n == 0 {
+                    return Some(ASCII_CASE_FOLDING_TABLE[c as usize] as char);
+                }
+                n -= 1;
+                continue;
+            }
+            let folded = CharCaseFold::new(self.lookup(c));
+            if n < folded.len() {
+                self.queue = folded;
+                return self.queue.nth(n);
+            }
+            n -= folded.len();
+        }
+        self.back_queue.nth(n)
+    }
+
+    fn count(mut self) -> usize {
+        let mut count = self.queue.len() + self.back_queue.len();
+        while let Some(c) = self.chars.next() {
+            count += if c.is_ascii() {
+                1
+            } else {
+                fold_len(&self.lookup(c))
+            };
+        }
+        count
+    }
+}
+
+impl<I> DoubleEndedIterator for CaseFold<I>
+where
+    I: DoubleEndedIterator<Item = char>,
+{
+    fn next_back(&mut self) -> Option<char> {
+        if let Some(c) = self.back_queue.next_back() {
+            return Some(c);
+        }
+        match self.chars.next_back() {
+            Some(c) => {
+                if c.is_ascii() {
+                    return Some(ASCII_CASE_FOLDING_TABLE[c as usize] as char);
+                }
+                self.back_queue = CharCaseFold::new(self.lookup(c));
+                self.back_queue.next_back()
+            }
+            None => self.queue.next_back(),
+        }
+    }
+}
+
+impl<I: FusedIterator<Item = char>> FusedIterator for CaseFold<I> {}
+
+/// Shows only the occupied queue slots.
+impl<I: fmt::Debug> fmt::Debug for CaseFold<I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CaseFold")
+            .field("chars", &self.chars)
+            .field("queue", &self.queue.as_slice())
+            .field("back_queue", &self.back_queue.as_slice())
+            .finish()
+    }
+}
+
+#[derive(Clone)]
+pub struct TurkicCaseFold<I> {
+    chars: I,
+    queue: CharCaseFold,
+}
+
+impl<I> Iterator for TurkicCaseFold<I>
+where
+    I: Iterator<Item = char>,
+{
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if let Some(c) = self.queue.next() {
+            return Some(c);
+        }
+        let c = self.chars.next()?;
+        let folded = search(TURKIC_CASE_FOLDING_TABLE, c).unwrap_or_else(|| fold(c));
+        self.queue = CharCaseFold::new(folded);
+        self.queue.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        size_hint(&self.chars, self.queue.len())
+    }
+}
+
+impl<I: FusedIterator<Item = char>> FusedIterator for TurkicCaseFold<I> {}
+
+impl<I: fmt::Debug> fmt::Debug for TurkicCaseFold<I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TurkicCaseFold")
+            .field("chars", &self.chars)
+            .field("queue", &self.queue.as_slice())
+            .finish()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SimpleCaseFold<I> {
+    chars: I,
+}
+
+impl<I> Iterator for SimpleCaseFold<I>
+where
+    I: Iterator<Item = char>,
+{
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        self.chars.next().map(simple_fold)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chars.size_hint()
+    }
+}
+
+impl<I: FusedIterator<Item = char>> FusedIterator for SimpleCaseFold<I> {}
+
+/// The characters that `canonical_caseless_match` compares:
+/// NFD, then default case folding, then NFD again.
+#[derive(Clone)]
+pub struct CanonicalCaseFold<I> {
+    chars: Decompositions<CaseFold<Decompositions<I>>>,
+}
+
+impl<I> Iterator for CanonicalCaseFold<I>
+where
+    I: Iterator<Item = char>,
+{
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        self.chars.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chars.size_hint()
+    }
+}
+
+impl<I: FusedIterator<Item = char>> FusedIterator for CanonicalCaseFold<I> {}
+
+/// The characters that `compatibility_caseless_match` compares:
+/// NFD, default case folding, NFKD, default case folding, then NFKD again.
+#[derive(Clone)]
+pub struct CompatibilityCaseFold<I> {
+    chars: Decompositions<CaseFold<Decompositions<CaseFold<Decompositions<I>>>>>,
+}
+
+impl<I> Iterator for CompatibilityCaseFold<I>
+where
+    I: Iterator<Item = char>,
+{
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        self.chars.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chars.size_hint()
+    }
+}
+
+impl<I: FusedIterator<Item = char>> FusedIterator for CompatibilityCaseFold<I> {}
+
+#[derive(Clone)]
+pub struct CharCaseFold {
+    folded: [char; 3],
+    start: usize,
+    end: usize,
+}
+
+impl CharCaseFold {
+    fn new(folded: [char; 3]) -> CharCaseFold {
+        CharCaseFold {
+            folded,
+            start: 0,
+            end: fold_len(&folded),
+        }
+    }
+
+    fn empty() -> CharCaseFold {
+        CharCaseFold {
+            folded: ['\0'; 3],
+            start: 0,
+            end: 0,
+        }
+    }
+
+    fn as_slice(&self) -> &[char] {
+        &self.folded[self.start..self.end]
+    }
+}
+
+impl Iterator for CharCaseFold {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if self.start == self.end {
+            return None;
+        }
+        self.start += 1;
+        Some(self.folded[self.start - 1])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.start;
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for CharCaseFold {
+    fn next_back(&mut self) -> Option<char> {
+        if self.start == self.end {
+            return None;
+        }
+        self.end -= 1;
+        Some(self.folded[self.end])
+    }
+}
+
+impl ExactSizeIterator for CharCaseFold {}
+
+impl FusedIterator for CharCaseFold {}
+
+/// Shows the characters not yet yielded.
+impl fmt::Debug for CharCaseFold {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("CharCaseFold")
+            .field(&self.as_slice())
+            .finish()
+    }
+}
+
+/// Yields each character with the characters its default case folding gives,
+/// to map positions in folded text back to the original.
+#[derive(Clone, Debug)]
+pub struct CaseFoldWithSource<I> {
+    chars: I,
+}
+
+impl<I> Iterator for CaseFoldWithSource<I>
+where
+    I: Iterator<Item = char>,
+{
+    type Item = (char, CharCaseFold);
+
+    fn next(&mut self) -> Option<(char, CharCaseFold)> {
+        self.chars.next().map(|c| (c, case_fold_char(c)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chars.size_hint()
+    }
+}
+
+impl<I> DoubleEndedIterator for CaseFoldWithSource<I>
+where
+    I: DoubleEndedIterator<Item = char>,
+{
+    fn next_back(&mut self) -> Option<(char, CharCaseFold)> {
+        self.chars.next_back().map(|c| (c, case_fold_char(c)))
+    }
+}
+
+impl<I: FusedIterator<Item = char>> FusedIterator for CaseFoldWithSource<I> {}
+
+/// The default case folding of a string, with the byte offset in the string
+/// of the character each folded character comes from, like `str::char_indices`.
+///
+/// All the characters that a character folds to share its offset.
+#[derive(Clone, Debug)]
+pub struct CaseFoldIndices<'a> {
+    chars: CharIndices<'a>,
+    offset: usize,
+    folded: CharCaseFold,
+}
+
+impl<'a> CaseFoldIndices<'a> {
+    pub fn new(s: &'a str) -> CaseFoldIndices<'a> {
+        CaseFoldIndices {
+            chars: s.char_indices(),
+            offset: 0,
+            folded: CharCaseFold::empty(),
+        }
+    }
+}
+
+impl<'a> Iterator for CaseFoldIndices<'a> {
+    type Item = (usize, char);
+
+    fn next(&mut self) -> Option<(usize, char)> {
+        loop {
+            if let Some(c) = self.folded.next() {
+                return Some((self.offset, c));
+            }
+            let (offset, c) = self.chars.next()?;
+            self.offset = offset;
+            self.folded = case_fold_char(c);
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        size_hint(&self.chars, self.folded.len())
+    }
+}
+
+impl<'a> FusedIterator for CaseFoldIndices<'a> {}
+
+/// Folds text given in chunks, such as when decoding it as it arrives.
+///
+/// Since each character folds independently, the output for chunks
+/// is the same as for their concatenation.
+#[derive(Clone, Debug, Default)]
+pub struct StreamingCaseFold {
+    folded: String,
+}
+
+impl StreamingCaseFold {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fold `chunk` and add it to the output.
+    pub fn push_str(&mut self, chunk: &str) {
+        self.folded.extend(chunk.chars().default_case_fold());
+    }
+
+    /// Fold `c` and add it to the output.
+    pub fn push_char(&mut self, c: char) {
+        self.folded.extend(case_fold_char(c));
+    }
+
+    /// The output not yet drained.
+    pub fn as_str(&self) -> &str {
+        &self.folded
+    }
+
+    /// Remove and return the output so far.
+    pub fn drain(&mut self) -> Drain<'_> {
+        self.folded.drain(..)
+    }
+}
+
+/// The most characters that one character folds to.
+const MAX_FOLDED_CHARS: usize = 3;
+
+/// The full case folding of `c`, padded with NUL.
+fn fold(c: char) -> [char; 3] {
+    find(c).unwrap_or([c, '\0', '\0'])
+}
+
+/// The simple case folding of `c`.
+fn simple_fold(c: char) -> char {
+    match SIMPLE_CASE_FOLDING_TABLE.binary_search_by(|&(x, _)| x.cmp(&c)) {
+        Err(_) => c,
+        Ok(i) => SIMPLE_CASE_FOLDING_TABLE[i].1,
+    }
+}
+
+/// The entry for `c` in `CASE_FOLDING_TABLE`, found with a perfect hash function.
+#[cfg(not(feature = "compact-tables"))]
+fn find(c: char) -> Option<[char; 3]> {
+    let n = CASE_FOLDING_INDICES.len();
+    let salt = CASE_FOLDING_SALTS[phf_hash(c as u32, 0, n)];
+    let index = CASE_FOLDING_INDICES[phf_hash(c as u32, salt as u32, n)];
+    let (from, to) = CASE_FOLDING_TABLE[index as usize];
+    if from == c {
+        Some(to)
+    } else {
+        None
+    }
+}
+
+/// The entry for `c` in `CASE_FOLDING_TABLE`, found in the smaller
+/// `CASE_FOLDING_EXPANSIONS` and `CASE_FOLDING_RUNS` tables.
+#[cfg(feature = "compact-tables")]
+fn find(c: char) -> Option<[char; 3]> {
+    if let Some(folded) = search(CASE_FOLDING_EXPANSIONS, c) {
+        return Some(folded);
+    }
+    let i = CASE_FOLDING_RUNS.partition_point(|&(start, ..)| start <= c);
+    let (start, length, stride, delta) = CASE_FOLDING_RUNS[i.checked_sub(1)?];
+    let offset = c as u32 - start as u32;
+    if offset.is_multiple_of(stride as u32) && offset / (stride as u32) < length as u32 {
+        let folded = char::from_u32((c as i32 + delta) as u32).unwrap();
+        Some([folded, '\0', '\0'])
+    } else {
+        None
+    }
+}
+
+// Must be kept in sync with phf_hash in print-table
+#[cfg(not(feature = "compact-tables"))]
+fn phf_hash(key: u32, salt: u32, n: usize) -> usize {
+    let y = key.wrapping_add(salt).wrapping_mul(2654435769);
+    let y = y ^ key.wrapping_mul(0x31415926);
+    (((y as u64) * (n as u64)) >> 32) as usize
+}
+
+/// The entry for `c` in a sorted table.
+fn search(table: &[(char, [char; 3])], c: char) -> Option<[char; 3]> {
+    table
+        .binary_search_by(|&(x, _)| x.cmp(&c))
+        .ok()
+        .map(|i| table[i].1)
+}
+
+/// The number of characters in a table entry, which is padded with NUL.
+/// Only the first can be NUL, when folding NUL itself.
+fn fold_len(folded: &[char; 3]) -> usize {
+    if folded[1] == '\0' {
+        1
+    } else if folded[2] == '\0' {
+        2
+    } else {
+        3
+    }
+}
+
+/// Bounds for folding the rest of `chars` after `queue_len` queued characters.
+///
+/// Without looking at the characters, no upper bound is tighter than three
+/// per character: a string of `'ΐ'` folds to three times as many characters.
+/// The upper bound is only `None` when that product does not fit in `usize`,
+/// as `Iterator::size_hint` requires. The lower bound is never lost.
+fn size_hint<I: Iterator>(chars: &I, queue_len: usize) -> (usize, Option<usize>) {
+    let (low, high) = chars.size_hint();
+    (
+        low.saturating_add(queue_len),
+        high.and_then(|h| h.checked_mul(MAX_FOLDED_CHARS))
+            .and_then(|h| h.checked_add(queue_len)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strs() {
+        assert_eq!(default_case_fold_str("Test Case"), "test case");
+        assert_eq!(default_case_fold_str("Teſt Caſe"), "test case");
+        assert_eq!(default_case_fold_str("spiﬃest"), "spiffiest");
+        assert_eq!(default_case_fold_str("straße"), "strasse");
+    }
+
+    #[test]
+    fn test_turkic() {
+        assert_eq!(turkic_case_fold_str("I"), "\u{131}");
+        assert_eq!(turkic_case_fold_str("\u{130}"), "i");
+        assert_eq!(turkic_case_fold_str("Istanbul"), "\u{131}stanbul");
+        assert_eq!(default_case_fold_str("\u{130}"), "i\u{307}");
+        assert!(turkic_caseless_match_str("\u{130}stanbul", "istanbul"));
+        assert!(!turkic_caseless_match_str("I", "i"));
+        assert!(turkic_caseless_match_str("IĞDIR", "ığdır"));
+        assert!(!default_caseless_match_str("IĞDIR", "ığdır"));
+        assert_eq!(turkic_default_case_fold_str("IĞDIR"), "ığdır");
+    }
+
+    #[test]
+    fn test_simple() {
+        assert_eq!(simple_case_fold_str("Test Case"), "test case");
+        assert_eq!(simple_case_fold_str("straße"), "straße");
+        assert_eq!(simple_case_fold_str("STRA\u{1e9e}E"), "straße");
+        assert_eq!(simple_case_fold_str("spiﬃest"), "spiﬃest");
+        assert_eq!(simple_case_fold_str("\u{130}"), "\u{130}");
+        assert!(simple_caseless_match_str("STRA\u{1e9e}E", "straße"));
+        assert!(!simple_caseless_match_str("straße", "STRASSE"));
+    }
+
+    #[test]
+    fn test_char() {
+        assert_eq!(case_fold_char('\0').collect::<String>(), "\0");
+        assert_eq!(case_fold_char('a').collect::<String>(), "a");
+        assert_eq!(case_fold_char('A').collect::<String>(), "a");
+        assert_eq!(case_fold_char('ß').collect::<String>(), "ss");
+        assert_eq!(case_fold_char('ﬃ').collect::<String>(), "ffi");
+        assert_eq!(case_fold_char('ﬃ').len(), 3);
+        assert_eq!(case_fold_len('a'), 1);
+        assert_eq!(case_fold_len('A'), 1);
+        assert_eq!(case_fold_len('\0'), 1);
+        assert_eq!(case_fold_len('ß'), 2);
+        assert_eq!(case_fold_len('ﬃ'), 3);
+        assert_eq!(case_fold_char('ﬃ').rev().collect::<String>(), "iff");
+    }
+
+    #[test]
+    fn test_is_case_folded() {
+        assert!(is_case_folded(""));
+        assert!(is_case_folded("test case"));
+        assert!(!is_case_folded("Test case"));
+        assert!(!is_case_folded("straße"));
+        assert!(is_case_folded("\u{13a0}"));
+        assert!(!is_case_folded("\u{ab70}"));
+    }
+
+    #[test]
+    fn test_into() {
+        let mut out = String::from("leftover");
+        default_case_fold_into("Straße", &mut out);
+        assert_eq!(out, "strasse");
+        default_case_fold_into("", &mut out);
+        assert_eq!(out, "");
+    }
+
+    #[test]
+    fn test_cow() {
+        assert!(matches!(
+            default_case_fold_cow("test case"),
+            Cow::Borrowed("test case")
+        ));
+        match default_case_fold_cow("test Straße") {
+            Cow::Owned(s) => assert_eq!(s, "test strasse"),
+            Cow::Borrowed(_) => panic!("expected an owned string"),
+        }
+    }
+
+    #[test]
+    fn test_cmp() {
+        assert_eq!(caseless_cmp("Straße", "STRASSE"), Ordering::Equal);
+        assert_eq!(caseless_cmp("apple", "Banana"), Ordering::Less);
+        assert_eq!(caseless_cmp("Zebra", "apple"), Ordering::Greater);
+        assert_eq!(caseless_cmp("abc", "ABCD"), Ordering::Less);
+        let mut v = vec!["b", "C", "A"];
+        v.sort_by(|a, b| caseless_cmp(a, b));
+        assert_eq!(v, ["A", "b", "C"]);
+    }
+
+    #[test]
+    fn test_canonical_cmp() {
+        assert_eq!(
+            canonical_caseless_cmp("\u{c5}", "a\u{30a}"),
+            Ordering::Equal
+        );
+        assert_eq!(caseless_cmp("\u{c5}", "a\u{30a}"), Ordering::Greater);
+        assert_eq!(canonical_caseless_cmp("\u{c5}", "\u{c6}"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_ascii() {
+        for b in 0..0x80u8 {
+            let c = b as char;
+            let folded: String = Some(c).into_iter().default_case_fold().collect();
+            let expected: String = case_fold_char(c).collect();
+            assert_eq!(folded, expected);
+        }
+    }
+
+    #[test]
+    fn test_fused() {
+        fn assert_fused<I: FusedIterator>(_: &I) {}
+        let mut fold = "ß".chars().default_case_fold();
+        assert_fused(&fold);
+        assert_eq!(fold.by_ref().collect::<String>(), "ss");
+        assert_eq!(fold.next(), None);
+        assert_eq!(fold.next(), None);
+    }
+
+    #[test]
+    fn test_rev() {
+        let rev = |s: &str| s.chars().default_case_fold().rev().collect::<String>();
+        assert_eq!(rev("Straße"), "essarts");
+        assert_eq!(rev("ﬃX"), "xiff");
+        assert_eq!(rev(""), "");
+
+        // Alternate between both ends.
+        let mut fold = "ßAﬃ".chars().default_case_fold();
+        assert_eq!(fold.next_back(), Some('i'));
+        assert_eq!(fold.next(), Some('s'));
+        assert_eq!(fold.size_hint(), (4, Some(6)));
+        assert_eq!(fold.next_back(), Some('f'));
+        assert_eq!(fold.next_back(), Some('f'));
+        assert_eq!(fold.next_back(), Some('a'));
+        assert_eq!(fold.next_back(), Some('s'));
+        assert_eq!(fold.next_back(), None);
+        assert_eq!(fold.next(), None);
+
+        let mut fold = "ﬃ".chars().default_case_fold();
+        assert_eq!(fold.next_back(), Some('i'));
+        assert_eq!(fold.next(), Some('f'));
+        assert_eq!(fold.next(), Some('f'));
+        assert_eq!(fold.next(), None);
+        assert_eq!(fold.next_back(), None);
+    }
+
+    #[test]
+    fn test_ascii_table() {
+        for b in 0..0x80u8 {
+            let c = b as char;
+            assert_eq!(
+                ASCII_CASE_FOLDING_TABLE[b as usize] as char,
+                c.to_ascii_lowercase()
+            );
+            assert_eq!(fold(c), [c.to_ascii_lowercase(), '\0', '\0']);
+        }
+    }
+
+    #[test]
+    fn test_nul() {
+        let s = "\0ß\0ﬃ\0";
+        let folded = "\0ss\0ffi\0";
+        assert_eq!(s.chars().default_case_fold().collect::<String>(), folded);
+        let rev = s.chars().default_case_fold().rev().collect::<String>();
+        assert_eq!(rev, folded.chars().rev().collect::<String>());
+        assert_eq!(s.chars().turkic_case_fold().collect::<String>(), folded);
+        let mut fold = "ß\0".chars().default_case_fold();
+        assert_eq!(fold.next(), Some('s'));
+        assert_eq!(fold.next_back(), Some('\0'));
+        assert_eq!(fold.next_back(), Some('s'));
+        assert_eq!(fold.next(), None);
+    }
+
+    #[test]
+    fn test_nth() {
+        let s = "Aßΐxﬃ€ẞ";
+        let all = s.chars().default_case_fold().collect::<Vec<_>>();
+        for n in 0..all.len() + 2 {
+            let mut fold = s.chars().default_case_fold();
+            assert_eq!(fold.nth(n), all.get(n).cloned());
+            assert!(fold.eq(all.iter().skip(n + 1).cloned()));
+        }
+        // With characters queued at both ends.
+        let mut fold = s.chars().default_case_fold();
+        assert_eq!(fold.nth(1), Some('s'));
+        assert_eq!(fold.next_back(), Some('s'));
+        assert_eq!(fold.nth(1), Some('ι'));
+        assert_eq!(fold.nth(1), Some('\u{301}'));
+        assert_eq!(fold.nth(5), Some('s'));
+        assert_eq!(fold.next(), None);
+    }
+
+    #[test]
+    fn test_count() {
+        let strings = ["", "abc", "Straße", "ﬃΐẞ€", "ǅǆǄ\0ﬃﬃ"];
+        for s in &strings {
+            let fold = s.chars().default_case_fold();
+            assert_eq!(fold.clone().count(), fold.collect::<Vec<_>>().len());
+        }
+        let mut fold = "ßAﬃ".chars().default_case_fold();
+        fold.next();
+        fold.next_back();
+        assert_eq!(fold.count(), 4);
+    }
+
+    #[test]
+    fn test_canonical_case_fold_fn() {
+        for s in &["Straße", "\u{1F80}", "Å", "a\u{30a}\u{345}", "ΐ\u{323}"] {
+            assert!(canonical_case_fold(s.chars()).eq(canonical_case_fold_str(s).chars()));
+        }
+        let mut fold = canonical_case_fold("ä".chars());
+        assert_eq!(fold.next(), Some('a'));
+        assert_eq!(fold.next(), Some('\u{308}'));
+        assert_eq!(fold.next(), None);
+        assert_eq!(fold.next(), None);
+    }
+
+    #[test]
+    fn test_repeated_chars() {
+        // Runs of the same char, where the last lookup is reused.
+        let s = "ßßßﬃﬃẞẞσσΣ___ǅǅ".repeat(3);
+        let expected = s.chars().flat_map(case_fold_char).collect::<String>();
+        assert_eq!(s.chars().default_case_fold().collect::<String>(), expected);
+        let rev = s.chars().default_case_fold().rev().collect::<String>();
+        assert_eq!(rev, expected.chars().rev().collect::<String>());
+    }
+
+    #[test]
+    fn test_canonical_input() {
+        fn check(s: &str) {
+            let double_nfd: String = s.chars().nfd().default_case_fold().nfd().collect();
+            let optimized: String = canonical_input(s).default_case_fold().nfd().collect();
+            assert_eq!(optimized, double_nfd, "{:?}", s);
+        }
+
+        // U+1FC3 GREEK SMALL LETTER ETA WITH YPOGEGRAMMENI
+        check("\u{1fc3}");
+        check("\u{1fc3}\u{301}");
+        check("\u{3b7}\u{345}\u{301}");
+        assert!(canonical_caseless_match_str(
+            "\u{1fc3}\u{301}",
+            "\u{397}\u{301}\u{399}"
+        ));
+        assert_ne!(
+            caseless_cmp("\u{1fc3}\u{301}", "\u{397}\u{301}\u{399}"),
+            Ordering::Equal
+        );
+
+        for c in (0..=char::MAX as u32).filter_map(char::from_u32) {
+            if NFD_BEFORE_FOLDING.binary_search(&c).is_err() {
+                let mut buf = [0; 4];
+                check(c.encode_utf8(&mut buf));
+            }
+        }
+
+        // Pseudo-random strings of characters with interesting foldings
+        // or canonical combining classes.
+        let pool = [
+            'a', 'A', 'ß', 'ﬃ', '\u{c5}', '\u{30a}', '\u{301}', '\u{308}', '\u{323}', '\u{345}',
+            '\u{399}', '\u{3b9}', '\u{390}', '\u{1fc3}', '\u{1fbc}', '\u{1f80}', '\u{1e9e}',
+            '\u{130}', '\u{1c5}',
+        ];
+        let mut state: u32 = 1;
+        for _ in 0..2000 {
+            let mut s = String::new();
+            for _ in 0..6 {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                s.push(pool[(state >> 16) as usize % pool.len()]);
+            }
+            check(&s);
+        }
+    }
+
+    #[test]
+    fn test_chars_folding_to() {
+        let folding_to = |c| chars_folding_to(c).collect::<String>();
+        assert_eq!(folding_to('k'), "kK\u{212a}");
+        assert_eq!(folding_to('i'), "iI");
+        assert_eq!(folding_to('s'), "sS\u{17f}");
+        assert_eq!(folding_to('\u{3b9}'), "\u{3b9}\u{345}\u{399}\u{1fbe}");
+        assert_eq!(folding_to('K'), "");
+        assert_eq!(folding_to('1'), "1");
+    }
+
+    #[test]
+    fn test_bytes() {
+        assert_eq!(
+            default_case_fold_bytes("Straße".as_bytes()).unwrap(),
+            "strasse"
+        );
+        assert!(default_case_fold_bytes(b"\xff").is_err());
+        assert!(default_caseless_match_bytes(
+            b"Content-Type",
+            b"content-type"
+        ));
+        assert!(!default_caseless_match_bytes(b"\xff", b"\xff"));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_os() {
+        assert!(caseless_match_os(
+            OsStr::new("README.md"),
+            OsStr::new("readme.MD")
+        ));
+        assert!(!caseless_match_os(
+            OsStr::new("README.md"),
+            OsStr::new("readme.txt")
+        ));
+    }
+
+    #[cfg(all(feature = "std", unix))]
+    #[test]
+    fn test_os_invalid() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let invalid = OsStr::from_bytes(b"A\xff");
+        assert!(caseless_match_os(invalid, invalid));
+        assert!(!caseless_match_os(invalid, OsStr::from_bytes(b"a\xff")));
+        assert!(!caseless_match_os(invalid, OsStr::from_bytes(b"A\xfe")));
+    }
+
+    #[test]
+    fn test_unicode_version() {
+        // U+1C90 GEORGIAN MTAVRULI CAPITAL LETTER AN was added in Unicode 11.0.
+        let s = "Ab\u{1c90}";
+        assert_eq!(
+            default_case_fold_str_with(UnicodeVersion::Latest, s),
+            "ab\u{10d0}"
+        );
+        #[cfg(feature = "unicode-10")]
+        assert_eq!(
+            default_case_fold_str_with(UnicodeVersion::V10_0_0, s),
+            "ab\u{1c90}"
+        );
+    }
+
+    #[test]
+    fn test_chars() {
+        assert_eq!(
+            default_case_fold_chars("Straße"),
+            ['s', 't', 'r', 'a', 's', 's', 'e']
+        );
+        assert!(default_case_fold_chars("").is_empty());
+    }
+
+    #[test]
+    fn test_find() {
+        for c in (0..=char::MAX as u32).filter_map(char::from_u32) {
+            assert_eq!(find(c), search(CASE_FOLDING_TABLE, c));
+        }
+    }
+
+    #[test]
+    fn test_sort_key() {
+        let strings = ["b", "Straße", "\u{c5}", "a\u{30a}", "STRASSE", "A", "ﬃ"];
+        for a in &strings {
+            for b in &strings {
+                assert_eq!(
+                    caseless_sort_key(a).cmp(&caseless_sort_key(b)),
+                    caseless_cmp(a, b)
+                );
+                assert_eq!(
+                    canonical_caseless_sort_key(a).cmp(&canonical_caseless_sort_key(b)),
+                    canonical_caseless_cmp(a, b)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_canonical_fold() {
+        assert_eq!(canonical_case_fold_str("\u{c5}"), "a\u{30a}");
+        assert_eq!(
+            canonical_case_fold_str("\u{1fc3}\u{301}"),
+            "\u{3b7}\u{301}\u{3b9}"
+        );
+        assert_eq!(
+            canonical_case_fold_str("\u{212b}"),
+            canonical_case_fold_str("\u{e5}")
+        );
+    }
+
+    #[test]
+    fn test_compatibility_fold() {
+        assert_eq!(compatibility_case_fold_str("\u{3392}"), "mhz");
+        assert_eq!(compatibility_case_fold_str("ﬃ"), "ffi");
+        assert_eq!(compatibility_case_fold_str("\u{2160}"), "i");
+        assert!(compatibility_caseless_match_str("\u{3392}", "MHz"));
+    }
+
+    #[test]
+    fn test_ext() {
+        assert_eq!("Straße".case_fold(), "strasse");
+        assert!("Straße".caseless_eq("STRASSE"));
+        assert!(!"Straße".caseless_eq("STRASE"));
+        assert!("\u{c5}".canonical_caseless_eq("a\u{30a}"));
+        assert!("\u{3392}".compatibility_caseless_eq("MHZ"));
+        assert!(String::from("A").caseless_eq("a"));
+        assert!(CaselessString::from("Maße").caseless_eq("MASSE"));
+        assert!("Straße".eq_ignore_case("STRASSE"));
+        assert!(eq_ignore_case("ǅ", "ǆ"));
+        assert!(!eq_ignore_case("ǅ", "dz"));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_hash() {
+        use std::collections::hash_map::DefaultHasher;
+
+        let hash = |s| {
+            let mut hasher = DefaultHasher::new();
+            caseless_hash(s, &mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash("Straße"), hash("STRASSE"));
+        assert_ne!(hash("Straße"), hash("STRASE"));
+    }
+
+    #[test]
+    fn test_any_match() {
+        let keywords = ["select", "from", "where"];
+        assert!(any_caseless_match("FROM", keywords.iter().cloned()));
+        assert!(!any_caseless_match("FRO", keywords.iter().cloned()));
+        assert_eq!(
+            find_caseless_match("Where", keywords.iter().cloned()),
+            Some((2, "where"))
+        );
+        assert_eq!(find_caseless_match("x", keywords.iter().cloned()), None);
+    }
+
+    #[test]
+    fn test_chars_caseless_eq() {
+        assert!(chars_caseless_eq('a', 'A'));
+        assert!(chars_caseless_eq('K', '\u{212A}'));
+        assert!(chars_caseless_eq('ſ', 'S'));
+        assert!(chars_caseless_eq('ﬃ', 'ﬃ'));
+        assert!(!chars_caseless_eq('ﬃ', 'f'));
+        assert!(!chars_caseless_eq('a', 'b'));
+        assert!(chars_caseless_eq('\0', '\0'));
+    }
+
+    #[test]
+    fn test_streaming() {
+        let mut folder = StreamingCaseFold::new();
+        folder.push_str("STRA");
+        folder.push_char('ß');
+        assert_eq!(folder.drain().collect::<String>(), "strass");
+        folder.push_str("E ");
+        folder.push_str("ﬃ");
+        assert_eq!(folder.as_str(), "e ffi");
+        assert_eq!(folder.drain().collect::<String>(), "e ffi");
+        assert_eq!(folder.as_str(), "");
+    }
+
+    #[test]
+    fn test_size_hint() {
+        let worst = "ΐΐΐ".chars().default_case_fold();
+        assert_eq!(worst.size_hint(), (2, Some(18)));
+        assert_eq!(worst.count(), 9);
+
+        let mut fold = "ßa".chars().default_case_fold();
+        fold.next();
+        assert_eq!(fold.size_hint(), (2, Some(4)));
+
+        let huge = core::iter::repeat_n('a', usize::MAX / 4);
+        let hint = huge.default_case_fold().size_hint();
+        assert_eq!(hint, (usize::MAX / 4, Some(usize::MAX / 4 * 3)));
+        let too_huge = core::iter::repeat_n('a', usize::MAX / 2);
+        assert_eq!(
+            too_huge.default_case_fold().size_hint(),
+            (usize::MAX / 2, None)
+        );
+    }
+
+    #[test]
+    fn test_canonical_case_fold() {
+        let folded = "\u{1FC3}ΐ"
+            .chars()
+            .canonical_case_fold()
+            .collect::<String>();
+        assert_eq!(folded, canonical_case_fold_str("\u{1FC3}ΐ"));
+        assert_eq!(folded, "\u{3B7}\u{3B9}\u{3B9}\u{308}\u{301}");
+        assert!("\u{1FC3}"
+            .chars()
+            .canonical_case_fold()
+            .eq("\u{397}\u{345}".chars().canonical_case_fold()));
+    }
+
+    #[test]
+    fn test_case_folding_pairs() {
+        let pairs = case_folding_pairs().collect::<Vec<_>>();
+        assert_eq!(pairs.len(), CASE_FOLDING_TABLE.len());
+        assert_eq!(pairs[0], ('A', &['a'][..]));
+        assert!(pairs.contains(&('ß', &['s', 's'][..])));
+        assert!(pairs.contains(&('ΐ', &['ι', '\u{308}', '\u{301}'][..])));
+        for (from, to) in pairs {
+            assert!(case_fold_char(from).eq(to.iter().cloned()));
+        }
+    }
+
+    #[test]
+    fn test_char_is_foldable() {
+        assert!(char_is_foldable('A'));
+        assert!(char_is_foldable('ß'));
+        assert!(char_is_foldable('\u{13F8}'));
+        assert!(!char_is_foldable('a'));
+        assert!(!char_is_foldable('\u{13A0}'));
+        assert!(!char_is_foldable('\0'));
+    }
+
+    #[test]
+    fn test_default_caseless_cmp() {
+        assert_eq!(
+            "Straße".chars().default_caseless_cmp("STRASSE".chars()),
+            Ordering::Equal
+        );
+        assert_eq!(
+            "a".chars().default_caseless_cmp("B".chars()),
+            Ordering::Less
+        );
+        let key = default_case_fold_str("Key");
+        assert_eq!(
+            key.chars().default_caseless_cmp(['K', 'E'].iter().cloned()),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn test_canonical_caseless_sort() {
+        let mut v = vec!["b", "\u{c5}", "a", "A\u{30a}", "\u{212b}", "B", "\u{e5}"];
+        canonical_caseless_sort(&mut v);
+        assert_eq!(
+            v,
+            ["a", "\u{c5}", "A\u{30a}", "\u{212b}", "\u{e5}", "b", "B"]
+        );
+        let mut v = vec![String::from("Z"), String::from("y")];
+        canonical_caseless_sort(&mut v);
+        assert_eq!(v, ["y", "Z"]);
+    }
+
+    #[test]
+    fn test_in_place() {
+        let mut s = String::from("Straße IS ok");
+        default_case_fold_in_place(&mut s);
+        assert_eq!(s, "strasse is ok");
+        let mut s = String::with_capacity(32);
+        s.push_str("already folded");
+        let ptr = s.as_ptr();
+        default_case_fold_in_place(&mut s);
+        assert_eq!(s, "already folded");
+        assert_eq!(s.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn test_case_folded_len() {
+        for s in &["", "Hello", "Straße", "ﬃ", "\u{130}", "ᏣᎳᎩ", "Ⰰΐẞ"] {
+            assert_eq!(case_folded_len(s), default_case_fold_str(s).len());
+        }
+        assert_eq!(case_folded_len("ﬃ"), 3);
+        assert_eq!("ﬃ".len(), 3);
+        assert_eq!(case_folded_len("ẞ"), 2);
+    }
+
+    #[test]
+    fn test_case_fold_with_source() {
+        let pairs = "Aßc"
+            .chars()
+            .case_fold_with_source()
+            .map(|(c, folded)| (c, folded.collect::<String>()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pairs,
+            [
+                ('A', "a".to_string()),
+                ('ß', "ss".to_string()),
+                ('c', "c".to_string())
+            ]
+        );
+        let lens = "ﬃx".chars().case_fold_with_source().rev();
+        let lens = lens.map(|(_, folded)| folded.len()).collect::<Vec<_>>();
+        assert_eq!(lens, [1, 3]);
+    }
+
+    #[test]
+    fn test_canonical_caseless_match_nfc() {
+        let strings = [
+            "\u{c5}",
+            "a\u{30a}",
+            "\u{212b}",
+            "A",
+            "\u{1fc3}",
+            "\u{397}\u{345}",
+            "\u{3b7}\u{3b9}",
+            "ΐ",
+            "\u{399}\u{308}\u{301}",
+            "ǰ",
+            "J\u{30c}",
+            "Straße",
+        ];
+        for a in &strings {
+            for b in &strings {
+                assert_eq!(
+                    canonical_caseless_match_nfc(a, b),
+                    canonical_caseless_match_str(a, b),
+                    "{:?} {:?}",
+                    a,
+                    b
+                );
+            }
+        }
+        assert!(canonical_caseless_match_nfc("\u{c5}", "a\u{30a}"));
+        assert!(!canonical_caseless_match_nfc("\u{c5}", "a"));
+    }
+
+    #[test]
+    fn test_case_fold_indices() {
+        let indices = CaseFoldIndices::new("AßΣ").collect::<Vec<_>>();
+        assert_eq!(indices, [(0, 'a'), (1, 's'), (1, 's'), (3, 'σ')]);
+        assert_eq!(CaseFoldIndices::new("").next(), None);
+        let s = "x\u{130}ﬃ";
+        assert!(CaseFoldIndices::new(s)
+            .map(|(_, c)| c)
+            .eq(s.chars().default_case_fold()));
+    }
+
+    #[test]
+    fn test_cherokee() {
+        // Guards against a data update folding Cherokee to lowercase.
+        let chars = |range: core::ops::RangeInclusive<u32>| range.filter_map(char::from_u32);
+        for c in chars(0x13A0..=0x13FF).chain(chars(0xAB70..=0xABBF)) {
+            let folded = case_fold_char(c).collect::<Vec<_>>();
+            let expected = match c as u32 {
+                0x13F8..=0x13FD => c as u32 - 8,
+                0xAB70..=0xABBF => c as u32 - 0xAB70 + 0x13A0,
+                _ => c as u32,
+            };
+            assert_eq!(folded, [char::from_u32(expected).unwrap()], "{:?}", c);
+            assert_eq!(is_cherokee_special(c), expected != c as u32, "{:?}", c);
+            if is_cherokee_special(c) {
+                assert!(c.to_uppercase().eq(folded.iter().cloned()));
+            }
+        }
+        assert_eq!(default_case_fold_str("ꮳꮃꭹ"), "ᏣᎳᎩ");
+        assert!(is_case_folded("ᏣᎳᎩ"));
+        assert!(!is_cherokee_special('a'));
+    }
+
+    #[test]
+    fn test_case_fold_capacity_hint() {
+        for c in (0..=char::MAX as u32).filter_map(char::from_u32) {
+            let folded = case_fold_char(c).map(char::len_utf8).sum::<usize>();
+            assert!(folded <= MAX_FOLDED_CHARS * c.len_utf8(), "{:?}", c);
+        }
+        let s = "ΐΰ Straße";
+        let mut out = String::with_capacity(case_fold_capacity_hint(s));
+        let capacity = out.capacity();
+        default_case_fold_into(s, &mut out);
+        assert_eq!(out.capacity(), capacity);
+        assert_eq!(case_fold_capacity_hint("ΐ"), case_folded_len("ΐ"));
+    }
+
+    #[test]
+    fn test_caseless_eq_chars() {
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        assert!(caseless_eq_chars(&chars("Straße"), &chars("STRASSE")));
+        assert!(caseless_eq_chars(&['ﬃ'], &['F', 'f', 'I']));
+        assert!(!caseless_eq_chars(&['ﬃ'], &['f', 'f']));
+        assert!(!caseless_eq_chars(&['ß'], &['s']));
+        assert!(caseless_eq_chars(&[], &[]));
+        assert!(caseless_match_chars(&['Ä'], &['ä']));
+        let buffer: Vec<char> = "ǄEMAL".chars().collect();
+        assert!(caseless_match_chars(&buffer, &chars("ǆemal")));
+    }
+
+    #[test]
+    fn test_compatibility_case_fold() {
+        for s in &["\u{3392}", "ﬃ", "\u{2165}", "\u{1FC3}", "Straße"] {
+            assert_eq!(
+                s.chars().compatibility_case_fold().collect::<String>(),
+                compatibility_case_fold_str(s)
+            );
+        }
+        assert!("\u{3392}"
+            .chars()
+            .compatibility_case_fold()
+            .eq("mhz".chars()));
+    }
+
+    #[test]
+    fn test_fs_caseless_key() {
+        assert_eq!(fs_caseless_key("README.md"), fs_caseless_key("readme.MD"));
+        assert_ne!(fs_caseless_key("Caf\u{e9}"), fs_caseless_key("cafe\u{301}"));
+        assert_eq!(
+            fs_caseless_key_nfd("Caf\u{e9}"),
+            fs_caseless_key_nfd("CAFE\u{301}")
+        );
+        assert_ne!(
+            fs_caseless_key_nfd("Cafe"),
+            fs_caseless_key_nfd("Caf\u{e9}")
+        );
+    }
+
+    #[test]
+    fn test_loose_caseless_match() {
+        assert!(loose_caseless_match_str("café", "CAFE"));
+        assert!(loose_caseless_match_str("Ångström", "angstrom"));
+        // U+0345 is a combining mark, but folds to a letter.
+        assert!(loose_caseless_match_str("\u{1FC3}", "ΗΙ"));
+        assert!(!loose_caseless_match_str("café", "cafes"));
+        let acute_only = |c| c == '\u{301}';
+        assert!(loose_caseless_match_str_with("CAFÉ", "cafe", acute_only));
+        assert!(!loose_caseless_match_str_with("Å", "a", acute_only));
+    }
+
+    #[test]
+    fn test_to_canonical_caseless() {
+        let forms = [
+            "\u{c5}ngstr\u{f6}m",
+            "A\u{30a}NGSTRO\u{308}M",
+            "\u{212b}ngstr\u{f6}m",
+        ];
+        for s in &forms {
+            assert_eq!(to_canonical_caseless(s), "a\u{30a}ngstro\u{308}m");
+        }
+        assert_ne!(
+            to_canonical_caseless("angstrom"),
+            to_canonical_caseless(forms[0])
+        );
+    }
+
+    #[test]
+    fn test_dedup_caseless() {
+        let mut v = vec![
+            String::from("Foo"),
+            String::from("BAR"),
+            String::from("foo"),
+        ];
+        dedup_caseless(&mut v);
+        assert_eq!(v, ["Foo", "BAR"]);
+        let mut v = vec!["Straße", "x", "STRASSE", "\u{c5}", "a\u{30a}"];
+        dedup_caseless(&mut v);
+        assert_eq!(v, ["Straße", "x", "\u{c5}", "a\u{30a}"]);
+        canonical_dedup_caseless(&mut v);
+        assert_eq!(v, ["Straße", "x", "\u{c5}"]);
+    }
+
+    #[test]
+    fn test_caseless_collisions() {
+        let keys = [
+            "Timeout", "retries", "timeout", "Retries", "host", "TIMEOUT", "host",
+        ];
+        assert_eq!(
+            caseless_collisions(keys.iter().cloned()),
+            [
+                ("Timeout", "timeout"),
+                ("Timeout", "TIMEOUT"),
+                ("timeout", "TIMEOUT"),
+                ("retries", "Retries"),
+            ]
+        );
+        assert!(caseless_collisions(vec!["a", "b", "a"]).is_empty());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_caseless_group() {
+        let items = [
+            "Timeout", "retries", "timeout", "Straße", "Retries", "STRASSE", "host",
+        ];
+        assert_eq!(
+            par_caseless_group(&items),
+            [vec![0, 2], vec![1, 4], vec![3, 5], vec![6]]
+        );
+        let many = (0..1000)
+            .map(|i| format!("Key{}", i % 10))
+            .collect::<Vec<_>>();
+        let groups = par_caseless_group(&many);
+        assert_eq!(groups.len(), 10);
+        assert!(groups.iter().all(|g| g.len() == 100 && g[1] == g[0] + 10));
+        assert!(par_caseless_group::<&str>(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_char_fold() {
+        assert_eq!(char_fold('A'), (['a', '\0', '\0'], 1));
+        assert_eq!(char_fold('a'), (['a', '\0', '\0'], 1));
+        assert_eq!(char_fold('ß'), (['s', 's', '\0'], 2));
+        assert_eq!(char_fold('ΐ'), (['ι', '\u{308}', '\u{301}'], 3));
+        assert_eq!(char_fold('\0'), (['\0', '\0', '\0'], 1));
+    }
+
+    #[test]
+    fn test_caseless_ref() {
+        let a: Vec<char> = "Straße".chars().collect();
+        let b = ['s', 't', 'r', 'a', 's', 's', 'e'];
+        assert!(a.iter().default_case_fold().eq(b.iter().cloned()));
+        assert!(a.iter().default_caseless_match(b.iter()));
+        assert_eq!(a.iter().default_caseless_cmp(b.iter()), Ordering::Equal);
+        assert!(!a.iter().simple_caseless_match(b.iter()));
+        assert!(['I'].iter().turkic_case_fold().eq(Some('ı')));
+        assert!(['\u{c5}']
+            .iter()
+            .canonical_caseless_match(['a', '\u{30a}'].iter()));
+    }
+
+    #[test]
+    fn test_clone() {
+        let mut fold = "aßc".chars().default_case_fold();
+        assert_eq!(fold.next(), Some('a'));
+        assert_eq!(fold.next(), Some('s'));
+        let lookahead = fold.clone().collect::<String>();
+        assert_eq!(lookahead, "sc");
+        assert_eq!(fold.collect::<String>(), "sc");
+        let mut indices = CaseFoldIndices::new("ﬃ");
+        indices.next();
+        assert_eq!(indices.clone().count(), 2);
+        assert_eq!(indices.count(), 2);
+    }
+
+    #[test]
+    fn test_debug() {
+        let mut fold = "ßΐ".chars().default_case_fold();
+        assert_eq!(
+            format!("{:?}", fold),
+            "CaseFold { chars: Chars(['ß', 'ΐ']), queue: [], back_queue: [] }"
+        );
+        fold.next();
+        fold.next_back();
+        assert_eq!(
+            format!("{:?}", fold),
+            "CaseFold { chars: Chars([]), queue: ['s'], back_queue: ['ι', '\\u{308}'] }"
+        );
+        let mut char_fold = case_fold_char('ﬃ');
+        char_fold.next();
+        assert_eq!(format!("{:?}", char_fold), "CharCaseFold(['f', 'i'])");
+    }
+
+    #[cfg(feature = "bstr")]
+    #[test]
+    fn test_caseless_match_bstr() {
+        use bstr::BStr;
+
+        let m = |a: &[u8], b: &[u8]| caseless_match_bstr(BStr::new(a), BStr::new(b));
+        assert!(m("Straße".as_bytes(), b"STRASSE"));
+        assert!(m(b"A\xFF", b"a\xFF"));
+        assert!(m(b"\xFF\xFEK\xC3", b"\xFF\xFEk\xC3"));
+        assert!(!m(b"A\xFF", b"a\xFE"));
+        assert!(!m(b"A\xFF", "a\u{FFFD}".as_bytes()));
+        assert!(!m(b"A\xFF", b"a"));
+        assert!(!m(b"\xFFA", b"a\xFF"));
+    }
+
+    #[test]
+    fn test_caseless_match_str() {
+        let pairs = [
+            ("Straße", "STRASSE"),
+            ("\u{c5}", "a\u{30a}"),
+            ("\u{3392}", "MHZ"),
+        ];
+        let modes = [
+            MatchMode::Default,
+            MatchMode::Canonical,
+            MatchMode::Compatibility,
+        ];
+        let expected = [
+            [true, true, true],
+            [false, true, true],
+            [false, false, true],
+        ];
+        for (&(a, b), expected) in pairs.iter().zip(&expected) {
+            for (&mode, &expected) in modes.iter().zip(expected) {
+                assert_eq!(
+                    caseless_match_str(a, b, mode),
+                    expected,
+                    "{:?} {:?}",
+                    a,
+                    mode
+                );
+            }
+        }
+        assert_eq!(MatchMode::default(), MatchMode::Default);
     }
 }
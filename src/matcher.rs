@@ -0,0 +1,215 @@
+//! Searching for many patterns at once, with an Aho-Corasick automaton
+//! over default case foldings.
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::iter::FusedIterator;
+use core::ops::Range;
+use core::str::CharIndices;
+
+use super::{case_fold_char, Caseless};
+
+/// A set of patterns to find caselessly in many haystacks.
+///
+/// Matches are found as with `caseless_find_range`: they are made of whole
+/// characters of the haystack whose folding is equal to that of a pattern.
+/// Empty patterns never match.
+#[derive(Clone, Debug)]
+pub struct CaselessMatcher {
+    nodes: Vec<Node>,
+    max_len: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Node {
+    /// Transitions of the trie of folded patterns, sorted by character.
+    next: Vec<(char, usize)>,
+    /// The node for the longest proper suffix of this one that is in the trie.
+    fail: usize,
+    /// The index and folded length of the first pattern ending at this node.
+    pattern: Option<(usize, usize)>,
+    /// The nearest node by `fail` links where a pattern ends.
+    output: Option<usize>,
+}
+
+impl CaselessMatcher {
+    pub fn new<I>(patterns: I) -> CaselessMatcher
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut nodes = Vec::new();
+        nodes.push(Node::default());
+        let mut max_len = 0;
+        for (index, pattern) in patterns.into_iter().enumerate() {
+            let mut node = 0;
+            let mut len = 0;
+            for c in pattern.as_ref().chars().default_case_fold() {
+                node = match nodes[node].next.binary_search_by(|&(x, _)| x.cmp(&c)) {
+                    Ok(i) => nodes[node].next[i].1,
+                    Err(i) => {
+                        nodes.push(Node::default());
+                        let new = nodes.len() - 1;
+                        nodes[node].next.insert(i, (c, new));
+                        new
+                    }
+                };
+                len += 1;
+            }
+            if len > 0 && nodes[node].pattern.is_none() {
+                nodes[node].pattern = Some((index, len));
+                max_len = max_len.max(len);
+            }
+        }
+
+        // Breadth-first, so that fail links point to nodes already done.
+        let mut queue = nodes[0]
+            .next
+            .iter()
+            .map(|&(_, n)| n)
+            .collect::<VecDeque<_>>();
+        while let Some(node) = queue.pop_front() {
+            for i in 0..nodes[node].next.len() {
+                let (c, child) = nodes[node].next[i];
+                let mut fail = nodes[node].fail;
+                let fail = loop {
+                    if let Some(next) = transition(&nodes[fail], c) {
+                        break next;
+                    }
+                    if fail == 0 {
+                        break 0;
+                    }
+                    fail = nodes[fail].fail;
+                };
+                nodes[child].fail = fail;
+                nodes[child].output = if nodes[fail].pattern.is_some() {
+                    Some(fail)
+                } else {
+                    nodes[fail].output
+                };
+                queue.push_back(child);
+            }
+        }
+        CaselessMatcher { nodes, max_len }
+    }
+
+    /// Iterate over non-overlapping matches in `haystack`, yielding the index
+    /// of the matching pattern and the byte range of the match.
+    ///
+    /// Matches are reported as soon as they end, preferring the longest
+    /// pattern that ends there. Scanning resumes after the end of each match.
+    pub fn find_iter<'m, 'h>(&'m self, haystack: &'h str) -> CaselessMatcherFindIter<'m, 'h> {
+        CaselessMatcherFindIter {
+            matcher: self,
+            chars: haystack.char_indices(),
+            state: 0,
+            recent: VecDeque::with_capacity(self.max_len),
+        }
+    }
+
+    fn step(&self, mut state: usize, c: char) -> usize {
+        loop {
+            if let Some(next) = transition(&self.nodes[state], c) {
+                return next;
+            }
+            if state == 0 {
+                return 0;
+            }
+            state = self.nodes[state].fail;
+        }
+    }
+}
+
+fn transition(node: &Node, c: char) -> Option<usize> {
+    node.next
+        .binary_search_by(|&(x, _)| x.cmp(&c))
+        .ok()
+        .map(|i| node.next[i].1)
+}
+
+/// Matches of a `CaselessMatcher`, from `CaselessMatcher::find_iter`.
+#[derive(Clone, Debug)]
+pub struct CaselessMatcherFindIter<'m, 'h> {
+    matcher: &'m CaselessMatcher,
+    chars: CharIndices<'h>,
+    state: usize,
+    /// For the last folded characters, up to the longest pattern:
+    /// the byte offset of their original character,
+    /// and whether they are the first of its folding.
+    recent: VecDeque<(usize, bool)>,
+}
+
+impl<'m, 'h> Iterator for CaselessMatcherFindIter<'m, 'h> {
+    type Item = (usize, Range<usize>);
+
+    fn next(&mut self) -> Option<(usize, Range<usize>)> {
+        let nodes = &self.matcher.nodes;
+        for (i, c) in &mut self.chars {
+            for (k, folded) in case_fold_char(c).enumerate() {
+                self.state = self.matcher.step(self.state, folded);
+                if self.recent.len() == self.matcher.max_len {
+                    self.recent.pop_front();
+                }
+                self.recent.push_back((i, k == 0));
+            }
+            // Only matches ending at the end of a character's folding count.
+            let mut node = if nodes[self.state].pattern.is_some() {
+                Some(self.state)
+            } else {
+                nodes[self.state].output
+            };
+            while let Some(n) = node {
+                let (index, len) = nodes[n].pattern.unwrap();
+                let (start, starts_char) = self.recent[self.recent.len() - len];
+                if starts_char {
+                    self.state = 0;
+                    self.recent.clear();
+                    return Some((index, start..i + c.len_utf8()));
+                }
+                node = nodes[n].output;
+            }
+        }
+        None
+    }
+}
+
+impl<'m, 'h> FusedIterator for CaselessMatcherFindIter<'m, 'h> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use search::caseless_find_iter;
+
+    #[test]
+    fn test_matcher() {
+        let matcher = CaselessMatcher::new(&["SELECT", "from", "Straße", "he", "she", "hers"]);
+        let find = |h| matcher.find_iter(h).collect::<Vec<_>>();
+        assert_eq!(find("select * FROM t"), [(0, 0..6), (1, 9..13)]);
+        assert_eq!(find("STRASSE strasse"), [(2, 0..7), (2, 8..15)]);
+        assert_eq!(find("ushers"), [(4, 1..4)]);
+        assert_eq!(find("HERS"), [(3, 0..2)]);
+        assert_eq!(find("nothing"), []);
+        // "s" does not match half of a "ß".
+        let matcher = CaselessMatcher::new(&["s", "ffi"]);
+        assert_eq!(matcher.find_iter("ßﬃ").collect::<Vec<_>>(), [(1, 2..5)]);
+        assert_eq!(CaselessMatcher::new(&[""]).find_iter("abc").next(), None);
+    }
+
+    #[test]
+    fn test_matcher_single_pattern() {
+        let haystacks = ["Straße, STRASSE", "aaaa", "ﬃ ffi FFI", "ǅǆǄ dž", "xΐxΐ"];
+        let needles = ["strasse", "aa", "ffi", "dž", "ΐ", "a"];
+        for haystack in &haystacks {
+            for needle in &needles {
+                let matcher = CaselessMatcher::new(&[needle]);
+                let found = matcher.find_iter(haystack).map(|(_, range)| range);
+                assert!(
+                    found.eq(caseless_find_iter(haystack, needle)),
+                    "{:?} {:?}",
+                    haystack,
+                    needle
+                );
+            }
+        }
+    }
+}
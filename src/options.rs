@@ -0,0 +1,128 @@
+//! Case folding and caseless matching configured at run time.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use unicode_normalization::UnicodeNormalization;
+
+use super::case_folding_data::TURKIC_CASE_FOLDING_TABLE;
+use super::{case_fold_char, iter_eq, search, simple_fold, CharCaseFold};
+
+/// Normalization around case folding, as in `CaseFoldOptions::normalize`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Normalization {
+    /// Case folding only, as in `default_case_fold_str`.
+    #[default]
+    None,
+    /// NFD, case folding, then NFD, as in `canonical_case_fold_str`.
+    Canonical,
+    /// NFD, case folding, NFKD, case folding, then NFKD,
+    /// as in `compatibility_case_fold_str`.
+    Compatibility,
+}
+
+/// A case folding chosen at run time, for when the `*_str` function
+/// to call would depend on configuration.
+///
+/// The default is full case folding without Turkic mappings or normalization,
+/// like `default_case_fold_str`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CaseFoldOptions {
+    simple: bool,
+    turkic: bool,
+    normalization: Normalization,
+}
+
+impl CaseFoldOptions {
+    pub fn new() -> CaseFoldOptions {
+        CaseFoldOptions::default()
+    }
+
+    /// Use full case folding, where a character can fold to several: `ß` to `ss`.
+    pub fn full(mut self) -> CaseFoldOptions {
+        self.simple = false;
+        self
+    }
+
+    /// Use simple case folding, where each character folds to one, as in
+    /// `simple_case_fold_str`.
+    pub fn simple(mut self) -> CaseFoldOptions {
+        self.simple = true;
+        self
+    }
+
+    /// Whether to fold `I` to `ı` and `İ` to `i`, as in `turkic_case_fold_str`.
+    pub fn turkic(mut self, turkic: bool) -> CaseFoldOptions {
+        self.turkic = turkic;
+        self
+    }
+
+    pub fn normalize(mut self, normalization: Normalization) -> CaseFoldOptions {
+        self.normalization = normalization;
+        self
+    }
+
+    pub fn fold(&self, s: &str) -> String {
+        self.fold_chars(s).collect()
+    }
+
+    /// Return whether `a` and `b` fold to the same string.
+    pub fn matches(&self, a: &str, b: &str) -> bool {
+        iter_eq(self.fold_chars(a), self.fold_chars(b))
+    }
+
+    fn fold_chars<'a>(&self, s: &'a str) -> Box<dyn Iterator<Item = char> + 'a> {
+        let options = *self;
+        let fold = move |c| options.fold_char(c);
+        match self.normalization {
+            Normalization::None => Box::new(s.chars().flat_map(fold)),
+            Normalization::Canonical => Box::new(s.chars().nfd().flat_map(fold).nfd()),
+            Normalization::Compatibility => {
+                Box::new(s.chars().nfd().flat_map(fold).nfkd().flat_map(fold).nfkd())
+            }
+        }
+    }
+
+    fn fold_char(&self, c: char) -> CharCaseFold {
+        if self.turkic {
+            if let Some(folded) = search(TURKIC_CASE_FOLDING_TABLE, c) {
+                return CharCaseFold::new(folded);
+            }
+        }
+        if self.simple {
+            CharCaseFold::new([simple_fold(c), '\0', '\0'])
+        } else {
+            case_fold_char(c)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use super::*;
+
+    #[test]
+    fn test_options() {
+        let strings = [
+            "Straße", "STRASSE", "\u{c5}", "a\u{30a}", "\u{3392}", "İI", "ﬃ",
+        ];
+        let default = CaseFoldOptions::new();
+        let canonical = CaseFoldOptions::new().normalize(Normalization::Canonical);
+        let compatibility = CaseFoldOptions::new().normalize(Normalization::Compatibility);
+        let turkic = CaseFoldOptions::new().turkic(true);
+        let simple = CaseFoldOptions::new().simple();
+        for s in &strings {
+            assert_eq!(default.fold(s), default_case_fold_str(s));
+            assert_eq!(canonical.fold(s), canonical_case_fold_str(s));
+            assert_eq!(compatibility.fold(s), compatibility_case_fold_str(s));
+            assert_eq!(turkic.fold(s), turkic_case_fold_str(s));
+            assert_eq!(simple.fold(s), simple_case_fold_str(s));
+        }
+        assert_eq!(simple.full(), default);
+        assert!(default.matches("Straße", "STRASSE"));
+        assert!(!simple.matches("Straße", "STRASSE"));
+        assert!(canonical.matches("\u{c5}", "a\u{30a}"));
+        assert!(!default.matches("\u{c5}", "a\u{30a}"));
+        assert_eq!(simple.turkic(true).fold("IĞDIR İ"), "ığdır i");
+    }
+}
@@ -0,0 +1,467 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::iter::FusedIterator;
+use core::ops::Range;
+
+use super::{case_fold_char, CaseFoldIndices, Caseless};
+
+/// Return whether the default case folding of `prefix`
+/// is a prefix of the default case folding of `haystack`.
+///
+/// Because of expansions, this can be true even when `prefix`
+/// ends in the middle of the folding of a character of `haystack`:
+/// `caseless_starts_with("straße", "STRASS")` is true.
+pub fn caseless_starts_with(haystack: &str, prefix: &str) -> bool {
+    let mut haystack = haystack.chars().default_case_fold();
+    prefix
+        .chars()
+        .default_case_fold()
+        .all(|c| haystack.next() == Some(c))
+}
+
+/// Return whether the default case folding of `suffix`
+/// is a suffix of the default case folding of `haystack`.
+///
+/// This does not allocate: both strings are folded from the end.
+pub fn caseless_ends_with(haystack: &str, suffix: &str) -> bool {
+    let mut haystack = haystack.chars().default_case_fold().rev();
+    suffix
+        .chars()
+        .default_case_fold()
+        .rev()
+        .all(|c| haystack.next() == Some(c))
+}
+
+/// If `s` starts with a caseless match of `prefix`, return the rest of `s`.
+///
+/// The match is made of whole characters of `s`:
+/// `caseless_strip_prefix("straße", "STRASS")` is `Some("e")`,
+/// but `caseless_strip_prefix("straße", "STRAS")` is `None`.
+pub fn caseless_strip_prefix<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let mut prefix = prefix.chars().default_case_fold().peekable();
+    if prefix.peek().is_none() {
+        return Some(s);
+    }
+    for (i, c) in s.char_indices() {
+        for folded in case_fold_char(c) {
+            if prefix.next() != Some(folded) {
+                return None;
+            }
+        }
+        if prefix.peek().is_none() {
+            return Some(&s[i + c.len_utf8()..]);
+        }
+    }
+    None
+}
+
+/// If `s` ends with a caseless match of `suffix`, return the rest of `s`.
+///
+/// Like with `caseless_strip_prefix`, the match is made of whole characters of `s`.
+pub fn caseless_strip_suffix<'a>(s: &'a str, suffix: &str) -> Option<&'a str> {
+    let mut suffix = suffix.chars().default_case_fold().rev().peekable();
+    if suffix.peek().is_none() {
+        return Some(s);
+    }
+    for (i, c) in s.char_indices().rev() {
+        for folded in case_fold_char(c).rev() {
+            if suffix.next() != Some(folded) {
+                return None;
+            }
+        }
+        if suffix.peek().is_none() {
+            return Some(&s[..i]);
+        }
+    }
+    None
+}
+
+/// Return the byte offset in `haystack` of the first caseless match of `needle`.
+///
+/// See `caseless_find_range`.
+pub fn caseless_find(haystack: &str, needle: &str) -> Option<usize> {
+    caseless_find_range(haystack, needle).map(|range| range.start)
+}
+
+/// Return the byte range in `haystack` of the first caseless match of `needle`.
+///
+/// A match is a substring of `haystack` made of whole characters
+/// whose default case folding is equal to that of `needle`,
+/// so the range can be used to slice `haystack`.
+/// For example `"s"` does not match within `"ß"`, but `"ss"` does.
+pub fn caseless_find_range(haystack: &str, needle: &str) -> Option<Range<usize>> {
+    let needle: Vec<char> = needle.chars().default_case_fold().collect();
+    find_folded(haystack, &needle)
+}
+
+/// Return the byte ranges in `haystack` of all caseless matches of `needle`.
+///
+/// Each match is found as with `caseless_find_range`, scanning left to right.
+/// Scanning resumes after the end of each match, so matches do not overlap.
+pub fn caseless_find_iter<'a>(haystack: &'a str, needle: &str) -> CaselessFindIter<'a> {
+    CaselessFindIter::new(haystack, needle)
+}
+
+/// Replace all caseless matches of `from` in `haystack` with `to`.
+///
+/// Matches are found as with `caseless_find_iter`.
+/// Text outside of matches is copied unchanged, keeping its original case.
+pub fn caseless_replace(haystack: &str, from: &str, to: &str) -> String {
+    let mut result = String::with_capacity(haystack.len());
+    let mut last_end = 0;
+    for range in caseless_find_iter(haystack, from) {
+        result.push_str(&haystack[last_end..range.start]);
+        result.push_str(to);
+        last_end = range.end;
+    }
+    result.push_str(&haystack[last_end..]);
+    result
+}
+
+/// Return the slices of `haystack` that caseless-match `needle`,
+/// like `str::matches`.
+///
+/// Matches are found as with `caseless_find_iter`. The slices are of the
+/// original text, so they can differ from `needle` in case and in length:
+/// `caseless_matches("Straße", "SS")` yields `"ß"`.
+pub fn caseless_matches<'a>(haystack: &'a str, needle: &str) -> CaselessMatches<'a> {
+    CaselessMatches {
+        matches: caseless_find_iter(haystack, needle),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CaselessMatches<'a> {
+    matches: CaselessFindIter<'a>,
+}
+
+impl<'a> Iterator for CaselessMatches<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let haystack = self.matches.haystack;
+        self.matches.next().map(|range| &haystack[range])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.matches.size_hint()
+    }
+}
+
+impl<'a> FusedIterator for CaselessMatches<'a> {}
+
+/// Split `s` by caseless matches of `delimiter`,
+/// yielding slices of the original string.
+///
+/// Matches are found as with `caseless_replace`.
+pub fn caseless_split<'a>(s: &'a str, delimiter: &str) -> CaselessSplit<'a> {
+    CaselessSplit {
+        matches: caseless_find_iter(s, delimiter),
+        last_end: Some(0),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CaselessSplit<'a> {
+    matches: CaselessFindIter<'a>,
+    last_end: Option<usize>,
+}
+
+impl<'a> Iterator for CaselessSplit<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let start = self.last_end?;
+        let haystack = self.matches.haystack;
+        match self.matches.next() {
+            Some(range) => {
+                self.last_end = Some(range.end);
+                Some(&haystack[start..range.start])
+            }
+            None => {
+                self.last_end = None;
+                Some(&haystack[start..])
+            }
+        }
+    }
+}
+
+/// Byte ranges of non-overlapping caseless matches of a needle, left to right.
+///
+/// See `caseless_find_iter`.
+#[derive(Clone, Debug)]
+pub struct CaselessFindIter<'a> {
+    haystack: &'a str,
+    needle: Vec<char>,
+    position: Option<usize>,
+}
+
+impl<'a> CaselessFindIter<'a> {
+    fn new(haystack: &'a str, needle: &str) -> CaselessFindIter<'a> {
+        CaselessFindIter {
+            haystack,
+            needle: needle.chars().default_case_fold().collect(),
+            position: Some(0),
+        }
+    }
+}
+
+impl<'a> Iterator for CaselessFindIter<'a> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Range<usize>> {
+        let position = self.position?;
+        let rest = &self.haystack[position..];
+        if self.needle.is_empty() {
+            // Like with str::matches, an empty needle matches at every char boundary.
+            self.position = rest.chars().next().map(|c| position + c.len_utf8());
+            return Some(position..position);
+        }
+        match find_folded(rest, &self.needle) {
+            Some(range) => {
+                self.position = Some(position + range.end);
+                Some(position + range.start..position + range.end)
+            }
+            None => {
+                self.position = None;
+                None
+            }
+        }
+    }
+}
+
+impl<'a> FusedIterator for CaselessFindIter<'a> {}
+
+/// Return the byte offsets in `a` and `b` of the characters
+/// where their default case foldings first differ,
+/// or `None` if they caseless-match.
+///
+/// Offsets are at char boundaries. When a string is a caseless prefix
+/// of the other, its offset is its length.
+/// When the difference is in the middle of an expansion,
+/// the offset is that of the expanded character.
+pub fn caseless_mismatch(a: &str, b: &str) -> Option<(usize, usize)> {
+    let mut a_folded = CaseFoldIndices::new(a);
+    let mut b_folded = CaseFoldIndices::new(b);
+    loop {
+        match (a_folded.next(), b_folded.next()) {
+            (None, None) => return None,
+            (Some((i, x)), Some((j, y))) => {
+                if x != y {
+                    return Some((i, j));
+                }
+            }
+            (Some((i, _)), None) => return Some((i, b.len())),
+            (None, Some((j, _))) => return Some((a.len(), j)),
+        }
+    }
+}
+
+/// Return the number of leading characters that the default case foldings
+/// of `a` and `b` have in common.
+pub fn caseless_common_prefix_len(a: &str, b: &str) -> usize {
+    a.chars()
+        .default_case_fold()
+        .zip(b.chars().default_case_fold())
+        .take_while(|&(x, y)| x == y)
+        .count()
+}
+
+/// Return whether the first `n` characters of the default case foldings
+/// of `a` and `b` are equal. If a folding has fewer than `n` characters,
+/// the other must be equal to it.
+///
+/// Only the first `n` folded characters are computed.
+pub fn caseless_match_prefix_n(a: &str, b: &str, n: usize) -> bool {
+    a.chars()
+        .default_case_fold()
+        .take(n)
+        .eq(b.chars().default_case_fold().take(n))
+}
+
+/// Return the longest prefix of `a` made of whole characters
+/// whose default case folding is a prefix of that of `b`.
+///
+/// When the common folded prefix ends in the middle of the expansion
+/// of a character of `a`, that character is not included:
+/// the common prefix of `"straße"` and `"STRASSER"` is `"straße"`,
+/// but that of `"straße"` and `"STRASX"` is `"stra"`.
+pub fn caseless_common_prefix<'a>(a: &'a str, b: &str) -> &'a str {
+    let mut b = b.chars().default_case_fold();
+    for (i, c) in a.char_indices() {
+        for folded in case_fold_char(c) {
+            if b.next() != Some(folded) {
+                return &a[..i];
+            }
+        }
+    }
+    a
+}
+
+/// Find the first match of an already-folded needle.
+fn find_folded(haystack: &str, needle: &[char]) -> Option<Range<usize>> {
+    if needle.is_empty() {
+        return Some(0..0);
+    }
+    haystack
+        .char_indices()
+        .find_map(|(start, _)| match_len(&haystack[start..], needle).map(|len| start..start + len))
+}
+
+/// If the folding of a prefix of `haystack` made of whole characters is `needle`,
+/// return the byte length of that prefix.
+fn match_len(haystack: &str, needle: &[char]) -> Option<usize> {
+    let mut needle = needle.iter();
+    for (i, c) in haystack.char_indices() {
+        for folded in case_fold_char(c) {
+            if needle.next() != Some(&folded) {
+                return None;
+            }
+        }
+        if needle.len() == 0 {
+            return Some(i + c.len_utf8());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_starts_with() {
+        assert!(caseless_starts_with("Bearer token", "bearer "));
+        assert!(caseless_starts_with("straße", "STRASS"));
+        assert!(caseless_starts_with("straße", "STRASSE"));
+        assert!(caseless_starts_with("abc", ""));
+        assert!(!caseless_starts_with("straße", "STRASSEN"));
+        assert!(!caseless_starts_with("", "a"));
+    }
+
+    #[test]
+    fn test_ends_with() {
+        assert!(caseless_ends_with("straße", "ASSE"));
+        assert!(caseless_ends_with("straße", "SSE"));
+        assert!(caseless_ends_with("STRASSE", "ße"));
+        assert!(caseless_ends_with("spiﬃ", "FI"));
+        assert!(caseless_ends_with("abc", ""));
+        assert!(!caseless_ends_with("straße", "ASE"));
+        assert!(!caseless_ends_with("e", "se"));
+    }
+
+    #[test]
+    fn test_find() {
+        assert_eq!(caseless_find("Hello World", "WORLD"), Some(6));
+        assert_eq!(caseless_find("Hello World", "planet"), None);
+        assert_eq!(caseless_find("Hello", ""), Some(0));
+        assert_eq!(caseless_find_range("Die Straße", "STRASSE"), Some(4..11));
+        assert_eq!(caseless_find_range("Die STRASSE", "straße"), Some(4..11));
+        assert_eq!(caseless_find_range("ein ﬃ", "FFI"), Some(4..7));
+        assert_eq!(caseless_find_range("aßa", "s"), None);
+        assert_eq!(caseless_find_range("aßa", "ssa"), Some(1..4));
+        assert_eq!(caseless_find_range("aßa", "sa"), None);
+    }
+
+    #[test]
+    fn test_find_iter() {
+        let find = |h, n| caseless_find_iter(h, n).collect::<Vec<_>>();
+        let haystack = "Straße, STRASSE, strasse";
+        let ranges = find(haystack, "STRASSE");
+        assert_eq!(ranges, [0..7, 9..16, 18..25]);
+        assert_eq!(&haystack[ranges[0].clone()], "Straße");
+        assert_eq!(find("aaaa", "AA"), [0..2, 2..4]);
+        assert_eq!(find("ﬃ ffi", "FFI"), [0..3, 4..7]);
+        assert_eq!(find("ﬃ", "ff"), []);
+        assert_eq!(find("ab", "").len(), 3);
+    }
+
+    #[test]
+    fn test_replace() {
+        assert_eq!(
+            caseless_replace("STRASSE road", "strasse", "Main"),
+            "Main road"
+        );
+        assert_eq!(
+            caseless_replace("Straße and STRASSE", "strasse", "x"),
+            "x and x"
+        );
+        assert_eq!(caseless_replace("aAa", "aa", "b"), "ba");
+        assert_eq!(caseless_replace("Hello", "xyz", "b"), "Hello");
+        assert_eq!(caseless_replace("ab", "", "-"), "ab".replace("", "-"));
+    }
+
+    #[test]
+    fn test_split() {
+        let split = |s, d| caseless_split(s, d).collect::<Vec<_>>();
+        assert_eq!(split("a AND b and c", " and "), ["a", "b", "c"]);
+        assert_eq!(split("Straße", "SS"), ["Stra", "e"]);
+        assert_eq!(split("abc", "x"), ["abc"]);
+        assert_eq!(split("", "x"), [""]);
+        assert_eq!(split("xAx", "a"), ["x", "x"]);
+        assert_eq!(split("ab", ""), "ab".split("").collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_matches() {
+        let matches = |h, n| caseless_matches(h, n).collect::<Vec<_>>();
+        assert_eq!(
+            matches("Straße STRASSE strasse", "strasse"),
+            ["Straße", "STRASSE", "strasse"]
+        );
+        assert_eq!(matches("Straße", "SS"), ["ß"]);
+        assert_eq!(matches("ﬃ FFI", "ffi"), ["ﬃ", "FFI"]);
+        assert_eq!(matches("abc", "x"), Vec::<&str>::new());
+        assert_eq!(matches("ab", ""), "ab".matches("").collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_match_prefix_n() {
+        assert!(caseless_match_prefix_n("Straße", "STRASSE", 7));
+        assert!(caseless_match_prefix_n("Straße", "STRASSER", 5));
+        assert!(!caseless_match_prefix_n("Straße", "STRASSER", 8));
+        assert!(caseless_match_prefix_n("ab", "AB", 10));
+        assert!(!caseless_match_prefix_n("ab", "ABC", 10));
+        assert!(caseless_match_prefix_n("x", "y", 0));
+    }
+
+    #[test]
+    fn test_mismatch() {
+        assert_eq!(caseless_mismatch("Straße", "STRASSE"), None);
+        assert_eq!(caseless_mismatch("timeout", "TimeOut"), None);
+        assert_eq!(caseless_mismatch("timeout", "TimeIn"), Some((4, 4)));
+        assert_eq!(caseless_mismatch("ßa", "sta"), Some((0, 1)));
+        assert_eq!(caseless_mismatch("Größe", "GROSSE"), Some((2, 2)));
+        assert_eq!(caseless_mismatch("abc", "ABCD"), Some((3, 3)));
+        assert_eq!(caseless_mismatch("aß", "as"), Some((1, 2)));
+    }
+
+    #[test]
+    fn test_common_prefix() {
+        assert_eq!(caseless_common_prefix_len("Straße", "STRASSER"), 7);
+        assert_eq!(caseless_common_prefix_len("Straße", "STRASX"), 5);
+        assert_eq!(caseless_common_prefix_len("abc", "xyz"), 0);
+        assert_eq!(caseless_common_prefix("Straße", "STRASSER"), "Straße");
+        assert_eq!(caseless_common_prefix("Straße", "STRASX"), "Stra");
+        assert_eq!(caseless_common_prefix("Config", "CONFIGURE"), "Config");
+        assert_eq!(caseless_common_prefix("abc", ""), "");
+    }
+
+    #[test]
+    fn test_strip() {
+        assert_eq!(caseless_strip_prefix("GET /index", "get "), Some("/index"));
+        assert_eq!(caseless_strip_prefix("straße 5", "STRASS"), Some("e 5"));
+        assert_eq!(caseless_strip_prefix("straße 5", "STRASSE "), Some("5"));
+        assert_eq!(caseless_strip_prefix("straße", "STRAS"), None);
+        assert_eq!(caseless_strip_prefix("abc", ""), Some("abc"));
+        assert_eq!(caseless_strip_prefix("ab", "abc"), None);
+        assert_eq!(
+            caseless_strip_suffix("Main STRASSE", " straße"),
+            Some("Main")
+        );
+        assert_eq!(caseless_strip_suffix("Maße", "SSE"), Some("Ma"));
+        assert_eq!(caseless_strip_suffix("Maße", "SE"), None);
+        assert_eq!(caseless_strip_suffix("abc", ""), Some("abc"));
+        assert_eq!(caseless_strip_suffix("bc", "abc"), None);
+    }
+}
@@ -0,0 +1,89 @@
+//! Writing case folded text without building a `String` first.
+
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+
+use super::Caseless;
+
+/// A `fmt::Write` adapter that writes the default case folding
+/// of everything written to it.
+///
+/// `write!(CaseFoldWriter::new(&mut out), "{}", value)` appends
+/// the case folding of `value`'s `Display` output to `out`.
+#[derive(Clone, Debug, Default)]
+pub struct CaseFoldWriter<W> {
+    inner: W,
+}
+
+impl<W: fmt::Write> CaseFoldWriter<W> {
+    pub fn new(inner: W) -> Self {
+        CaseFoldWriter { inner }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: fmt::Write> fmt::Write for CaseFoldWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        s.chars()
+            .default_case_fold()
+            .try_for_each(|c| self.inner.write_char(c))
+    }
+}
+
+/// Write the default case folding of `s` to `w` as UTF-8,
+/// in chunks encoded in a buffer on the stack.
+#[cfg(feature = "std")]
+pub fn write_case_folded<W: io::Write + ?Sized>(s: &str, w: &mut W) -> io::Result<()> {
+    let mut buf = [0; 256];
+    let mut len = 0;
+    for c in s.chars().default_case_fold() {
+        if buf.len() - len < c.len_utf8() {
+            w.write_all(&buf[..len])?;
+            len = 0;
+        }
+        len += c.encode_utf8(&mut buf[len..]).len();
+    }
+    w.write_all(&buf[..len])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::default_case_fold_str;
+    use super::*;
+    use alloc::string::String;
+    use core::fmt::Write;
+
+    #[test]
+    fn test_case_fold_writer() {
+        let mut out = String::from("> ");
+        let name = "Straße";
+        write!(CaseFoldWriter::new(&mut out), "{} {}", name, 42).unwrap();
+        assert_eq!(out, "> strasse 42");
+        let mut writer = CaseFoldWriter::new(String::new());
+        writer.write_char('ﬃ').unwrap();
+        writer.write_str("ΣΑΣ").unwrap();
+        assert_eq!(writer.get_ref(), "ffiσασ");
+        assert_eq!(writer.into_inner(), "ffiσασ");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_write_case_folded() {
+        let mut out = Vec::new();
+        write_case_folded("Straße ﬃ", &mut out).unwrap();
+        assert_eq!(out, "strasse ffi".as_bytes());
+
+        let long = "ΑΒΓ ß€𝐀".repeat(100);
+        let mut out = Vec::new();
+        write_case_folded(&long, &mut out).unwrap();
+        assert_eq!(out, default_case_fold_str(&long).as_bytes());
+    }
+}